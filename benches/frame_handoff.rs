@@ -0,0 +1,53 @@
+//! Handing a decoded 1080p frame from the streaming thread to the view: copied
+//! into an image as the view used to, or shared through the frame slot.
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use druid::{piet::ImageFormat, ImageBuf};
+use druid_video::{
+	player::{FrameData, VideoFrame},
+	present::FrameSlot,
+};
+use gstreamer as gst;
+
+const WIDTH: usize = 1920;
+const HEIGHT: usize = 1080;
+
+fn handoff(c: &mut Criterion) {
+	gst::init().unwrap();
+	let buffer = gst::Buffer::from_mut_slice(vec![0x80_u8; WIDTH * HEIGHT * 4]);
+
+	let mut group = c.benchmark_group("frame_handoff");
+	group.throughput(Throughput::Bytes((WIDTH * HEIGHT * 4) as u64));
+	group.bench_function("copy", |b| {
+		b.iter(|| {
+			let map = buffer.map_readable().unwrap();
+			let image = ImageBuf::from_raw(
+				map.as_slice().to_owned(),
+				ImageFormat::RgbaSeparate,
+				WIDTH,
+				HEIGHT,
+			);
+			black_box(image.to_owned())
+		})
+	});
+	let slot = FrameSlot::default();
+	group.bench_function("shared", |b| {
+		b.iter(|| {
+			let data = buffer.clone().into_mapped_buffer_readable().unwrap();
+			slot.put(VideoFrame {
+				data: FrameData::from(data),
+				width: WIDTH as u32,
+				height: HEIGHT as u32,
+				pixel_aspect_ratio: (1, 1),
+				pts: None,
+				running_time: None,
+				clock: None,
+				number: 0,
+			});
+			black_box(slot.take())
+		})
+	});
+	group.finish();
+}
+
+criterion_group!(benches, handoff);
+criterion_main!(benches);
@@ -0,0 +1,67 @@
+//! Play a media without a window, counting the frames and printing the
+//! events of the player.
+//!
+//! ```sh
+//! cargo run --example headless -- https://media.local/stream.m3u8
+//! ```
+use std::{
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		mpsc, Arc, Mutex,
+	},
+	time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use druid_video::player::{
+	Disconnected, PlayerEvent, PlayerOptions, PlayerSink, VideoFrame, VideoPlayer,
+};
+
+/// Counts the frames and passes the events on to the main thread.
+struct Counter {
+	frames: AtomicU64,
+	events: Mutex<mpsc::Sender<PlayerEvent>>,
+}
+
+impl PlayerSink for Counter {
+	fn frame(&self, _frame: VideoFrame) -> Result<(), Disconnected> {
+		self.frames.fetch_add(1, Ordering::Relaxed);
+		Ok(())
+	}
+
+	fn event(&self, event: PlayerEvent) -> Result<(), Disconnected> {
+		self.events.lock().unwrap().send(event).map_err(|_| Disconnected)
+	}
+}
+
+fn main() -> Result<()> {
+	let item = std::env::args().nth(1).ok_or_else(|| anyhow!("usage: headless <uri or path>"))?;
+	let uri = match url::Url::parse(&item) {
+		Ok(uri) => uri,
+		Err(_) => url::Url::from_file_path(std::fs::canonicalize(&item)?)
+			.map_err(|_| anyhow!("{} is no uri or path", item))?,
+	};
+
+	let (sender, events) = mpsc::channel();
+	let counter = Arc::new(Counter { frames: AtomicU64::new(0), events: Mutex::new(sender) });
+	let mut player =
+		VideoPlayer::with_options(&uri, false, PlayerOptions::default(), counter.clone())?;
+	println!(
+		"{}x{} at {:.2} fps, {:?}",
+		player.width, player.height, player.framerate, player.duration
+	);
+	player.set_paused(false);
+
+	loop {
+		match events.recv_timeout(Duration::from_secs(1)) {
+			Ok(PlayerEvent::Ended) => break,
+			Ok(PlayerEvent::Error(error)) => return Err(anyhow!(error)),
+			Ok(event) => println!("{:?}", event),
+			Err(mpsc::RecvTimeoutError::Timeout) => {}
+			Err(mpsc::RecvTimeoutError::Disconnected) => break,
+		}
+		println!("{:?}: {} frames", player.position(), counter.frames.load(Ordering::Relaxed));
+	}
+	println!("{} frames", counter.frames.load(Ordering::Relaxed));
+	Ok(())
+}
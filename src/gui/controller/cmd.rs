@@ -1,6 +1,10 @@
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
-use druid::{ImageBuf, Selector};
+use druid::{Point, Selector};
+
+use crate::gui::data::video::{
+	AdaptiveStats, BufferingInfo, CaptureDevice, CaptureSelection, Variant,
+};
 
 // Playback state
 
@@ -10,7 +14,17 @@ pub const PLAYBACK_DURATION: Selector<u64> = Selector::new("app.playback-progres
 pub const PLAYBACK_PAUSING: Selector = Selector::new("app.playback-pausing");
 pub const PLAYBACK_RESUMING: Selector = Selector::new("app.playback-resuming");
 pub const PLAYBACK_BLOCKED: Selector = Selector::new("app.playback-blocked");
+pub const PLAYBACK_LIVE: Selector<bool> = Selector::new("app.playback-live");
+pub const PLAYBACK_VARIANTS: Selector<Vec<Variant>> = Selector::new("app.playback-variants");
+pub const PLAYBACK_VARIANT_CHANGED: Selector<usize> = Selector::new("app.playback-variant-changed");
+pub const PLAYBACK_ADAPTIVE_STATS: Selector<AdaptiveStats> =
+	Selector::new("app.playback-adaptive-stats");
+pub const PLAYBACK_BUFFERING: Selector<BufferingInfo> = Selector::new("app.playback-buffering");
 pub const PLAYBACK_STOPPED: Selector = Selector::new("app.playback-stopped");
+pub const PLAYBACK_RECONNECTING: Selector<u32> = Selector::new("app.playback-reconnecting");
+pub const PLAYBACK_RECONNECTED: Selector = Selector::new("app.playback-reconnected");
+pub const PLAYBACK_ERROR: Selector<String> = Selector::new("app.playback-error");
+pub const PLAYBACK_ENDED: Selector = Selector::new("app.playback-ended");
 
 // Playback control
 
@@ -23,7 +37,23 @@ pub const PLAY_STOP: Selector = Selector::new("app.play-stop");
 pub const PLAY_SEEK: Selector<u64> = Selector::new("app.play-seek");
 pub const PLAY_VOLUME: Selector<f64> = Selector::new("app.play-volume");
 pub const PLAY_RATE: Selector<f64> = Selector::new("app.play-rate");
+pub const PLAY_LIVE_EDGE: Selector = Selector::new("app.play-live-edge");
+
+// Recording
+
+pub const RECORD_START: Selector = Selector::new("app.record-start");
+pub const RECORD_STOP: Selector = Selector::new("app.record-stop");
+
+// Capture
+
+pub const CAPTURE_DEVICES: Selector<Arc<Vec<CaptureDevice>>> = Selector::new("app.capture-devices");
+pub const CAPTURE_SELECTED: Selector<CaptureSelection> = Selector::new("app.capture-selected");
 
 //Video Frame
 
-pub const VIDEO_FRAME: Selector<ImageBuf> = Selector::new("app.video-frame");
+pub const VIDEO_FRAME: Selector = Selector::new("app.video-frame");
+
+// Inspector
+
+/// Pin the pixel under this point of the view to the samples.
+pub const INSPECT_PIN: Selector<Point> = Selector::new("app.inspect-pin");
@@ -1,87 +1,82 @@
-use druid::{widget::Image, Data, ExtEventError, ExtEventSink, Lens};
-use gst::prelude::*;
-use gstreamer as gst;
-use gstreamer::query::Uri;
-use thiserror::Error;
-
-#[derive(Debug, Error)]
-pub enum VideoError {
-	#[error("{0}")]
-	Glib(#[from] glib::Error),
-	#[error("{0}")]
-	Bool(#[from] glib::BoolError),
-	#[error("failed to get the gstreamer bus")]
-	Bus,
-	#[error("{0}")]
-	StateChange(#[from] gst::StateChangeError),
-	#[error("failed to cast gstreamer element")]
-	Cast,
-	#[error("{0}")]
-	Io(#[from] std::io::Error),
-	#[error("invalid URI")]
-	Uri,
-	#[error("failed to get media capabilities")]
-	Caps,
-	#[error("failed to query media duration or position")]
-	Duration,
-	#[error("failed to sync with playback")]
-	Sync,
-	#[error("{0}")]
-	ExtEventError(#[from] ExtEventError),
-
-	#[error("{0}")]
-	PadLinkError(#[from] gst::PadLinkError),
-	#[error("{0}")]
-	FlowError(#[from] gstreamer::FlowError),
-	#[error("{0}")]
-	Other(#[from] anyhow::Error),
-}
+use std::{fmt, sync::Arc, time::Duration};
+
+use druid::{
+	piet::{ImageFormat, PietImage},
+	Data, ExtEventSink, ImageBuf, Lens, Point, TimerToken, Vec2,
+};
+
+use crate::media::{
+	actor::PlayerHandle,
+	backend::MediaBackend,
+	player::{SharedSink, VideoFrame},
+	present::FrameScheduler,
+};
+pub use crate::media::{
+	devices::{CaptureDevice, CaptureMode, CaptureSelection},
+	error::VideoError,
+	frame_source::FrameSourcePlayer,
+	options::{
+		AudioFormat, BufferingOptions, FrameSourceConfig, NetworkOptions, PlayerOptions,
+		RecordingOptions, RetryPolicy, SourceOptions, Transport,
+	},
+	player::VideoPlayer,
+	present::PresentStats,
+	tap::{AudioSamples, DropPolicy, FrameRef, TapId, TapOptions},
+	testsrc::{TestPattern, TestSourceConfig},
+	types::{
+		AdaptiveStats, BufferingInfo, Colorimetry, Orientation, Position, Quality, RecordStatus,
+		Variant, YuvMatrix,
+	},
+};
 
-/// `CameraView` widget
+/// Creates the player of a [`VideoView`] instead of playing the current item.
+/// It runs on the player thread.
+pub type PlayerFactory =
+	Box<dyn FnOnce(&PlayerOptions, SharedSink) -> Result<Box<dyn MediaBackend>, VideoError> + Send>;
+
+/// `VideoView` widget
 pub struct VideoView {
-	pub image: Image,
-	pub player: Option<VideoPlayer>,
+	/// Frame shown, with its image once it was painted.
+	pub(crate) shown: Option<(Arc<VideoFrame>, Option<PietImage>)>,
+	pub player: Option<PlayerHandle>,
 	pub event: Option<ExtEventSink>,
+	pub options: PlayerOptions,
+	pub factory: Option<PlayerFactory>,
+	/// Capture devices the player is playing, if it is capturing.
+	pub capture: Option<CaptureSelection>,
+	/// Timer updating the recording and timeshift status.
+	pub timer: TimerToken,
+	/// Playback of the timeshift buffer while behind the live edge.
+	pub timeshift: Option<Timeshifted>,
+	/// Frames received, waiting for their running time to be shown.
+	pub scheduler: FrameScheduler,
+	/// Size in pixels the players were told to scale the frames down to,
+	/// `None` keeps the size of the video.
+	pub(crate) render_size: Option<(u32, u32)>,
+	/// Where the pointer is over the view, for the loupe.
+	pub(crate) pointer: Option<Point>,
+	/// Rotation and mirroring the players were told about.
+	pub(crate) orientation: Orientation,
 	// pub state: VideoViewState,
 }
 
-/// Position in the media.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub enum Position {
-	/// Position based on time.
-	///
-	/// Not the most accurate format for videos.
-	Time(std::time::Duration),
-	/// Position based on nth frame.
-	Frame(u64),
-}
-
-impl From<Position> for gst::GenericFormattedValue {
-	fn from(pos: Position) -> Self {
-		match pos {
-			Position::Time(t) => gst::ClockTime::from_nseconds(t.as_nanos() as _).into(),
-			Position::Frame(f) => gst::format::Default(f).into(),
-		}
-	}
-}
-
-impl From<std::time::Duration> for Position {
-	fn from(t: std::time::Duration) -> Self {
-		Position::Time(t)
-	}
+/// Position of a [`VideoView`] in the timeshift buffer of a live stream.
+///
+/// The live player keeps running hidden, so the buffer keeps growing.
+pub struct Timeshifted {
+	/// Player of the buffered files, `None` while paused at `start`.
+	pub player: Option<PlayerHandle>,
+	/// Time in the buffer the player starts at.
+	pub start: Duration,
 }
 
-impl From<u64> for Position {
-	fn from(f: u64) -> Self {
-		Position::Frame(f)
-	}
-}
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Data)]
 pub enum VideoPlayerState {
 	Playing,
 	Paused,
 	Stopped,
 }
+
 #[derive(Data, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
 pub enum VideoRate {
 	D2,
@@ -91,6 +86,7 @@ pub enum VideoRate {
 	I5,
 	I20,
 }
+
 #[derive(Clone, Debug, Data, Lens)]
 pub struct VideoViewState {
 	pub state: VideoPlayerState,
@@ -102,21 +98,176 @@ pub struct VideoViewState {
 	pub seeking_enabled: bool,
 	pub rate: f64,
 	pub rate_set: bool,
+	/// Playback is blocked until the network buffer refills.
+	pub buffering: bool,
+	/// Fill level of the network buffer, `0..=100`.
+	pub buffer_percentage: i32,
+	/// Downloaded ranges as `(start, stop)` fractions of the media.
+	pub buffered_ranges: Arc<Vec<(f64, f64)>>,
+	/// The current item is a live source. Can be set up front to force live
+	/// mode, otherwise it is detected when the player starts.
+	pub live: bool,
+	/// Variants of an adaptive (HLS/DASH) stream, lowest bitrate first.
+	pub variants: Arc<Vec<Variant>>,
+	/// Selected quality of an adaptive stream.
+	pub quality: Quality,
+	/// Index into `variants` of the variant being downloaded.
+	pub current_variant: Option<usize>,
+	/// Estimated download bandwidth in bits per second.
+	pub bandwidth: u64,
+	/// Attempt of reconnecting to a dropped source.
+	pub reconnect_attempt: Option<u32>,
+	/// Error which stopped playback.
+	pub error: Option<String>,
+	/// Capture devices to pick from, empty unless capturing.
+	pub devices: Arc<Vec<CaptureDevice>>,
+	/// Devices being captured, changing it restarts the capture.
+	pub capture: CaptureSelection,
+	/// Recording of the live stream in progress.
+	pub recording: Option<RecordStatus>,
+	/// Timeshift buffer of the live stream, if there is one.
+	pub timeshift: Option<TimeshiftStatus>,
+	/// Frames the view dropped or showed late.
+	pub presentation: PresentStats,
+	/// How the frame is placed in the view.
+	pub display_mode: DisplayMode,
+	/// Zoom and pan of the frame.
+	pub view_transform: ViewTransform,
+	/// Whether a magnifier follows the pointer.
+	pub loupe: bool,
+	/// Whether the pixel under the pointer is inspected.
+	pub inspector: bool,
+	/// Pixels pinned in the inspector.
+	pub samples: Arc<Vec<PixelSample>>,
+	/// Rotation and mirroring picked by the user.
+	pub orientation: Orientation,
+}
+
+/// How much of a live stream is buffered to rewind in, in seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Data)]
+pub struct TimeshiftStatus {
+	pub buffered: u64,
+	/// Distance of what is shown from the live edge.
+	pub behind: u64,
+}
+
+/// How a frame is placed in the view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Data)]
+pub enum DisplayMode {
+	/// The whole frame at its display aspect ratio, letterboxed.
+	Fit,
+	/// The view covered at the display aspect ratio, cropping what doesn't
+	/// fit.
+	Fill,
+	/// The frame stretched to the view.
+	Stretch,
+	/// One pixel of the video per pixel of the screen.
+	Native,
+	/// The whole frame as if its display aspect ratio was `width:height`,
+	/// letterboxed.
+	Ratio(u32, u32),
 }
 
-/// Video player which handles multimedia playback.
-pub struct VideoPlayer {
-	pub bus: gst::Bus,
-	pub pipeline: gst::Element,
-
-	pub volume: f64,
-	pub width: i32,
-	pub height: i32,
-	pub framerate: f64,
-	pub duration: std::time::Duration,
-	pub paused: bool,
-	pub muted: bool,
-	pub looping: bool,
-	pub is_eos: bool,
-	pub restart_stream: bool,
+impl DisplayMode {
+	/// The modes in the order they are cycled through.
+	pub const ALL: [DisplayMode; 7] = [
+		DisplayMode::Fit,
+		DisplayMode::Fill,
+		DisplayMode::Stretch,
+		DisplayMode::Native,
+		DisplayMode::Ratio(4, 3),
+		DisplayMode::Ratio(16, 9),
+		DisplayMode::Ratio(239, 100),
+	];
+
+	/// The mode after this one.
+	pub fn next(self) -> Self {
+		let index = Self::ALL.iter().position(|mode| *mode == self).map_or(0, |index| index + 1);
+		Self::ALL[index % Self::ALL.len()]
+	}
+}
+
+impl Default for DisplayMode {
+	fn default() -> Self {
+		Self::Fit
+	}
+}
+
+impl fmt::Display for DisplayMode {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match *self {
+			DisplayMode::Fit => f.write_str("Fit"),
+			DisplayMode::Fill => f.write_str("Fill"),
+			DisplayMode::Stretch => f.write_str("Stretch"),
+			DisplayMode::Native => f.write_str("Native"),
+			DisplayMode::Ratio(width, 100) => write!(f, "{:.2}:1", width as f64 / 100.0),
+			DisplayMode::Ratio(width, height) => write!(f, "{}:{}", width, height),
+		}
+	}
+}
+
+/// Zoom and pan of the frame, on top of where the display mode puts it.
+#[derive(Debug, Clone, Copy, PartialEq, Data)]
+pub struct ViewTransform {
+	/// Magnification, 1 shows the frame as placed by the display mode.
+	pub zoom: f64,
+	/// Offset of the magnified view, in view coordinates.
+	pub pan: Vec2,
+}
+
+impl Default for ViewTransform {
+	fn default() -> Self {
+		Self { zoom: 1.0, pan: Vec2::ZERO }
+	}
+}
+
+/// A pixel of a frame, as picked in the inspector.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Data)]
+pub struct PixelSample {
+	/// Column in the frame.
+	pub x: u32,
+	/// Row in the frame.
+	pub y: u32,
+	/// Red, green, blue and alpha.
+	pub rgba: [u8; 4],
+	/// How the frame was coded as Y'CbCr.
+	pub colorimetry: Colorimetry,
+}
+
+/// The types of the player core know nothing of druid, the state holding
+/// them compares them by value.
+macro_rules! data_by_value {
+	($($ty:ty),*) => {
+		$(impl Data for $ty {
+			fn same(&self, other: &Self) -> bool {
+				self == other
+			}
+		})*
+	};
+}
+
+data_by_value!(
+	CaptureMode,
+	CaptureSelection,
+	Colorimetry,
+	Orientation,
+	PresentStats,
+	Quality,
+	RecordStatus
+);
+
+impl FrameSourcePlayer {
+	/// Push a druid image to be shown at `pts`, converting it to RGBA first.
+	pub fn push_frame(&self, frame: &ImageBuf, pts: Duration) -> Result<(), VideoError> {
+		let pixels = frame.raw_pixels();
+		let data = match frame.format() {
+			ImageFormat::RgbaSeparate | ImageFormat::RgbaPremul => pixels.to_vec(),
+			ImageFormat::Rgb => {
+				pixels.chunks_exact(3).flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255]).collect()
+			}
+			ImageFormat::Grayscale => pixels.iter().flat_map(|&l| [l, l, l, 255]).collect(),
+			_ => return Err(VideoError::Caps),
+		};
+		self.push_rgba(data, pts)
+	}
 }
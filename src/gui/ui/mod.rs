@@ -2,42 +2,80 @@ mod playback;
 
 use druid::{
 	theme,
-	widget::{Align, Axis, CrossAxisAlignment, Flex, SizedBox, Tabs, TabsEdge},
+	widget::{
+		Align, Axis, CrossAxisAlignment, Either, Flex, Label, SizedBox, Spinner, Tabs, TabsEdge,
+		ZStack,
+	},
 	Color, Data, ExtEventSink, Lens, UnitPoint, Widget, WidgetExt,
 };
 
 use crate::gui::{
-	data::{video, AppState},
+	data::{
+		video::{self, PlayerOptions, VideoViewState},
+		AppState,
+	},
 	widgets::{
+		cam_picker::cam_picker,
+		empty::Empty,
 		theme::{self as CustomTheme, ThemeScope},
 		video::VideoViewController,
 	},
 };
 
-/// Build the root UI widget.
-pub fn root_widget() -> impl Widget<AppState> {
+/// Build the root UI widget, players are created with `options`.
+pub fn root_widget(options: PlayerOptions) -> impl Widget<AppState> {
 	let layout = Flex::column()
 		.cross_axis_alignment(CrossAxisAlignment::Start)
 		.with_flex_child(
-			video::VideoView::new()
-				.expand()
-				.controller(VideoViewController {})
-				.lens(AppState::video),
+			ZStack::new(
+				video::VideoView::new()
+					.with_options(options)
+					.expand()
+					.controller(VideoViewController::default()),
+			)
+			.with_centered_child(Either::new(
+				|video: &VideoViewState, _| video.buffering,
+				Spinner::new().fix_size(CustomTheme::grid(4.0), CustomTheme::grid(4.0)),
+				Empty,
+			))
+			.with_centered_child(Either::new(
+				|video: &VideoViewState, _| {
+					video.reconnect_attempt.is_some() || video.error.is_some()
+				},
+				status_widget(),
+				Empty,
+			))
+			.lens(AppState::video),
 			1.0,
 		)
+		.with_child(
+			Either::new(
+				|video: &VideoViewState, _| !video.devices.is_empty(),
+				cam_picker().padding(CustomTheme::grid(1.0)),
+				Empty,
+			)
+			.lens(AppState::video),
+		)
 		.with_spacer(CustomTheme::grid(6.0))
 		.with_child(playback::panel_widget())
 		.background(theme::BACKGROUND_LIGHT);
 
-	let sized = SizedBox::new(layout)
-		.width(320.0)
-		.height(240.0)
-		.expand()
-		.border(Color::grey(0.6), 2.0)
-		.center()
-		.boxed();
+	let sized = SizedBox::new(layout).expand().border(Color::grey(0.6), 2.0).center().boxed();
 
 	// layout
 	ThemeScope::new(Align::centered(sized))
 	// ThemeScope::new(layout)
 }
+
+/// Overlay telling why nothing is playing.
+fn status_widget() -> impl Widget<VideoViewState> {
+	Label::dynamic(|video: &VideoViewState, _| match (video.reconnect_attempt, &video.error) {
+		(Some(attempt), _) => format!("Reconnecting (attempt {})", attempt),
+		(None, Some(error)) => error.clone(),
+		(None, None) => String::new(),
+	})
+	.with_text_color(Color::WHITE)
+	.padding(CustomTheme::grid(1.0))
+	.background(Color::rgba8(0, 0, 0, 0xa0))
+	.rounded(CustomTheme::grid(0.5))
+}
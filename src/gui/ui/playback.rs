@@ -2,8 +2,8 @@ use std::time::Duration;
 
 use druid::{
 	widget::{
-		Axis, Button, Controller, Either, Flex, KnobStyle, Label, RangeSlider, SizedBox, Slider,
-		Stepper, ViewSwitcher,
+		Axis, Button, Controller, Either, Flex, KnobStyle, Label, Painter, RangeSlider, SizedBox,
+		Slider, Stepper, ViewSwitcher,
 	},
 	Color, Cursor, Data, Env, Event, EventCtx, KeyOrValue, MouseButton, PaintCtx, Point, Rect,
 	RenderContext, Size, Widget, WidgetExt,
@@ -13,7 +13,7 @@ use druid_widget_nursery::DropdownSelect;
 use crate::gui::{
 	controller::cmd,
 	data::{
-		video::{VideoPlayer, VideoPlayerState, VideoRate, VideoViewState},
+		video::{Quality, VideoPlayer, VideoPlayerState, VideoRate, VideoViewState},
 		AppState,
 	},
 	widgets::{
@@ -51,7 +51,9 @@ pub fn panel_widget() -> impl Widget<AppState> {
 
 	Flex::column()
 		.with_child(Either::new(
-			|state: &VideoViewState, _| !state.current_item.is_empty(),
+			|state: &VideoViewState, _| {
+				!state.current_item.is_empty() && (!state.live || state.timeshift.is_some())
+			},
 			Slider::new()
 				.with_range(0.0, 1.0)
 				.track_color(KeyOrValue::Concrete(Color::RED))
@@ -62,6 +64,11 @@ pub fn panel_widget() -> impl Widget<AppState> {
 				.controller(SliderController {}),
 			Empty,
 		))
+		.with_child(Either::new(
+			|state: &VideoViewState, _| !state.buffered_ranges.is_empty(),
+			buffered_ranges_widget(),
+			Empty,
+		))
 		.with_default_spacer()
 		.with_child(controls)
 		.with_default_spacer()
@@ -75,20 +82,122 @@ fn player_widget() -> impl Widget<VideoViewState> {
 		.with_child(player_play_pause_widget())
 		.with_default_spacer()
 		.with_child(Either::new(
-			|state: &VideoViewState, _| !state.current_item.is_empty(),
-			durations_widget(),
+			|state: &VideoViewState, _| state.live,
+			live_badge_widget(),
+			Either::new(
+				|state: &VideoViewState, _| !state.current_item.is_empty(),
+				durations_widget(),
+				Empty,
+			),
+		))
+		.with_child(Either::new(
+			|state: &VideoViewState, _| state.timeshift.map_or(false, |status| status.behind > 0),
+			behind_live_widget(),
 			Empty,
 		))
 		.with_default_spacer()
-		.with_child(
+		.with_child(Either::new(|state: &VideoViewState, _| state.live, record_widget(), Empty))
+		.with_default_spacer()
+		.with_child(Either::new(
+			|state: &VideoViewState, _| state.live,
+			Empty,
 			Button::dynamic(|data: &VideoViewState, _: &Env| format!("{:.1}x", data.rate))
 				.on_click(|_ctx, state: &mut VideoViewState, _env| {
 					state.rate_set = !state.rate_set
 				}),
-		)
+		))
+		.with_default_spacer()
+		.with_child(Either::new(
+			|state: &VideoViewState, _| !state.variants.is_empty(),
+			quality_widget(),
+			Empty,
+		))
 		.padding(theme::grid(2.0))
 }
 
+/// Quality menu of an adaptive stream, with the variant being downloaded.
+fn quality_widget() -> impl Widget<VideoViewState> {
+	Flex::row()
+		.with_child(ViewSwitcher::new(
+			|state: &VideoViewState, _| state.variants.clone(),
+			|variants, _, _| {
+				let choices = std::iter::once(("Auto".to_string(), Quality::Auto)).chain(
+					variants
+						.iter()
+						.map(|variant| (variant.to_string(), Quality::Fixed(variant.bitrate))),
+				);
+				DropdownSelect::new(choices).lens(VideoViewState::quality).boxed()
+			},
+		))
+		.with_default_spacer()
+		.with_child(
+			Label::dynamic(|state: &VideoViewState, _| {
+				let variant = state
+					.current_variant
+					.and_then(|index| state.variants.get(index))
+					.map_or_else(|| "–".to_string(), ToString::to_string);
+				format!("{} ({:.1} Mb/s)", variant, state.bandwidth as f64 / 1_000_000.0)
+			})
+			.with_text_size(theme::TEXT_SIZE_SMALL)
+			.with_text_color(theme::PLACEHOLDER_COLOR),
+		)
+}
+
+/// "LIVE" badge, clicking it jumps back to the live edge.
+fn live_badge_widget() -> impl Widget<VideoViewState> {
+	Label::new("LIVE")
+		.with_text_size(theme::TEXT_SIZE_SMALL)
+		.with_text_color(Color::WHITE)
+		.padding((theme::grid(0.5), 0.0))
+		.background(theme::RED)
+		.rounded(theme::BUTTON_BORDER_RADIUS)
+		.on_click(|ctx, _, _| ctx.submit_command(cmd::PLAY_LIVE_EDGE))
+}
+
+/// Distance from the live edge while paused or rewound in the timeshift
+/// buffer.
+fn behind_live_widget() -> impl Widget<VideoViewState> {
+	Label::dynamic(|state: &VideoViewState, _| {
+		let behind = state.timeshift.map_or(0, |status| status.behind);
+		format!("−{}", as_minutes_and_seconds(behind))
+	})
+	.with_text_size(theme::TEXT_SIZE_SMALL)
+	.with_text_color(theme::PLACEHOLDER_COLOR)
+	.padding((theme::grid(1.0), 0.0))
+}
+
+/// Record button of live streams, with the length and size of the
+/// recording in progress.
+fn record_widget() -> impl Widget<VideoViewState> {
+	Flex::row()
+		.with_child(
+			Button::dynamic(|state: &VideoViewState, _: &Env| {
+				let label = if state.recording.is_some() { "■ Stop" } else { "● Record" };
+				label.to_string()
+			})
+			.on_click(|ctx, state: &mut VideoViewState, _| {
+				if state.recording.is_some() {
+					ctx.submit_command(cmd::RECORD_STOP);
+				} else {
+					ctx.submit_command(cmd::RECORD_START);
+				}
+			}),
+		)
+		.with_default_spacer()
+		.with_child(
+			Label::dynamic(|state: &VideoViewState, _| match state.recording {
+				Some(ref recording) => format!(
+					"{} · {:.1} MB",
+					as_minutes_and_seconds(recording.duration),
+					recording.bytes as f64 / 1_000_000.0
+				),
+				None => String::new(),
+			})
+			.with_text_size(theme::TEXT_SIZE_SMALL)
+			.with_text_color(theme::PLACEHOLDER_COLOR),
+		)
+}
+
 fn player_play_pause_widget() -> impl Widget<VideoViewState> {
 	ViewSwitcher::new(
 		|video: &VideoViewState, _| video.state,
@@ -136,6 +245,20 @@ fn durations_widget() -> impl Widget<VideoViewState> {
 	.fix_width(theme::grid(8.0))
 }
 
+/// Thin bar below the seek slider showing the downloaded ranges.
+fn buffered_ranges_widget() -> impl Widget<VideoViewState> {
+	Painter::new(|ctx, state: &VideoViewState, env| {
+		let bounds = ctx.size();
+		let color = env.get(theme::GREY_400);
+		for (start, stop) in state.buffered_ranges.iter() {
+			let rect = Rect::new(bounds.width * start, 0.0, bounds.width * stop, bounds.height);
+			ctx.fill(rect, &color);
+		}
+	})
+	.fix_height(theme::grid(0.5))
+	.expand_width()
+}
+
 pub fn as_minutes_and_seconds(dur: u64) -> String {
 	let minutes = dur / 60;
 	let seconds = dur % 60;
@@ -0,0 +1,65 @@
+use druid::{
+	widget::{Flex, Label, ViewSwitcher},
+	Widget, WidgetExt,
+};
+use druid_widget_nursery::DropdownSelect;
+
+use crate::gui::{
+	data::video::{CaptureSelection, VideoViewState},
+	widgets::theme,
+};
+
+/// Dropdowns for the camera, its mode and the microphone being captured.
+///
+/// Picking something changes [`VideoViewState::capture`], which restarts
+/// the capture.
+pub fn cam_picker() -> impl Widget<VideoViewState> {
+	ViewSwitcher::new(
+		|state: &VideoViewState, _| (state.devices.clone(), state.capture.clone()),
+		|(devices, capture), _, _| {
+			// The current choice is the selection itself, so the dropdowns show it.
+			let cameras = devices.iter().filter(|device| device.video).map(|device| {
+				let selection = if capture.video.as_ref() == Some(&device.name) {
+					capture.clone()
+				} else {
+					CaptureSelection {
+						video: Some(device.name.clone()),
+						mode: None,
+						..capture.clone()
+					}
+				};
+				(device.name.clone(), selection)
+			});
+			let modes = devices
+				.iter()
+				.find(|device| capture.video.as_ref() == Some(&device.name))
+				.map(|device| device.modes.clone())
+				.unwrap_or_default();
+			let modes = std::iter::once(("Auto".to_string(), None))
+				.chain(modes.iter().map(|mode| (mode.to_string(), Some(*mode))))
+				.map(|(label, mode)| (label, CaptureSelection { mode, ..capture.clone() }));
+			let microphones = std::iter::once(("No audio".to_string(), None))
+				.chain(
+					devices
+						.iter()
+						.filter(|device| !device.video)
+						.map(|device| (device.name.clone(), Some(device.name.clone()))),
+				)
+				.map(|(label, audio)| (label, CaptureSelection { audio, ..capture.clone() }));
+
+			Flex::row()
+				.with_child(
+					Label::new("Camera")
+						.with_text_size(theme::TEXT_SIZE_SMALL)
+						.with_text_color(theme::PLACEHOLDER_COLOR),
+				)
+				.with_default_spacer()
+				.with_child(DropdownSelect::new(cameras).lens(VideoViewState::capture))
+				.with_default_spacer()
+				.with_child(DropdownSelect::new(modes).lens(VideoViewState::capture))
+				.with_default_spacer()
+				.with_child(DropdownSelect::new(microphones).lens(VideoViewState::capture))
+				.boxed()
+		},
+	)
+}
@@ -0,0 +1,191 @@
+use druid::{
+	menu::{Menu, MenuItem},
+	Rect, Scale, Size,
+};
+
+use crate::{
+	gui::data::{
+		video::{DisplayMode, Orientation, VideoView},
+		AppState,
+	},
+	media::player::VideoFrame,
+};
+
+impl VideoView {
+	/// Where the shown frame goes in a view of `size` showing it in `mode`,
+	/// on a screen of `scale`. Without a frame it is the whole view.
+	pub(crate) fn frame_rect(&self, mode: DisplayMode, size: Size, scale: Scale) -> Rect {
+		match self.shown {
+			Some((ref frame, _)) => frame_rect(mode, size, display_size(frame), scale),
+			None => size.to_rect(),
+		}
+	}
+}
+
+/// Size of `frame` with square pixels, in pixels of its height.
+pub(crate) fn display_size(frame: &VideoFrame) -> Size {
+	let par = match frame.pixel_aspect_ratio {
+		(numer, denom) if numer > 0 && denom > 0 => numer as f64 / denom as f64,
+		_ => 1.0,
+	};
+	Size::new(frame.width as f64 * par, frame.height as f64)
+}
+
+/// Where a frame of display size `frame` goes in a view of `size`, centered.
+fn frame_rect(mode: DisplayMode, size: Size, frame: Size, scale: Scale) -> Rect {
+	if frame.is_empty() || size.is_empty() {
+		return size.to_rect();
+	}
+	// The largest size of aspect ratio `aspect` within the view.
+	let fit = |aspect: f64| {
+		if size.width / size.height > aspect {
+			Size::new(size.height * aspect, size.height)
+		} else {
+			Size::new(size.width, size.width / aspect)
+		}
+	};
+	let shown = match mode {
+		DisplayMode::Fit => fit(frame.width / frame.height),
+		DisplayMode::Fill => {
+			let zoom = (size.width / frame.width).max(size.height / frame.height);
+			frame * zoom
+		}
+		DisplayMode::Stretch => size,
+		DisplayMode::Native => Size::new(frame.width / scale.x(), frame.height / scale.y()),
+		DisplayMode::Ratio(width, height) => fit(width as f64 / height.max(1) as f64),
+	};
+	Rect::from_center_size(size.to_rect().center(), shown)
+}
+
+/// Context menu of the view, picking the display mode and the orientation.
+pub(crate) fn display_menu() -> Menu<AppState> {
+	let menu = DisplayMode::ALL.iter().fold(Menu::empty(), |menu, &mode| {
+		menu.entry(
+			MenuItem::new(mode.to_string())
+				.on_activate(move |_ctx, data: &mut AppState, _env| data.video.display_mode = mode)
+				.selected_if(move |data: &AppState, _env| data.video.display_mode == mode),
+		)
+	});
+	let orient = |label: &'static str, change: fn(Orientation) -> Orientation| {
+		MenuItem::new(label).on_activate(move |_ctx, data: &mut AppState, _env| {
+			data.video.orientation = change(data.video.orientation)
+		})
+	};
+	menu.separator()
+		.entry(orient("Rotate clockwise", Orientation::rotate_clockwise))
+		.entry(orient("Flip horizontally", Orientation::flip_horizontal))
+		.entry(orient("Flip vertically", Orientation::flip_vertical))
+		.entry(
+			MenuItem::new("Reset orientation")
+				.on_activate(|_ctx, data: &mut AppState, _env| {
+					data.video.orientation = Orientation::default()
+				})
+				.enabled_if(|data: &AppState, _env| {
+					data.video.orientation != Orientation::default()
+				}),
+		)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::media::{player::FrameData, types::Colorimetry};
+
+	const VIEW: Size = Size::new(400.0, 300.0);
+
+	fn frame(width: u32, height: u32, pixel_aspect_ratio: (i32, i32)) -> VideoFrame {
+		VideoFrame {
+			data: FrameData::from(Vec::new()),
+			width,
+			height,
+			pixel_aspect_ratio,
+			pts: None,
+			running_time: None,
+			clock: None,
+			number: 0,
+			colorimetry: Colorimetry::default(),
+		}
+	}
+
+	fn assert_rect(mode: DisplayMode, frame: Size, scale: f64, expected: Rect) {
+		let rect = frame_rect(mode, VIEW, frame, Scale::new(scale, scale));
+		let close = [
+			(rect.x0, expected.x0),
+			(rect.y0, expected.y0),
+			(rect.x1, expected.x1),
+			(rect.y1, expected.y1),
+		]
+		.iter()
+		.all(|(a, b)| (a - b).abs() < 1e-6);
+		assert!(close, "{} of {}: {:?} != {:?}", mode, frame, rect, expected);
+	}
+
+	#[test]
+	fn display_size_applies_the_pixel_aspect_ratio() {
+		for &(width, height, par, expected) in &[
+			(640, 480, (1, 1), Size::new(640.0, 480.0)),
+			(720, 576, (16, 15), Size::new(768.0, 576.0)),
+			(720, 576, (64, 45), Size::new(1024.0, 576.0)),
+			(1440, 1080, (4, 3), Size::new(1920.0, 1080.0)),
+			// Unknown ratios show square pixels.
+			(720, 576, (0, 1), Size::new(720.0, 576.0)),
+			(720, 576, (1, 0), Size::new(720.0, 576.0)),
+			(720, 576, (-4, 3), Size::new(720.0, 576.0)),
+		] {
+			assert_eq!(display_size(&frame(width, height, par)), expected, "{:?}", par);
+		}
+	}
+
+	#[test]
+	fn frame_rect_per_mode() {
+		let wide = Size::new(1920.0, 1080.0);
+		let tall = Size::new(480.0, 640.0);
+		for &(mode, frame, scale, expected) in &[
+			// Letterbox and pillarbox.
+			(DisplayMode::Fit, wide, 1.0, Rect::new(0.0, 37.5, 400.0, 262.5)),
+			(DisplayMode::Fit, tall, 1.0, Rect::new(87.5, 0.0, 312.5, 300.0)),
+			(DisplayMode::Fit, Size::new(40.0, 30.0), 1.0, Rect::new(0.0, 0.0, 400.0, 300.0)),
+			// Cropped on the other axis.
+			(DisplayMode::Fill, wide, 1.0, Rect::new(-200.0 / 3.0, 0.0, 1400.0 / 3.0, 300.0)),
+			(DisplayMode::Fill, tall, 1.0, Rect::new(0.0, -350.0 / 3.0, 400.0, 1250.0 / 3.0)),
+			(DisplayMode::Stretch, wide, 1.0, Rect::new(0.0, 0.0, 400.0, 300.0)),
+			(DisplayMode::Stretch, tall, 1.0, Rect::new(0.0, 0.0, 400.0, 300.0)),
+			// One frame pixel per screen pixel, whatever the view size.
+			(
+				DisplayMode::Native,
+				Size::new(200.0, 100.0),
+				1.0,
+				Rect::new(100.0, 100.0, 300.0, 200.0),
+			),
+			(DisplayMode::Native, wide, 2.0, Rect::new(-280.0, -120.0, 680.0, 420.0)),
+			// The frame's own aspect ratio doesn't matter.
+			(DisplayMode::Ratio(1, 1), wide, 1.0, Rect::new(50.0, 0.0, 350.0, 300.0)),
+			(DisplayMode::Ratio(1, 1), tall, 1.0, Rect::new(50.0, 0.0, 350.0, 300.0)),
+			(DisplayMode::Ratio(16, 9), tall, 1.0, Rect::new(0.0, 37.5, 400.0, 262.5)),
+			(DisplayMode::Ratio(4, 0), wide, 1.0, Rect::new(0.0, 100.0, 400.0, 200.0)),
+		] {
+			assert_rect(mode, frame, scale, expected);
+		}
+	}
+
+	#[test]
+	fn frame_rect_of_anamorphic_frames() {
+		// PAL widescreen, stored 5:4 and shown 16:9.
+		let frame = display_size(&frame(720, 576, (64, 45)));
+		assert_rect(DisplayMode::Fit, frame, 1.0, Rect::new(0.0, 37.5, 400.0, 262.5));
+		assert_rect(
+			DisplayMode::Fill,
+			frame,
+			1.0,
+			Rect::new(-200.0 / 3.0, 0.0, 1400.0 / 3.0, 300.0),
+		);
+		assert_rect(DisplayMode::Native, frame, 1.0, Rect::new(-312.0, -138.0, 712.0, 438.0));
+	}
+
+	#[test]
+	fn frame_rect_without_a_frame_is_the_view() {
+		for &mode in &DisplayMode::ALL {
+			assert_rect(mode, Size::ZERO, 1.0, VIEW.to_rect());
+		}
+	}
+}
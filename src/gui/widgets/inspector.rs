@@ -0,0 +1,302 @@
+use std::fmt;
+
+use druid::{
+	piet::{Text, TextLayout, TextLayoutBuilder},
+	Color, FontFamily, PaintCtx, Point, Rect, RenderContext, Scale, Size,
+};
+
+use crate::{
+	gui::data::video::{DisplayMode, PixelSample, VideoView, ViewTransform},
+	media::player::VideoFrame,
+};
+
+/// Pinned samples listed in the overlay, the newest ones.
+const LISTED_SAMPLES: usize = 8;
+
+impl VideoView {
+	/// The pixel of the shown frame under `point` of a view of `size`, the
+	/// frame placed by `mode` and magnified by `transform`.
+	pub(crate) fn sample_at(
+		&self,
+		point: Point,
+		mode: DisplayMode,
+		transform: ViewTransform,
+		size: Size,
+		scale: Scale,
+	) -> Option<PixelSample> {
+		let (frame, _) = self.shown.as_ref()?;
+		let rect = self.frame_rect(mode, size, scale);
+		let point = transform.clamped(size).affine().inverse() * point;
+		if !rect.contains(point) || rect.area() == 0.0 {
+			return None;
+		}
+		let x = (point.x - rect.x0) / rect.width() * frame.width as f64;
+		let y = (point.y - rect.y0) / rect.height() * frame.height as f64;
+		PixelSample::from_frame(frame, x as u32, y as u32)
+	}
+}
+
+impl PixelSample {
+	/// The pixel at `x`, `y` of the buffer of `frame`.
+	pub fn from_frame(frame: &VideoFrame, x: u32, y: u32) -> Option<Self> {
+		let (x, y) = (x.min(frame.width.checked_sub(1)?), y.min(frame.height.checked_sub(1)?));
+		let data = frame.data.as_slice();
+		// Rows may be padded beyond the width.
+		let stride = data.len() / frame.height as usize;
+		let offset = y as usize * stride + x as usize * 4;
+		let rgba = data.get(offset..offset + 4)?;
+		let rgba = [rgba[0], rgba[1], rgba[2], rgba[3]];
+		Some(Self { x, y, rgba, colorimetry: frame.colorimetry })
+	}
+
+	/// The colour as `#rrggbbaa`.
+	pub fn hex(&self) -> String {
+		let [r, g, b, a] = self.rgba;
+		format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+	}
+
+	/// The colour as 8 bit Y'CbCr, in the matrix and range the frame was
+	/// coded with.
+	pub fn yuv(&self) -> [u8; 3] {
+		let [r, g, b] = [self.rgba[0], self.rgba[1], self.rgba[2]].map(|c| c as f64 / 255.0);
+		let (kr, kb) = self.colorimetry.matrix.coefficients();
+		let y = kr * r + (1.0 - kr - kb) * g + kb * b;
+		let cb = (b - y) / (2.0 * (1.0 - kb));
+		let cr = (r - y) / (2.0 * (1.0 - kr));
+		let (offset, luma, chroma) =
+			if self.colorimetry.full_range { (0.0, 255.0, 255.0) } else { (16.0, 219.0, 224.0) };
+		let quantize = |value: f64| value.round().clamp(0.0, 255.0) as u8;
+		[quantize(offset + luma * y), quantize(128.0 + chroma * cb), quantize(128.0 + chroma * cr)]
+	}
+}
+
+impl fmt::Display for PixelSample {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let [r, g, b, a] = self.rgba;
+		let [y, u, v] = self.yuv();
+		write!(
+			f,
+			"{:>5} {:>5}  rgba {:>3} {:>3} {:>3} {:>3}  {}  yuv {:>3} {:>3} {:>3} ({})",
+			self.x,
+			self.y,
+			r,
+			g,
+			b,
+			a,
+			self.hex(),
+			y,
+			u,
+			v,
+			self.colorimetry
+		)
+	}
+}
+
+/// The samples as text, one per line, to be copied out.
+pub(crate) fn samples_text(samples: &[PixelSample]) -> String {
+	let lines: Vec<_> = samples.iter().map(PixelSample::to_string).collect();
+	lines.join("\n")
+}
+
+/// Draw the inspector: the pixel under the pointer and the newest pinned
+/// samples, in the top left corner of the view.
+pub(crate) fn paint_inspector(
+	ctx: &mut PaintCtx,
+	hovered: Option<PixelSample>,
+	samples: &[PixelSample],
+) {
+	let mut text = match hovered {
+		Some(sample) => sample.to_string(),
+		None => "outside the frame".to_owned(),
+	};
+	let listed = &samples[samples.len().saturating_sub(LISTED_SAMPLES)..];
+	for (index, sample) in listed.iter().enumerate() {
+		let number = samples.len() - listed.len() + index + 1;
+		text.push_str(&format!("\n#{:<3} {}", number, sample));
+	}
+	let layout = ctx
+		.text()
+		.new_text_layout(text)
+		.font(FontFamily::MONOSPACE, 12.0)
+		.text_color(Color::WHITE)
+		.build();
+	let layout = match layout {
+		Ok(layout) => layout,
+		Err(err) => {
+			log::error!("Failed to lay out the inspector: {}", err);
+			return;
+		}
+	};
+	let origin = Point::new(8.0, 8.0);
+	let background = Rect::from_origin_size(origin, layout.size()).inflate(4.0, 4.0);
+	ctx.fill(background, &Color::rgba8(0, 0, 0, 0xa0));
+	ctx.draw_text(&layout, origin);
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use druid::Vec2;
+
+	use super::*;
+	use crate::media::{
+		player::FrameData,
+		types::{Colorimetry, YuvMatrix},
+	};
+
+	const VIEW: Size = Size::new(400.0, 300.0);
+
+	/// A frame of `width` by `height` pixels with rows of `stride` bytes,
+	/// each pixel holding its own coordinates in red and green.
+	fn frame(width: u32, height: u32, stride: usize) -> VideoFrame {
+		let mut data = vec![0; stride * height as usize];
+		for y in 0..height as usize {
+			for x in 0..width as usize {
+				data[y * stride + x * 4..][..4].copy_from_slice(&[x as u8, y as u8, 0, 255]);
+			}
+		}
+		VideoFrame {
+			data: FrameData::from(data),
+			width,
+			height,
+			pixel_aspect_ratio: (1, 1),
+			pts: None,
+			running_time: None,
+			clock: None,
+			number: 0,
+			colorimetry: Colorimetry::default(),
+		}
+	}
+
+	fn view(frame: VideoFrame) -> VideoView {
+		let mut view = VideoView::new();
+		view.shown = Some((Arc::new(frame), None));
+		view
+	}
+
+	/// Coordinates of the pixel under `point` of the view.
+	fn pixel_at(
+		view: &VideoView,
+		point: (f64, f64),
+		mode: DisplayMode,
+		transform: ViewTransform,
+	) -> Option<(u32, u32)> {
+		let point = Point::new(point.0, point.1);
+		let sample = view.sample_at(point, mode, transform, VIEW, Scale::new(1.0, 1.0))?;
+		assert_eq!(sample.rgba, [sample.x as u8, sample.y as u8, 0, 255]);
+		Some((sample.x, sample.y))
+	}
+
+	#[test]
+	fn sample_at_the_frame_placed_by_the_mode() {
+		let plain = ViewTransform::default();
+		let stretched = view(frame(4, 3, 16));
+		assert_eq!(pixel_at(&stretched, (0.0, 0.0), DisplayMode::Stretch, plain), Some((0, 0)));
+		assert_eq!(pixel_at(&stretched, (150.0, 250.0), DisplayMode::Stretch, plain), Some((1, 2)));
+		assert_eq!(pixel_at(&stretched, (399.9, 299.9), DisplayMode::Stretch, plain), Some((3, 2)));
+
+		// Letterboxed to 400 by 200, one pixel is 100 by 100.
+		let letterboxed = view(frame(4, 2, 16));
+		assert_eq!(pixel_at(&letterboxed, (50.0, 25.0), DisplayMode::Fit, plain), None);
+		assert_eq!(pixel_at(&letterboxed, (350.0, 240.0), DisplayMode::Fit, plain), Some((3, 1)));
+		assert_eq!(pixel_at(&letterboxed, (350.0, 260.0), DisplayMode::Fit, plain), None);
+	}
+
+	#[test]
+	fn sample_at_a_zoomed_and_panned_view() {
+		let view = view(frame(4, 3, 16));
+		let zoomed = ViewTransform::default().zoom_at(2.0, Point::new(200.0, 150.0), VIEW);
+		assert_eq!(pixel_at(&view, (0.0, 0.0), DisplayMode::Stretch, zoomed), Some((1, 0)));
+		assert_eq!(pixel_at(&view, (399.0, 299.0), DisplayMode::Stretch, zoomed), Some((2, 2)));
+
+		let panned = zoomed.pan_by(Vec2::new(1000.0, 1000.0), VIEW);
+		assert_eq!(pixel_at(&view, (0.0, 0.0), DisplayMode::Stretch, panned), Some((0, 0)));
+		assert_eq!(pixel_at(&view, (399.0, 299.0), DisplayMode::Stretch, panned), Some((1, 1)));
+
+		// A pan beyond the frame is clamped like when painting.
+		let beyond = ViewTransform { zoom: 2.0, pan: Vec2::new(100.0, 100.0) };
+		assert_eq!(pixel_at(&view, (399.0, 299.0), DisplayMode::Stretch, beyond), Some((1, 1)));
+	}
+
+	#[test]
+	fn sample_at_a_rotated_frame() {
+		// The pipeline rotates the frames, a 4 by 3 video turned a quarter
+		// comes as 3 by 4 and is pillarboxed to 225 by 300.
+		let view = view(frame(3, 4, 12));
+		let plain = ViewTransform::default();
+		assert_eq!(pixel_at(&view, (80.0, 10.0), DisplayMode::Fit, plain), None);
+		assert_eq!(pixel_at(&view, (100.0, 10.0), DisplayMode::Fit, plain), Some((0, 0)));
+		assert_eq!(pixel_at(&view, (300.0, 290.0), DisplayMode::Fit, plain), Some((2, 3)));
+	}
+
+	#[test]
+	fn sample_without_a_frame() {
+		let view = VideoView::new();
+		let point = Point::new(10.0, 10.0);
+		let transform = ViewTransform::default();
+		assert!(view
+			.sample_at(point, DisplayMode::Fit, transform, VIEW, Scale::new(1.0, 1.0))
+			.is_none());
+		assert!(PixelSample::from_frame(&frame(0, 0, 0), 0, 0).is_none());
+	}
+
+	#[test]
+	fn from_frame_skips_the_row_padding() {
+		let frame = frame(2, 2, 12);
+		let sample = PixelSample::from_frame(&frame, 1, 1).unwrap();
+		assert_eq!(sample.rgba, [1, 1, 0, 255]);
+		// Coordinates beyond the frame take its last pixel.
+		let sample = PixelSample::from_frame(&frame, 5, 5).unwrap();
+		assert_eq!((sample.x, sample.y, sample.rgba), (1, 1, [1, 1, 0, 255]));
+	}
+
+	#[test]
+	fn yuv_per_matrix_and_range() {
+		let yuv = |rgb: [u8; 3], matrix, full_range| {
+			let sample = PixelSample {
+				x: 0,
+				y: 0,
+				rgba: [rgb[0], rgb[1], rgb[2], 255],
+				colorimetry: Colorimetry { matrix, full_range },
+			};
+			sample.yuv()
+		};
+		let (red, green, blue) = ([255, 0, 0], [0, 255, 0], [0, 0, 255]);
+		for &(matrix, full_range, expected) in &[
+			(YuvMatrix::Bt601, false, [[81, 90, 240], [145, 54, 34], [41, 240, 110]]),
+			(YuvMatrix::Bt601, true, [[76, 85, 255], [150, 44, 21], [29, 255, 107]]),
+			(YuvMatrix::Bt709, false, [[63, 102, 240], [173, 42, 26], [32, 240, 118]]),
+			(YuvMatrix::Bt709, true, [[54, 99, 255], [182, 30, 12], [18, 255, 116]]),
+			(YuvMatrix::Bt2020, false, [[74, 97, 240], [164, 47, 25], [29, 240, 119]]),
+			(YuvMatrix::Bt2020, true, [[67, 92, 255], [173, 36, 11], [15, 255, 118]]),
+			(YuvMatrix::Smpte240m, false, [[62, 102, 240], [170, 42, 28], [35, 240, 116]]),
+			(YuvMatrix::Smpte240m, true, [[54, 98, 255], [179, 30, 15], [22, 255, 114]]),
+		] {
+			let actual = [
+				yuv(red, matrix, full_range),
+				yuv(green, matrix, full_range),
+				yuv(blue, matrix, full_range),
+			];
+			assert_eq!(actual, expected, "{:?} full range {}", matrix, full_range);
+			// Greys have no chroma in any matrix.
+			let black = if full_range { 0 } else { 16 };
+			let white = if full_range { 255 } else { 235 };
+			assert_eq!(yuv([0, 0, 0], matrix, full_range), [black, 128, 128]);
+			assert_eq!(yuv([255, 255, 255], matrix, full_range), [white, 128, 128]);
+		}
+	}
+
+	#[test]
+	fn sample_text_names_the_colorimetry() {
+		let sample = PixelSample {
+			x: 3,
+			y: 4,
+			rgba: [255, 0, 0, 255],
+			colorimetry: Colorimetry { matrix: YuvMatrix::Bt601, full_range: true },
+		};
+		let text = sample.to_string();
+		assert!(text.ends_with("#ff0000ff  yuv  76  85 255 (BT.601 full)"), "{}", text);
+		assert_eq!(samples_text(&[sample, sample]), format!("{}\n{}", text, text));
+	}
+}
@@ -1,9 +1,14 @@
 //! Widgets for the UI.
 
-// mod cam_picker;
+pub mod cam_picker;
+
+mod display;
 
 pub mod empty;
 pub mod icons;
+mod inspector;
 pub mod theme;
+mod timeshift;
 pub mod video;
+mod zoom;
 // mod audio;
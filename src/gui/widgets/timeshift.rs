@@ -0,0 +1,190 @@
+use std::{sync::Arc, time::Duration};
+
+use gst::prelude::*;
+use gstreamer as gst;
+
+use crate::{
+	gui::{
+		controller::cmd,
+		data::video::{TimeshiftStatus, Timeshifted, VideoError, VideoPlayer, VideoView},
+		widgets::video::CommandSink,
+	},
+	media::{actor::PlayerHandle, backend::MediaBackend, record::Progress},
+};
+
+/// Files ending this close after a position have nothing left to show.
+const END_TOLERANCE: Duration = Duration::from_millis(500);
+
+impl VideoView {
+	/// What the timeshift buffer of the live player holds.
+	fn timeshift_progress(&self) -> Option<Progress> {
+		self.player.as_ref()?.status().timeshift
+	}
+
+	/// Whether the live stream can be paused and rewound.
+	pub(crate) fn can_timeshift(&self) -> bool {
+		self.timeshift_progress().is_some()
+	}
+
+	/// Time in the timeshift buffer being shown.
+	fn timeshift_position(&self, progress: &Progress) -> Duration {
+		match self.timeshift {
+			None => progress.end,
+			Some(Timeshifted { player: Some(ref player), start }) => {
+				start + player.status().position
+			}
+			Some(Timeshifted { player: None, start }) => start,
+		}
+	}
+
+	/// Buffered time of the timeshift buffer, and how far behind the live
+	/// edge the view is.
+	pub(crate) fn timeshift_status(&self) -> Option<TimeshiftStatus> {
+		let progress = self.timeshift_progress()?;
+		let oldest = progress.fragments.first().map_or(progress.end, |fragment| fragment.start);
+		let position = self.timeshift_position(&progress);
+		Some(TimeshiftStatus {
+			buffered: progress.end.saturating_sub(oldest).as_secs(),
+			behind: progress.end.saturating_sub(position).as_secs(),
+		})
+	}
+
+	/// Time in the timeshift buffer `seconds` after its oldest file starts.
+	pub(crate) fn timeshift_time(&self, seconds: u64) -> Option<Duration> {
+		let progress = self.timeshift_progress()?;
+		let oldest = progress.fragments.first().map_or(progress.end, |fragment| fragment.start);
+		Some(oldest + Duration::from_secs(seconds))
+	}
+
+	/// Where the view is in the timeshift buffer.
+	pub(crate) fn timeshift_current(&self) -> Option<Duration> {
+		let progress = self.timeshift_progress()?;
+		Some(self.timeshift_position(&progress))
+	}
+
+	/// Stop showing the live stream while it keeps being buffered.
+	pub(crate) fn pause_live(&mut self) {
+		let progress = match self.timeshift_progress() {
+			Some(progress) => progress,
+			None => return,
+		};
+		if let Some(ref player) = self.player {
+			let hide = player.run(|backend| {
+				backend.set_hidden(true);
+				// Only closed files can be played.
+				backend.split_timeshift();
+				Ok(())
+			});
+			hide.then(log_failure);
+		}
+		self.timeshift = Some(Timeshifted { player: None, start: progress.end });
+	}
+
+	/// Show the timeshift buffer from `position` on.
+	///
+	/// Without anything buffered after `position` the view goes back live,
+	/// or stays paused there if it isn't `playing`. The timeshift player is
+	/// opened on its own thread, the view goes back live if that fails.
+	pub(crate) fn shift_to(
+		&mut self,
+		position: Duration,
+		playing: bool,
+		event_sink: impl CommandSink,
+	) -> Result<(), VideoError> {
+		let progress = self.timeshift_progress().ok_or(VideoError::Record)?;
+		let fragments: Vec<_> = progress
+			.fragments
+			.into_iter()
+			.filter(|fragment| fragment.end > position + END_TOLERANCE)
+			.collect();
+		// The old player goes first, its frames would mix with the new ones.
+		let closed = self.timeshift.take().and_then(|old| old.player).map(|old| old.close());
+		let start = match fragments.first() {
+			Some(fragment) => fragment.start,
+			None if playing => {
+				self.go_live();
+				return Ok(());
+			}
+			None => {
+				self.timeshift = Some(Timeshifted { player: None, start: position });
+				return Ok(());
+			}
+		};
+		if let Some(ref live) = self.player {
+			live.set_hidden(true).then(log_failure);
+		}
+
+		let options = self.options.clone();
+		let sink = self.view_sink(event_sink.clone());
+		let offset = gst::ClockTime::from_nseconds(position.saturating_sub(start).as_nanos() as _);
+		let open = move || -> Result<Box<dyn MediaBackend>, VideoError> {
+			if let Some(closed) = closed {
+				let _ = closed.wait();
+			}
+			let player =
+				VideoPlayer::from_fragments(&fragments, progress.audio, &options, Arc::new(sink))?;
+			let flags = gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT;
+			if playing {
+				player.pipeline.seek_simple(flags, offset)?;
+				player.pipeline.set_state(gst::State::Playing)?;
+			} else {
+				// Show the frame sought to, paused pipelines don't hand it out.
+				player.set_muted(true);
+				player.pipeline.set_state(gst::State::Playing)?;
+				player.pipeline.seek_simple(flags, offset)?;
+				player.pipeline.state(gst::ClockTime::from_mseconds(20)).0?;
+				player.pipeline.set_state(gst::State::Paused)?;
+				player.set_muted(false);
+			}
+			Ok(Box::new(player))
+		};
+		let player = PlayerHandle::spawn();
+		player.open(open).then(move |result| {
+			if let Err(err) = result {
+				log::error!("Failed to play the timeshift buffer: {}", err);
+				let _ = event_sink.submit(cmd::PLAY_LIVE_EDGE, ());
+			}
+		});
+		self.scale_down(&player);
+		self.orient(&player);
+		self.timeshift = Some(Timeshifted { player: Some(player), start });
+		Ok(())
+	}
+
+	/// Continue after the files the timeshift player was opened with,
+	/// going back live if there is nothing newer.
+	pub(crate) fn continue_timeshift(
+		&mut self,
+		event_sink: impl CommandSink,
+	) -> Result<(), VideoError> {
+		match self.timeshift {
+			Some(Timeshifted { player: Some(_), .. }) => {}
+			_ => return Ok(()),
+		}
+		let position = self.timeshift_current().ok_or(VideoError::Record)?;
+		self.shift_to(position, true, event_sink)
+	}
+
+	/// Show the live stream again, it kept playing hidden.
+	pub(crate) fn go_live(&mut self) {
+		self.timeshift = None;
+		if let Some(ref player) = self.player {
+			player.set_hidden(false).then(log_failure);
+		}
+	}
+
+	/// The player shown, of the timeshift buffer while behind live.
+	pub(crate) fn shown_player(&self) -> Option<&PlayerHandle> {
+		match self.timeshift {
+			Some(Timeshifted { player: Some(ref player), .. }) => Some(player),
+			_ => self.player.as_ref(),
+		}
+	}
+}
+
+/// Log why the live player couldn't be hidden or shown.
+fn log_failure(result: Result<(), VideoError>) {
+	if let Err(err) = result {
+		log::error!("Failed to switch the live player: {}", err);
+	}
+}
@@ -1,131 +1,576 @@
-use anyhow::Error;
+use std::{any::Any, sync::Arc, time::Duration};
+
 use druid::{
-	kurbo::Circle,
-	piet::{ImageFormat, InterpolationMode},
-	widget::{Controller, FillStrat, Image},
-	BoxConstraints, Color, Env, Event, EventCtx, ExtEventSink, ImageBuf, LayoutCtx, LifeCycle,
-	LifeCycleCtx, MouseButton, PaintCtx, RenderContext, Selector, SingleUse, Size, Target,
-	UpdateCtx, Widget,
+	kurbo::Circle, piet::ImageFormat, widget::Controller, Application, BoxConstraints, Color,
+	Command, Env, Event, EventCtx, ExtEventError, ExtEventSink, KbKey, LayoutCtx, LifeCycle,
+	LifeCycleCtx, MouseButton, PaintCtx, Point, RenderContext, Scale, Selector, SingleUse, Size,
+	Target, TimerToken, UpdateCtx, Widget,
 };
-use gst::prelude::*;
-use gstreamer as gst;
-use gstreamer::{event::Seek, Element, SeekFlags, SeekType};
-use gstreamer_app as gst_app;
-use num_traits::ToPrimitive;
 
 use crate::{
 	gui::{
 		controller::cmd,
 		data::video::{
-			Position, VideoError, VideoError::Duration, VideoPlayer, VideoPlayerState, VideoView,
-			VideoViewState,
+			DisplayMode, Orientation, PlayerOptions, Timeshifted, VideoError, VideoPlayer,
+			VideoPlayerState, VideoView, VideoViewState,
+		},
+		widgets::{
+			display::{display_menu, display_size},
+			inspector::{paint_inspector, samples_text},
+			zoom::{paint_frame, ZOOM_STEP},
 		},
 	},
-	media::thumbnail::Thumbnail,
+	media::{
+		actor::PlayerHandle,
+		backend::MediaBackend,
+		devices, launch,
+		player::{Disconnected, PlayerEvent, PlayerSink, SharedSink, VideoFrame},
+		present::{FrameScheduler, FrameSlot},
+		testsrc,
+		thumbnail::Thumbnail,
+	},
 };
 
 impl VideoView {
-	/// Create new camera view
+	/// Create a new video view
 	pub fn new() -> Self {
-		let image_buf = ImageBuf::default();
-		let image = Image::new(image_buf)
-			.fill_mode(FillStrat::Fill)
-			.interpolation_mode(InterpolationMode::Bilinear);
+		Self {
+			shown: None,
+			player: None,
+			event: None,
+			options: PlayerOptions::default(),
+			factory: None,
+			capture: None,
+			timer: TimerToken::INVALID,
+			timeshift: None,
+			scheduler: FrameScheduler::new(),
+			render_size: None,
+			pointer: None,
+			orientation: Orientation::default(),
+		}
+	}
 
-		Self { image, player: None, event: None }
+	/// Create the player with `factory` instead of from the current item.
+	///
+	/// Any [`MediaBackend`] can be returned, [`VideoPlayer`] or a
+	/// [`FakeBackend`](crate::fake::FakeBackend) in tests. The factory runs on
+	/// the player thread.
+	pub fn with_player_factory<F, B>(mut self, factory: F) -> Self
+	where
+		F: FnOnce(&PlayerOptions, SharedSink) -> Result<B, VideoError> + Send + 'static,
+		B: MediaBackend + 'static,
+	{
+		self.factory = Some(Box::new(move |options: &PlayerOptions, sink: SharedSink| {
+			Ok(Box::new(factory(options, sink)?) as Box<dyn MediaBackend>)
+		}));
+		self
 	}
-}
 
-impl Widget<VideoViewState> for VideoView {
-	fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut VideoViewState, env: &Env) {
-		if let Event::Command(command) = event {
-			if let Some(image_buf) = command.get(cmd::VIDEO_FRAME) {
-				if self.player.is_some() {
-					let player = self.player.as_ref().unwrap();
-					for msg in player.bus.iter() {
-						match msg.view() {
-							gst::MessageView::Error(err) => panic!("{:#?}", err),
-							gst::MessageView::Eos(_) => {
-								println!("EOS");
-							}
-							_ => {}
-						}
+	/// Use `options` when the player gets created.
+	pub fn with_options(mut self, options: PlayerOptions) -> Self {
+		self.options = options;
+		self
+	}
+
+	/// Open the current item on the player thread.
+	///
+	/// Everything which waits for the media, from listing capture devices to
+	/// prerolling, happens there. Failures are reported to the view.
+	fn open_player<S: CommandSink>(&mut self, data: &VideoViewState, event_sink: S) {
+		let player = self.player.get_or_insert_with(PlayerHandle::spawn).clone();
+		let options = self.options.clone();
+		let factory = self.factory.take();
+		let item = data.current_item.clone();
+		let live = data.live;
+		let sink = self.view_sink(event_sink.clone());
+		let open = move || -> Result<Box<dyn MediaBackend>, VideoError> {
+			let player_sink: SharedSink = Arc::new(sink.clone());
+			let event_sink = &sink.event_sink;
+			if let Some(factory) = factory {
+				let player = factory(&options, player_sink)?;
+				announce(&sink, player.as_ref(), None);
+				return Ok(player);
+			}
+			if let Some(description) = item.strip_prefix(launch::SCHEME) {
+				let player = VideoPlayer::from_launch(description, options, player_sink)?;
+				announce(&sink, &player, None);
+				return Ok(Box::new(player));
+			}
+			let uri = media_uri(&item)?;
+			if uri.scheme() == devices::SCHEME {
+				let devices = devices::list()?;
+				let selection = devices::parse_uri(&uri, &devices)?;
+				let devices = Arc::new(devices);
+				let _ = event_sink.submit(cmd::CAPTURE_DEVICES, devices);
+				let _ = event_sink.submit(cmd::CAPTURE_SELECTED, selection.clone());
+				let player = VideoPlayer::from_capture(&selection, options, player_sink)?;
+				announce(&sink, &player, None);
+				return Ok(Box::new(player));
+			}
+			let player = VideoPlayer::with_options(&uri, live, options, player_sink)?;
+			announce(&sink, &player, Some(uri));
+			Ok(Box::new(player))
+		};
+		player.open(open).then(move |result| {
+			if let Err(err) = result {
+				report_error(&event_sink, err);
+			}
+		});
+		self.scale_down(&player);
+		self.orient(&player);
+	}
+
+	/// Have the players scale the frames down to `size`, the size the frame
+	/// is shown at in pixels, or keep them at the size of the video for
+	/// `None`. Nothing changes if the native resolution is wanted anyway.
+	fn set_render_size(&mut self, size: Option<Size>) {
+		if self.options.native_resolution {
+			return;
+		}
+		// Steps spare renegotiating on every pixel a window is resized by.
+		let step = |length: f64| {
+			let length = (length.ceil().max(1.0) as u32 + RENDER_STEP - 1) / RENDER_STEP;
+			length * RENDER_STEP
+		};
+		let render_size = size.map(|size| (step(size.width), step(size.height)));
+		if render_size == self.render_size {
+			return;
+		}
+		self.render_size = render_size;
+		let timeshift = self.timeshift.as_ref().and_then(|timeshift| timeshift.player.as_ref());
+		for player in self.player.iter().chain(timeshift) {
+			self.scale_down(player);
+		}
+	}
+
+	/// Tell `player` the size to scale the frames down to.
+	pub(crate) fn scale_down(&self, player: &PlayerHandle) {
+		if !self.options.native_resolution {
+			player.set_render_size(self.render_size).then(log_failure("scale the video"));
+		}
+	}
+
+	/// Tell `player` how to rotate and mirror the frames, unless they are
+	/// shown as they are.
+	pub(crate) fn orient(&self, player: &PlayerHandle) {
+		if self.orientation != Orientation::default() {
+			player.set_orientation(self.orientation).then(log_failure("orient the video"));
+		}
+	}
+
+	/// Sink for the players of the view, putting frames in the slot of its
+	/// scheduler.
+	pub(crate) fn view_sink<S: CommandSink>(&self, event_sink: S) -> ViewSink<S> {
+		ViewSink { event_sink, slot: self.scheduler.slot() }
+	}
+
+	/// Update the recording status, the position in the timeshift buffer and
+	/// the frames dropped or shown late.
+	fn update_status(&self, data: &mut VideoViewState) {
+		data.recording = self.player.as_ref().and_then(|player| player.status().recording);
+		data.timeshift = self.timeshift_status();
+		data.presentation = self.scheduler.stats();
+		if let Some(status) = data.timeshift {
+			// The slider of live streams moves in the timeshift buffer.
+			data.duration = status.buffered;
+			data.position = status.buffered.saturating_sub(status.behind);
+			if data.percentage == data.pre_percentage {
+				let percentage = if status.buffered > 0 {
+					data.position as f64 / status.buffered as f64
+				} else {
+					1.0
+				};
+				data.percentage = percentage;
+				data.pre_percentage = percentage;
+			}
+		}
+	}
+
+	/// Show the frame due now, if a new one is.
+	fn anim_frame(&mut self, ctx: &mut impl ViewCtx) {
+		if let Some(frame) = self.scheduler.take_due() {
+			let resized = self.shown.as_ref().map_or(true, |(shown, _)| {
+				(shown.width, shown.height, shown.pixel_aspect_ratio)
+					!= (frame.width, frame.height, frame.pixel_aspect_ratio)
+			});
+			// The image gets made from the frame when it is painted.
+			self.shown = Some((frame, None));
+			if resized {
+				ctx.request_layout();
+			}
+			ctx.request_paint();
+		}
+		if self.scheduler.pending() {
+			ctx.request_anim_frame();
+		}
+	}
+
+	/// Handle `command`, from the players or the controls.
+	fn command(&mut self, ctx: &mut impl ViewCtx, command: &Command, data: &mut VideoViewState) {
+		if let Some(_) = command.get(cmd::VIDEO_FRAME) {
+			if let Some(status) = self.player.as_ref().map(PlayerHandle::status) {
+				if data.live {
+					// Behind the live edge the status timer keeps the position.
+					if data.timeshift.is_none() {
+						data.position = status.position.as_secs();
 					}
+				} else {
 					if data.percentage == data.pre_percentage {
-						let position = player.position().as_secs();
-						let percentage = position as f64 / data.duration as f64;
+						let position = status.position.as_secs();
+						let percentage = if data.duration > 0 {
+							position as f64 / data.duration as f64
+						} else {
+							0.0
+						};
 						data.position = position;
 						data.percentage = percentage;
 						data.pre_percentage = percentage;
 					}
-					if data.position == data.duration {
+					let ended = data.duration > 0 && data.position == data.duration;
+					if ended && !status.growing {
 						ctx.submit_command(cmd::PLAY_PAUSE)
 					}
 				}
-				self.image.set_image_data(image_buf.to_owned());
-				ctx.request_paint();
 			}
-			if let Some(_) = command.get(cmd::PLAY_PAUSE) {
-				if let Some(ref player) = self.player {
-					player.pipeline.set_state(gst::State::Paused);
-					data.state = VideoPlayerState::Paused;
+			// The frame waits in the slot for the animation frame it is due at.
+			ctx.request_anim_frame();
+		}
+		if let Some(point) = command.get(cmd::INSPECT_PIN) {
+			let (mode, transform) = (data.display_mode, data.view_transform);
+			match self.sample_at(*point, mode, transform, ctx.size(), ctx.scale()) {
+				Some(sample) => Arc::make_mut(&mut data.samples).push(sample),
+				None => log::warn!("No pixel of the frame to pin at {:?}", point),
+			}
+		}
+		if let Some(_) = command.get(cmd::PLAY_PAUSE) {
+			if data.live && self.timeshift.is_none() && self.can_timeshift() {
+				// The stream keeps being buffered while paused.
+				self.pause_live();
+				data.state = VideoPlayerState::Paused;
+			} else if let Some(player) = self.shown_player() {
+				player.pause().then(log_failure("pause"));
+				data.state = VideoPlayerState::Paused;
+			}
+			// ctx.request_paint();
+		}
+		if let Some(_) = command.get(cmd::PLAY_RESUME) {
+			if let Some(Timeshifted { player: None, start }) = self.timeshift {
+				if let Err(err) = self.shift_to(start, true, ctx.command_sink()) {
+					log::error!("Failed to play the timeshift buffer: {}", err);
+					self.go_live();
 				}
-				// ctx.request_paint();
+				data.state = VideoPlayerState::Playing;
+			} else if let Some(player) = self.shown_player() {
+				player.play().then(log_failure("play"));
+				data.state = VideoPlayerState::Playing;
+			}
+		}
+		if let Some(live) = command.get(cmd::PLAYBACK_LIVE) {
+			data.live = *live;
+		}
+		if let Some(variants) = command.get(cmd::PLAYBACK_VARIANTS) {
+			data.variants = Arc::new(variants.clone());
+		}
+		if let Some(variant) = command.get(cmd::PLAYBACK_VARIANT_CHANGED) {
+			data.current_variant = Some(*variant);
+		}
+		if let Some(stats) = command.get(cmd::PLAYBACK_ADAPTIVE_STATS) {
+			data.bandwidth = stats.bandwidth;
+		}
+		if let Some(attempt) = command.get(cmd::PLAYBACK_RECONNECTING) {
+			data.reconnect_attempt = Some(*attempt);
+		}
+		if let Some(_) = command.get(cmd::PLAYBACK_RECONNECTED) {
+			data.reconnect_attempt = None;
+			data.error = None;
+		}
+		if let Some(devices) = command.get(cmd::CAPTURE_DEVICES) {
+			data.devices = devices.clone();
+		}
+		if let Some(selection) = command.get(cmd::CAPTURE_SELECTED) {
+			self.capture = Some(selection.clone());
+			data.capture = selection.clone();
+		}
+		if let Some(error) = command.get(cmd::PLAYBACK_ERROR) {
+			data.reconnect_attempt = None;
+			data.error = Some(error.clone());
+		}
+		if let Some(_) = command.get(cmd::PLAY_LIVE_EDGE) {
+			if self.timeshift.is_some() {
+				// The live stream never stopped, it only has to be shown again.
+				self.go_live();
+				data.state = VideoPlayerState::Playing;
+			} else if let Some(ref player) = self.player {
+				player.jump_to_live_edge().then(log_failure("jump to the live edge"));
+				data.state = VideoPlayerState::Playing;
+			}
+		}
+		if let Some(info) = command.get(cmd::PLAYBACK_BUFFERING) {
+			data.buffer_percentage = info.percent;
+			data.buffered_ranges = Arc::new(info.ranges.clone());
+			// The player holds the pipeline paused until the buffer is full,
+			// live sources keep playing.
+			let buffering = !data.live && info.percent < 100;
+			if buffering && !data.buffering {
+				ctx.submit_command(cmd::PLAYBACK_BLOCKED);
+			}
+			data.buffering = buffering;
+		}
+		if let Some(duration) = command.get(cmd::PLAYBACK_DURATION) {
+			data.duration = *duration;
+		}
+		if let Some(_) = command.get(cmd::PLAY) {
+			if let Some(ref player) = self.player {
+				player.play().then(log_failure("play"));
+			}
+			// ctx.request_paint();
+		}
+		if let Some(_) = command.get(cmd::PLAYBACK_ENDED) {
+			if let Err(err) = self.continue_timeshift(ctx.command_sink()) {
+				log::error!("Failed to play the timeshift buffer: {}", err);
+				self.go_live();
+			}
+		}
+		if let Some(_) = command.get(cmd::RECORD_START) {
+			let path = self.options.recording.dir.join(recording_file_name());
+			match self.player {
+				Some(ref player) => player.start_recording(path).then(log_failure("record")),
+				None => log::error!("Failed to record: nothing is playing"),
+			}
+		}
+		if let Some(_) = command.get(cmd::RECORD_STOP) {
+			if let Some(ref player) = self.player {
+				player.stop_recording().then(log_failure("stop recording"));
 			}
-			if let Some(_) = command.get(cmd::PLAY_RESUME) {
-				if let Some(ref player) = self.player {
-					player.pipeline.set_state(gst::State::Playing);
-					data.state = VideoPlayerState::Playing;
+			data.recording = None;
+		}
+		if let Some(position) = command.get(cmd::PLAY_SEEK).filter(|_| data.live) {
+			// Live streams only seek in their timeshift buffer.
+			if let Some(time) = self.timeshift_time(*position) {
+				let playing = data.state == VideoPlayerState::Playing;
+				if let Err(err) = self.shift_to(time, playing, ctx.command_sink()) {
+					log::error!("Failed to seek in the timeshift buffer: {}", err);
+					self.go_live();
 				}
+				data.position = *position;
+			}
+		}
+		if let Some(position) = command.get(cmd::PLAY_SEEK) {
+			if let Some(player) = self.player.as_ref().filter(|_| !data.live) {
+				player.seek(Duration::from_secs(*position)).then(log_failure("seek"));
+				data.position = *position;
+			}
+		}
+		if let Some(rate) = command.get(cmd::PLAY_RATE) {
+			if let Some(player) = self.shown_player() {
+				player.set_rate(*rate).then(log_failure("change the rate"));
+			}
+			// ctx.request_paint();
+		}
+	}
+}
+
+/// Granularity of the size frames are scaled down to, in pixels.
+const RENDER_STEP: u32 = 32;
+
+/// How often the recording and timeshift status is updated.
+const STATUS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Name of a new recording, from the time it started.
+fn recording_file_name() -> String {
+	let format = time::macros::format_description!("[year]-[month]-[day]-[hour][minute][second]");
+	let now = time::OffsetDateTime::now_utc();
+	format!("recording-{}.mkv", now.format(format).unwrap_or_default())
+}
+
+/// Where the commands of the players of a view go, from any thread.
+pub(crate) trait CommandSink: Clone + Send + Sync + 'static {
+	fn submit<T: Any + Send>(&self, selector: Selector<T>, payload: T)
+		-> Result<(), ExtEventError>;
+}
+
+impl CommandSink for ExtEventSink {
+	fn submit<T: Any + Send>(
+		&self,
+		selector: Selector<T>,
+		payload: T,
+	) -> Result<(), ExtEventError> {
+		self.submit_command(selector, payload, Target::Auto)
+	}
+}
+
+/// What handling the commands of a view needs of its context.
+pub(crate) trait ViewCtx {
+	type Sink: CommandSink;
+
+	fn submit_command(&mut self, command: impl Into<Command>);
+
+	fn request_anim_frame(&mut self);
+
+	fn request_layout(&mut self);
+
+	fn request_paint(&mut self);
+
+	fn size(&self) -> Size;
+
+	fn scale(&self) -> Scale;
+
+	/// Sink for the players opened while handling a command.
+	fn command_sink(&self) -> Self::Sink;
+}
+
+impl ViewCtx for EventCtx<'_, '_> {
+	type Sink = ExtEventSink;
+
+	fn submit_command(&mut self, command: impl Into<Command>) {
+		EventCtx::submit_command(self, command)
+	}
+
+	fn request_anim_frame(&mut self) {
+		EventCtx::request_anim_frame(self)
+	}
+
+	fn request_layout(&mut self) {
+		EventCtx::request_layout(self)
+	}
+
+	fn request_paint(&mut self) {
+		EventCtx::request_paint(self)
+	}
+
+	fn size(&self) -> Size {
+		EventCtx::size(self)
+	}
+
+	fn scale(&self) -> Scale {
+		EventCtx::scale(self)
+	}
+
+	fn command_sink(&self) -> ExtEventSink {
+		self.get_external_handle()
+	}
+}
+
+/// What the players of a view put out. Frames go to the slot of its
+/// scheduler, events are commands.
+#[derive(Clone)]
+pub(crate) struct ViewSink<S = ExtEventSink> {
+	pub(crate) event_sink: S,
+	pub(crate) slot: Arc<FrameSlot>,
+}
+
+impl<S: CommandSink> PlayerSink for ViewSink<S> {
+	fn frame(&self, frame: VideoFrame) -> Result<(), Disconnected> {
+		// A frame still in the slot has woken the view up already.
+		if self.slot.put(frame) {
+			self.event_sink.submit(cmd::VIDEO_FRAME, ()).map_err(|_| Disconnected)?;
+		}
+		Ok(())
+	}
+
+	fn event(&self, event: PlayerEvent) -> Result<(), Disconnected> {
+		let event_sink = &self.event_sink;
+		let sent = match event {
+			PlayerEvent::AdaptiveStats(stats) => {
+				event_sink.submit(cmd::PLAYBACK_ADAPTIVE_STATS, stats)
+			}
+			PlayerEvent::VariantChanged(variant) => {
+				event_sink.submit(cmd::PLAYBACK_VARIANT_CHANGED, variant)
+			}
+			PlayerEvent::Buffering(info) => event_sink.submit(cmd::PLAYBACK_BUFFERING, info),
+			PlayerEvent::Reconnecting(attempt) => {
+				event_sink.submit(cmd::PLAYBACK_RECONNECTING, attempt)
 			}
-			if let Some(duration) = command.get(cmd::PLAYBACK_DURATION) {
-				data.duration = *duration;
+			PlayerEvent::Reconnected => event_sink.submit(cmd::PLAYBACK_RECONNECTED, ()),
+			PlayerEvent::Error(error) => event_sink.submit(cmd::PLAYBACK_ERROR, error),
+			PlayerEvent::Ended => event_sink.submit(cmd::PLAYBACK_ENDED, ()),
+			PlayerEvent::Duration(duration) => {
+				event_sink.submit(cmd::PLAYBACK_DURATION, duration.as_secs())
 			}
-			if let Some(_) = command.get(cmd::PLAY) {
-				if let Some(ref player) = self.player {
-					player.pipeline.set_state(gst::State::Playing);
+		};
+		sent.map_err(|_| Disconnected)
+	}
+}
+
+/// Tell the view about the media `player` opened, which is `uri` if it was
+/// opened from one.
+fn announce<S: CommandSink>(sink: &ViewSink<S>, player: &dyn MediaBackend, uri: Option<url::Url>) {
+	let event_sink = &sink.event_sink;
+	let _ = event_sink.submit(cmd::PLAYBACK_LIVE, player.live());
+	let variants = player.variants();
+	if !variants.is_empty() {
+		let _ = event_sink.submit(cmd::PLAYBACK_VARIANTS, variants);
+	}
+	// Live sources have neither a duration nor a frame to seek to.
+	if !player.live() {
+		let duration = player.duration().as_secs();
+		let _ = event_sink.submit(cmd::PLAYBACK_DURATION, duration);
+		// Test sources have no file to take a thumbnail from.
+		if let Some(uri) = uri.filter(|uri| uri.scheme() != testsrc::SCHEME) {
+			let thumbnail = match Thumbnail::new(uri.as_str(), 7) {
+				Ok(thumbnail) => thumbnail,
+				Err(err) => {
+					log::warn!("No poster frame for {}: {}", uri, err);
+					return;
 				}
-				// ctx.request_paint();
-			}
-			if let Some(position) = command.get(cmd::PLAY_SEEK) {
-				if let Some(ref player) = self.player {
-					if data.state != VideoPlayerState::Playing {
-						player.set_muted(true);
-						player.pipeline.set_state(gst::State::Playing);
-						player
-							.pipeline
-							.seek_simple(
-								gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT,
-								*position * gst::ClockTime::SECOND,
-							)
-							.unwrap();
-						player.pipeline.set_property("mute", false);
-						player.pipeline.state(gst::ClockTime::from_mseconds(20)).0.unwrap();
-						player.pipeline.set_state(gst::State::Paused);
-					} else {
-						player.set_muted(true);
-						player
-							.pipeline
-							.seek_simple(
-								gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT,
-								*position * gst::ClockTime::SECOND,
-							)
-							.unwrap();
-						player.pipeline.set_property("mute", false);
-					}
-					data.position = *position;
+			};
+			// The thumbnail pipeline ran to its end, the frame is there unless
+			// it had none.
+			let frame = match thumbnail.receiver.try_recv() {
+				Ok(frame) => frame,
+				Err(_) => {
+					log::warn!("No poster frame for {}: no frame decoded", uri);
+					return;
 				}
+			};
+			let _ = sink.frame(frame);
+		}
+	}
+}
+
+/// Log why a command sent to the player failed.
+fn log_failure(action: &'static str) -> impl FnOnce(Result<(), VideoError>) + Send {
+	move |result| {
+		if let Err(err) = result {
+			log::error!("Failed to {}: {}", action, err);
+		}
+	}
+}
+
+/// Show why there is nothing to play.
+fn report_error(event_sink: &impl CommandSink, err: VideoError) {
+	log::error!("Failed to create the player: {}", err);
+	let _ = event_sink.submit(cmd::PLAYBACK_ERROR, err.to_string());
+}
+
+/// Turn a media item (URI or local path) into an URI playbin understands.
+pub fn media_uri(item: &str) -> Result<url::Url, VideoError> {
+	url::Url::parse(item).or_else(|_| {
+		let path = std::path::Path::new(item).canonicalize()?;
+		url::Url::from_file_path(path).map_err(|_| VideoError::Uri)
+	})
+}
+
+impl Widget<VideoViewState> for VideoView {
+	fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut VideoViewState, _env: &Env) {
+		if let Event::Timer(token) = event {
+			if *token == self.timer {
+				self.update_status(data);
+				self.timer = ctx.request_timer(STATUS_INTERVAL);
 			}
-			if let Some(rate) = command.get(cmd::PLAY_RATE) {
-				let rate = if *rate == 0.0 { rate + 0.000000001 } else { *rate };
-				if let Some(ref player) = self.player {
-					send_seek_event(&player.pipeline, rate);
-				}
-				// ctx.request_paint();
+		}
+		if let Event::MouseMove(mouse) = event {
+			self.pointer = Some(mouse.pos);
+			if data.loupe || data.inspector {
+				ctx.request_paint();
 			}
 		}
-
-		self.image.event(ctx, event, data, env)
+		if let Event::AnimFrame(_) = event {
+			self.anim_frame(ctx);
+		}
+		if let Event::Command(command) = event {
+			self.command(ctx, command, data);
+		}
 	}
 
 	fn lifecycle(
@@ -133,39 +578,26 @@ impl Widget<VideoViewState> for VideoView {
 		ctx: &mut LifeCycleCtx,
 		event: &LifeCycle,
 		data: &VideoViewState,
-		env: &Env,
+		_env: &Env,
 	) {
 		match event {
 			LifeCycle::WidgetAdded => {
-				println!("uri {:?}", std::path::PathBuf::from(file!()).parent().unwrap());
-				let uri = &url::Url::from_file_path(
-					std::path::PathBuf::from(file!())
-						.parent()
-						.unwrap()
-						.join("../../../.media/test.mp4")
-						.canonicalize()
-						.unwrap(),
-				)
-				.unwrap();
 				/*				let png_data = ImageBuf::from_data(include_bytes!("../../../.media/PicWithAlpha.png")).unwrap();
 
 				let  image = Image::new(png_data)			.fill_mode(FillStrat::Contain)
 					.interpolation_mode(InterpolationMode::Bilinear);*/
-				let thumbnail = Thumbnail::new(uri.as_str(), 7).unwrap();
-				let image_buf = thumbnail.receiver.recv().unwrap();
-
-				let player = VideoPlayer::new(uri, false, ctx.get_external_handle()).unwrap();
-				self.player = Some(player);
-				self.image.set_image_data(image_buf.to_owned());
-				ctx.get_external_handle()
-					.submit_command(cmd::PLAYBACK_DURATION, thumbnail.duration, Target::Auto)
-					.map_err(|e| gstreamer::FlowError::Error)
-					.unwrap();
+				self.open_player(data, ctx.get_external_handle());
+				self.timer = ctx.request_timer(STATUS_INTERVAL);
+			}
+			LifeCycle::HotChanged(false) => {
+				self.pointer = None;
+				if data.loupe || data.inspector {
+					ctx.request_paint();
+				}
 			}
 
 			_ => {}
 		}
-		self.image.lifecycle(ctx, event, data, env)
 	}
 
 	fn update(
@@ -173,13 +605,60 @@ impl Widget<VideoViewState> for VideoView {
 		ctx: &mut UpdateCtx,
 		old_data: &VideoViewState,
 		data: &VideoViewState,
-		env: &Env,
+		_env: &Env,
 	) {
 		/*		if old_data.image_dimensions != data.image_dimensions {
 			ctx.request_layout();
 		}*/
+		// Magnified and inspected frames are kept at the size of the video.
+		if old_data.display_mode != data.display_mode
+			|| old_data.view_transform.is_zoomed() != data.view_transform.is_zoomed()
+			|| old_data.inspector != data.inspector
+		{
+			ctx.request_layout();
+		}
+		if old_data.view_transform != data.view_transform
+			|| old_data.loupe != data.loupe
+			|| !old_data.samples.same(&data.samples)
+		{
+			ctx.request_paint();
+		}
+		if old_data.quality != data.quality {
+			if let Some(ref player) = self.player {
+				player.set_quality(data.quality).then(log_failure("change the quality"));
+			}
+		}
+		let capture_changed =
+			self.capture.as_ref().map_or(false, |capture| *capture != data.capture);
+		if capture_changed && data.capture.video.is_some() {
+			// The devices are only free once the old player is gone.
+			self.timeshift = None;
+			self.capture = Some(data.capture.clone());
+			let event_sink = ctx.get_external_handle();
+			let sink = self.view_sink(event_sink.clone());
+			let (capture, options) = (data.capture.clone(), self.options.clone());
+			let open = move || -> Result<Box<dyn MediaBackend>, VideoError> {
+				let player = VideoPlayer::from_capture(&capture, options, Arc::new(sink.clone()))?;
+				announce(&sink, &player, None);
+				Ok(Box::new(player))
+			};
+			let player = self.player.get_or_insert_with(PlayerHandle::spawn).clone();
+			player.open(open).then(move |result| {
+				if let Err(err) = result {
+					report_error(&event_sink, err);
+				}
+			});
+			self.scale_down(&player);
+			self.orient(&player);
+		}
+		if old_data.orientation != data.orientation {
+			self.orientation = data.orientation;
+			let timeshift = self.timeshift.as_ref().and_then(|timeshift| timeshift.player.as_ref());
+			for player in self.player.iter().chain(timeshift) {
+				player.set_orientation(data.orientation).then(log_failure("orient the video"));
+			}
+		}
 		//TODO
-		self.image.update(ctx, old_data, data, env)
 	}
 
 	fn layout(
@@ -187,16 +666,75 @@ impl Widget<VideoViewState> for VideoView {
 		ctx: &mut LayoutCtx,
 		bc: &BoxConstraints,
 		data: &VideoViewState,
-		env: &Env,
+		_env: &Env,
 	) -> Size {
-		self.image.layout(ctx, bc, data, env)
+		// The view takes the space there is, without a bound the frame sets
+		// the size.
+		let frame = self.shown.as_ref().map_or(Size::ZERO, |(frame, _)| display_size(frame));
+		let size = if bc.is_width_bounded() && bc.is_height_bounded() {
+			bc.max()
+		} else {
+			bc.constrain(frame)
+		};
+		if let Ok(scale) = ctx.window().get_scale() {
+			// Frames shown 1:1, magnified or inspected keep the size of the video.
+			let keep_size = data.view_transform.is_zoomed() || data.inspector;
+			let render_size = Some(data.display_mode)
+				.filter(|mode| *mode != DisplayMode::Native && !keep_size)
+				.map(|mode| self.frame_rect(mode, size, scale).size())
+				.map(|shown| Size::new(shown.width * scale.x(), shown.height * scale.y()));
+			self.set_render_size(render_size);
+		}
+		size
 	}
 
-	fn paint(&mut self, ctx: &mut PaintCtx, data: &VideoViewState, env: &Env) {
-		self.image.paint(ctx, data, env);
+	fn paint(&mut self, ctx: &mut PaintCtx, data: &VideoViewState, _env: &Env) {
+		let view = ctx.size().to_rect();
+		let rect = self.frame_rect(data.display_mode, view.size(), ctx.scale());
+		ctx.fill(view, &Color::BLACK);
+		let (frame, image) = match self.shown {
+			Some((ref frame, ref mut image)) => (frame, image),
+			None => return,
+		};
+		if image.is_none() {
+			// The pixels go to the renderer straight from the decoded buffer.
+			let made = ctx.make_image(
+				frame.width as _,
+				frame.height as _,
+				frame.data.as_slice(),
+				ImageFormat::RgbaSeparate,
+			);
+			match made {
+				Ok(made) => *image = Some(made),
+				Err(err) => log::error!("Failed to make the image of a frame: {}", err),
+			}
+		}
+		if let Some(image) = image {
+			let loupe = self.pointer.filter(|_| data.loupe);
+			paint_frame(ctx, image, rect, data.view_transform, loupe);
+		}
+		if data.inspector {
+			// Pixels are read from the frame, not from what was drawn.
+			let (mode, transform, scale) = (data.display_mode, data.view_transform, ctx.scale());
+			let hovered = self
+				.pointer
+				.and_then(|point| self.sample_at(point, mode, transform, view.size(), scale));
+			paint_inspector(ctx, hovered, &data.samples);
+		}
 	}
 }
-pub struct VideoViewController {}
+/// Handles the pointer and the keys of the view: a click toggles pause, a
+/// drag pans the magnified frame and the wheel zooms.
+#[derive(Debug, Default)]
+pub struct VideoViewController {
+	/// Where the pointer was when the left button got pressed or last moved.
+	drag: Option<Point>,
+	/// The press became a drag, it doesn't toggle pause.
+	dragged: bool,
+}
+
+/// A press moving further than this is a drag, not a click.
+const DRAG_SLOP: f64 = 4.0;
 
 impl<W: Widget<VideoViewState>> Controller<VideoViewState, W> for VideoViewController {
 	fn event(
@@ -208,14 +746,74 @@ impl<W: Widget<VideoViewState>> Controller<VideoViewState, W> for VideoViewContr
 		env: &Env,
 	) {
 		match event {
+			// Keys go to the view unless another widget takes the focus.
+			Event::WindowConnected => ctx.request_focus(),
+			Event::KeyDown(key) => match key.key {
+				KbKey::Character(ref c) if c == "a" => {
+					data.display_mode = data.display_mode.next();
+				}
+				KbKey::Character(ref c) if c == "0" => data.view_transform = Default::default(),
+				KbKey::Character(ref c) if c == "l" => data.loupe = !data.loupe,
+				KbKey::Character(ref c) if c == "i" => data.inspector = !data.inspector,
+				KbKey::Character(ref c) if c == "r" => {
+					data.orientation = data.orientation.rotate_clockwise();
+				}
+				KbKey::Character(ref c) if c == "h" => {
+					data.orientation = data.orientation.flip_horizontal();
+				}
+				KbKey::Character(ref c) if c == "v" => {
+					data.orientation = data.orientation.flip_vertical();
+				}
+				KbKey::Character(ref c) if c == "c" && data.inspector => {
+					let text = samples_text(&data.samples);
+					Application::global().clipboard().put_string(text);
+				}
+				KbKey::Delete if data.inspector => data.samples = Default::default(),
+				_ => {}
+			},
+			Event::MouseDown(mouse) if mouse.button == MouseButton::Right => {
+				ctx.show_context_menu(display_menu(), mouse.window_pos);
+			}
 			Event::MouseDown(mouse) => {
 				if mouse.button == MouseButton::Left {
+					// Pause toggles on release, unless the press became a drag.
+					self.drag = Some(mouse.pos);
+					self.dragged = false;
+					ctx.set_active(true);
+				}
+			}
+			Event::MouseMove(mouse) if ctx.is_active() => {
+				if let Some(last) = self.drag {
+					let delta = mouse.pos - last;
+					if self.dragged || delta.hypot() > DRAG_SLOP {
+						self.dragged = true;
+						self.drag = Some(mouse.pos);
+						if data.view_transform.is_zoomed() {
+							data.view_transform = data.view_transform.pan_by(delta, ctx.size());
+						}
+					}
+				}
+			}
+			Event::MouseUp(mouse) if mouse.button == MouseButton::Left && ctx.is_active() => {
+				ctx.set_active(false);
+				let clicked = self.drag.take().is_some() && !self.dragged;
+				if clicked && data.inspector {
+					// Clicks pin pixels while inspecting.
+					ctx.submit_command(cmd::INSPECT_PIN.with(mouse.pos).to(ctx.widget_id()));
+				} else if clicked {
 					if data.state == VideoPlayerState::Playing {
 						ctx.submit_command(cmd::PLAY_PAUSE)
 					} else {
 						ctx.submit_command(cmd::PLAY_RESUME)
 					}
-					// ctx.set_active(true);
+				}
+			}
+			Event::Wheel(mouse) => {
+				let factor = if mouse.wheel_delta.y < 0.0 { ZOOM_STEP } else { 1.0 / ZOOM_STEP };
+				if mouse.wheel_delta.y != 0.0 {
+					data.view_transform =
+						data.view_transform.zoom_at(factor, mouse.pos, ctx.size());
+					ctx.set_handled();
 				}
 			}
 			_ => {}
@@ -224,336 +822,306 @@ impl<W: Widget<VideoViewState>> Controller<VideoViewState, W> for VideoViewContr
 	}
 }
 
-impl Drop for VideoPlayer {
-	fn drop(&mut self) {
-		self.pipeline.set_state(gst::State::Null).expect("failed to set state");
+#[cfg(test)]
+mod tests {
+	use std::{
+		collections::VecDeque,
+		mem,
+		sync::{mpsc, Mutex},
+	};
+
+	use super::*;
+	use crate::{
+		gui::data::video::{BufferingInfo, PresentStats},
+		media::fake::{FakeBackend, FakeMedia},
+	};
+
+	const TIMEOUT: Duration = Duration::from_secs(10);
+
+	/// A command submitted from a player thread, made once it is delivered.
+	type Submitted = Box<dyn FnOnce() -> Command + Send>;
+
+	/// Queue of the commands the players of a view submitted.
+	#[derive(Clone, Default)]
+	struct Commands(Arc<Mutex<VecDeque<Submitted>>>);
+
+	impl Commands {
+		fn pop(&self) -> Option<Command> {
+			self.0.lock().unwrap().pop_front().map(|submitted| submitted())
+		}
 	}
-}
 
-impl VideoPlayer {
-	/// Create a new video player from a given video which loads from `uri`.
-	///
-	/// If `live` is set then no duration is queried (as this will result in an
-	/// error and is non-sensical for live streams). Set `live` if the streaming
-	/// source is indefinite (e.g. a live stream). Note that this will cause the
-	/// duration to be zero.
-	pub fn new(uri: &url::Url, live: bool, event_sink: ExtEventSink) -> Result<Self, VideoError> {
-		// Initialize GStreamer
-		gst::init()?;
-
-		// Build the pipeline
-		let uri =
-			"https://www.freedesktop.org/software/gstreamer-sdk/data/media/sintel_trailer-480p.webm";
-		let uri = url::Url::from_file_path(
-			std::path::PathBuf::from(file!())
-				.parent()
-				.unwrap()
-				.join("../../../.media/test.mp4")
-				.canonicalize()
-				.unwrap(),
-		)
-		.unwrap();
-		println!("{:?}", uri);
-		let pipeline = gst::ElementFactory::make("playbin", None).unwrap();
-		pipeline.set_property("uri", uri.as_str());
-		/// ************************** audio
-		/// ****************************************
-		// Create elements that go inside the sink bin
-		let equalizer = gst::ElementFactory::make("equalizer-3bands", Some("equalizer"))
-			.expect("Could not create equalizer element.");
-		let convert = gst::ElementFactory::make("audioconvert", Some("convert"))
-			.expect("Could not create audioconvert element.");
-		let sink = gst::ElementFactory::make("autoaudiosink", Some("audio_sink"))
-			.expect("Could not create autoaudiosink element.");
-
-		// Create the sink bin, add the elements and link them
-		let bin = gst::Bin::new(Some("audio_sink_bin"));
-		bin.add_many(&[&equalizer, &convert, &sink]).unwrap();
-		gst::Element::link_many(&[&equalizer, &convert, &sink]).expect("Failed to link elements.");
-
-		let pad = equalizer.static_pad("sink").expect("Failed to get a static pad from equalizer.");
-		let ghost_pad = gst::GhostPad::with_target(Some("sink"), &pad).unwrap();
-		ghost_pad.set_active(true)?;
-		bin.add_pad(&ghost_pad)?;
-
-		// Configure the equalizer
-		equalizer.set_property("band1", -24.0);
-		equalizer.set_property("band2", -24.0);
-
-		pipeline.set_property("audio-sink", &bin);
-
-		/// ************************** video
-		/// ****************************************
-		// Create elements that go inside the sink bin
-		let queue = gst::ElementFactory::make("queue", None).expect("Could not create queue element.");
-		let convert = gst::ElementFactory::make("videoconvert", None)
-			.expect("Could not create videoconvert element.");
-		let scale = gst::ElementFactory::make("videoscale", None)
-			.expect("Could not create videoscale element.");
-		// let sink = gst::ElementFactory::make("autovideosink", None)
-		// 	.map_err(|_| MissingElement("autovideosink"))?;
-		let sink = gst::ElementFactory::make("appsink", None)
-			.expect("Could not create autovideosink element.");
-
-		// Create the sink bin, add the elements and link them
-		let bin = gst::Bin::new(Some("video_sink_bin"));
-		bin.add_many(&[&queue, &convert, &scale, &sink]).unwrap();
-		gst::Element::link_many(&[&queue, &convert, &scale, &sink])
-			.expect("Failed to link elements.");
-
-		let pad = queue.static_pad("sink").expect("Failed to get a static pad from equalizer.");
-		let ghost_pad = gst::GhostPad::with_target(Some("sink"), &pad).unwrap();
-		ghost_pad.set_active(true)?;
-		bin.add_pad(&ghost_pad)?;
-
-		let video_sink = sink
-			.dynamic_cast::<gst_app::AppSink>()
-			.expect("Sink element is expected to be an appsink!");
-		// caps=video/x-raw,format=BGRA,pixel-aspect-ratio=1/1
-		video_sink.set_caps(Some(&gst::Caps::new_simple(
-			"video/x-raw",
-			&[("format", &"RGBA"), ("pixel-aspect-ratio", &gst::Fraction::from((1, 1)))],
-		)));
-		video_sink.set_callbacks(
-			gst_app::AppSinkCallbacks::builder()
-				.new_sample(move |sink| {
-					let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
-					let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
-					let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
-
-					let pad = sink.static_pad("sink").ok_or(gst::FlowError::Error)?;
-
-					let caps = pad.current_caps().ok_or(gst::FlowError::Error)?;
-					let s = caps.structure(0).ok_or(gst::FlowError::Error)?;
-					let width = s.get::<i32>("width").map_err(|_| gst::FlowError::Error)?;
-					let height = s.get::<i32>("height").map_err(|_| gst::FlowError::Error)?;
-					// println!("W: {:?}, H: {:?}", width, height);
-					// Send original and processed image.
-					let image = ImageBuf::from_raw(
-						map.as_slice().to_owned(),
-						ImageFormat::RgbaSeparate,
-						width as _,
-						height as _,
-					);
-					event_sink
-						.submit_command(cmd::VIDEO_FRAME, image, Target::Auto)
-						.map_err(|e| gstreamer::FlowError::Error)?;
-					// let position = 		std::time::Duration::from_nanos(
-					// 	pipeline.query_position::<gst::ClockTime>().map_or(0, |pos| pos.nseconds()),
-					// ).as_secs();
-					// event_sink
-					// 	.submit_command(cmd::PLAYBACK_PROGRESS, position, Target::Auto)
-					// 	.map_err(|e| gstreamer::FlowError::Error)?;
-
-					Ok(gst::FlowSuccess::Ok)
-				})
-				.build(),
-		);
-
-		// Configure the equalizer
-		equalizer.set_property("band1", -24.0);
-		equalizer.set_property("band2", -24.0);
-
-		pipeline.set_property("video-sink", &bin);
-		pipeline.set_property("volume", 0.9);
-
-		pipeline.set_state(gst::State::Paused)?;
-
-		// wait for up to 5 seconds until the decoder gets the source capabilities
-		pipeline.state(gst::ClockTime::from_seconds(5)).0?;
-
-		// extract resolution and framerate
-		// TODO(jazzfool): maybe we want to extract some other information too?
-		let caps = pad.current_caps().ok_or(VideoError::Caps)?;
-		let s = caps.structure(0).ok_or(VideoError::Caps)?;
-		let width = s.get::<i32>("width").map_err(|_| VideoError::Caps)?;
-		let height = s.get::<i32>("height").map_err(|_| VideoError::Caps)?;
-		let framerate = s.get::<gst::Fraction>("framerate").map_err(|_| VideoError::Caps)?;
-
-		/*		let duration = if !live {
-			std::time::Duration::from_nanos(
-				pipeline.query_duration::<gst::ClockTime>().ok_or(VideoError::Duration)?.nseconds(),
-			)
-		} else {
-			std::time::Duration::from_secs(0)
-		};*/
-
-		Ok(VideoPlayer {
-			bus: pipeline.bus().unwrap(),
-			pipeline,
-
-			volume: 0.0,
-			width,
-			height,
-			framerate: num_rational::Rational32::new(
-				framerate.numer() ,
-				framerate.denom() ,
-			)
-				.to_f64().unwrap(/* if the video framerate is bad then it would've been implicitly caught far earlier */),
-			duration: std::time::Duration::from_secs(0),
-
-			paused: false,
-			muted: false,
-			looping: false,
-			is_eos: false,
-			restart_stream: false,
-		})
-	}
-
-	/// Get the size/resolution of the video as `(width, height)`.
-	#[inline(always)]
-	pub fn size(&self) -> (i32, i32) {
-		(self.width, self.height)
-	}
-
-	/// Set the volume multiplier of the audio.
-	/// `0.0` = 0% volume, `1.0` = 100% volume.
-	///
-	/// This uses a linear scale, for example `0.5` is perceived as half as
-	/// loud.
-	pub fn set_volume(&mut self, volume: f64) {
-		self.pipeline.set_property("volume", &volume);
+	impl CommandSink for Commands {
+		fn submit<T: Any + Send>(
+			&self,
+			selector: Selector<T>,
+			payload: T,
+		) -> Result<(), ExtEventError> {
+			self.0.lock().unwrap().push_back(Box::new(move || selector.with(payload)));
+			Ok(())
+		}
 	}
 
-	/// Set if the audio is muted or not, without changing the volume.
-	pub fn set_muted(&self, muted: bool) {
-		// self.muted = muted;
-		self.pipeline.set_property("mute", &muted);
+	/// Context of a view without a window, keeping what it was asked for.
+	struct TestCtx {
+		commands: Commands,
+		submitted: VecDeque<Command>,
+		anim_frame: bool,
 	}
 
-	/// Get if the stream ended or not.
-	#[inline(always)]
-	pub fn eos(&self) -> bool {
-		self.is_eos
+	impl ViewCtx for TestCtx {
+		type Sink = Commands;
+
+		fn submit_command(&mut self, command: impl Into<Command>) {
+			self.submitted.push_back(command.into());
+		}
+
+		fn request_anim_frame(&mut self) {
+			self.anim_frame = true;
+		}
+
+		fn request_layout(&mut self) {}
+
+		fn request_paint(&mut self) {}
+
+		fn size(&self) -> Size {
+			Size::new(640.0, 480.0)
+		}
+
+		fn scale(&self) -> Scale {
+			Scale::new(1.0, 1.0)
+		}
+
+		fn command_sink(&self) -> Commands {
+			self.commands.clone()
+		}
 	}
 
-	/// Set if the media will loop or not.
-	#[inline(always)]
-	pub fn set_looping(&mut self, looping: bool) {
-		self.looping = looping;
+	/// A view playing a [`FakeBackend`], with the commands delivered by hand.
+	struct Harness {
+		view: VideoView,
+		data: VideoViewState,
+		commands: Commands,
+		fake: FakeBackend,
+		/// The sink the view handed the fake, to send events past the script.
+		sink: SharedSink,
 	}
 
-	/// Set if the media is paused or not.
-	pub fn set_paused(&mut self, paused: bool) {
-		self.pipeline
-			.set_state(if paused {
-				gst::State::Paused
-			} else {
-				gst::State::Playing
-			})
-			.unwrap(/* state was changed in ctor; state errors caught there */);
-		self.paused = paused;
+	impl Harness {
+		/// Open the default [`FakeMedia`] with the events of `script`.
+		fn open(script: Vec<(Duration, PlayerEvent)>) -> Self {
+			let (opened, receiver) = mpsc::channel();
+			let mut view =
+				VideoView::new().with_player_factory(move |_options, sink: SharedSink| {
+					let fake = FakeBackend::new(FakeMedia::default(), sink.clone());
+					for (at, event) in script {
+						fake.script(at, event);
+					}
+					let _ = opened.send((fake.clone(), sink));
+					Ok(fake)
+				});
+			let data = state();
+			let commands = Commands::default();
+			view.open_player(&data, commands.clone());
+			let (fake, sink) = receiver.recv_timeout(TIMEOUT).expect("the player opened");
+			let mut harness = Self { view, data, commands, fake, sink };
+			harness.sync();
+			harness.pump();
+			harness
+		}
+
+		/// Wait until the player ran what it was sent and refreshed its
+		/// status.
+		fn sync(&self) {
+			let player = self.view.player.as_ref().expect("a player");
+			// The status is refreshed after every job, so before the second.
+			player.run(|_| Ok(()));
+			player.run(|_| Ok(())).wait().expect("the player runs");
+		}
 
-		// Set restart_stream flag to make the stream restart on the next
-		// Message::NextFrame
-		if self.is_eos && !paused {
-			self.restart_stream = true;
+		/// Hand the view `command` and then what it and the player submitted.
+		fn command(&mut self, command: impl Into<Command>) {
+			let mut ctx = self.ctx();
+			ctx.submitted.push_back(command.into());
+			self.deliver(ctx);
+			self.sync();
+		}
+
+		/// Hand the view what the player submitted, showing the frames due.
+		fn pump(&mut self) {
+			let ctx = self.ctx();
+			self.deliver(ctx);
+		}
+
+		fn ctx(&self) -> TestCtx {
+			TestCtx {
+				commands: self.commands.clone(),
+				submitted: VecDeque::new(),
+				anim_frame: false,
+			}
+		}
+
+		fn deliver(&mut self, mut ctx: TestCtx) {
+			while let Some(command) = ctx.submitted.pop_front().or_else(|| self.commands.pop()) {
+				self.view.command(&mut ctx, &command, &mut self.data);
+				if mem::take(&mut ctx.anim_frame) {
+					self.view.anim_frame(&mut ctx);
+				}
+			}
+		}
+
+		/// Play for `elapsed` on the clock of the fake.
+		fn advance(&mut self, elapsed: Duration) {
+			self.fake.advance(elapsed);
+			self.sync();
 		}
 	}
 
-	/*	/// Get if the media is paused or not.
-	#[inline(always)]
-	pub fn paused(&self) -> bool {
-		self.paused
-	}*/
+	fn state() -> VideoViewState {
+		VideoViewState {
+			state: VideoPlayerState::Paused,
+			current_item: "fake:".to_string(),
+			duration: 0,
+			position: 0,
+			percentage: 0.0,
+			pre_percentage: 0.0,
+			seeking_enabled: true,
+			rate: 1.0,
+			rate_set: false,
+			buffering: false,
+			buffer_percentage: 0,
+			buffered_ranges: Default::default(),
+			live: false,
+			variants: Default::default(),
+			quality: Default::default(),
+			current_variant: None,
+			bandwidth: 0,
+			reconnect_attempt: None,
+			error: None,
+			devices: Default::default(),
+			capture: Default::default(),
+			recording: None,
+			timeshift: None,
+			presentation: Default::default(),
+			display_mode: Default::default(),
+			view_transform: Default::default(),
+			loupe: false,
+			inspector: false,
+			samples: Default::default(),
+			orientation: Default::default(),
+		}
+	}
 
-	/// Jumps to a specific position in the media.
-	/// The seeking is not perfectly accurate.
-	pub fn seek(&mut self, position: impl Into<Position>) -> Result<(), Error> {
-		self.pipeline.seek_simple(gst::SeekFlags::FLUSH, position.into())?;
-		Ok(())
+	fn buffering(percent: i32) -> PlayerEvent {
+		PlayerEvent::Buffering(BufferingInfo { percent, ranges: Vec::new() })
+	}
+
+	#[test]
+	fn opening_announces_the_media() {
+		let harness = Harness::open(Vec::new());
+		assert_eq!(harness.data.duration, 10);
+		assert!(!harness.data.live);
+		assert_eq!(harness.data.error, None);
+		// The prerolled frame is shown.
+		let (shown, _) = harness.view.shown.as_ref().expect("a frame is shown");
+		assert_eq!((shown.width, shown.height), (64, 48));
+	}
+
+	#[test]
+	fn play_and_pause_toggle() {
+		let mut harness = Harness::open(Vec::new());
+		assert!(!harness.fake.is_playing());
+
+		harness.command(cmd::PLAY_RESUME);
+		assert!(harness.fake.is_playing());
+		assert_eq!(harness.data.state, VideoPlayerState::Playing);
+
+		harness.command(cmd::PLAY_PAUSE);
+		assert!(!harness.fake.is_playing());
+		assert_eq!(harness.data.state, VideoPlayerState::Paused);
+
+		harness.command(cmd::PLAY_RESUME);
+		assert!(harness.fake.is_playing());
+		assert_eq!(harness.data.state, VideoPlayerState::Playing);
 	}
-	pub fn position(&self) -> std::time::Duration {
-		std::time::Duration::from_nanos(
-			self.pipeline.query_position::<gst::ClockTime>().map_or(0, |pos| pos.nseconds()),
-		)
-		.into()
+
+	#[test]
+	fn slider_follows_the_position() {
+		let mut harness = Harness::open(Vec::new());
+		harness.command(cmd::PLAY_RESUME);
+		harness.advance(Duration::from_secs(4));
+		harness.pump();
+		assert_eq!(harness.data.position, 4);
+		assert_eq!(harness.data.percentage, 0.4);
+		assert_eq!(harness.data.pre_percentage, 0.4);
+
+		// Paused, the clock doesn't move the slider.
+		harness.command(cmd::PLAY_PAUSE);
+		harness.advance(Duration::from_secs(2));
+		harness.pump();
+		assert_eq!(harness.data.position, 4);
 	}
-	/*
-	/// Get the current playback position in time.
-	pub fn position(&self) -> std::time::Duration {
-		std::time::Duration::from_nanos(
-			self.pipeline.query_position::<gst::ClockTime>().map_or(0, |pos| pos.nseconds()),
-		)
-		.into()
+
+	#[test]
+	fn ending_pauses() {
+		let mut harness = Harness::open(Vec::new());
+		harness.command(cmd::PLAY_RESUME);
+		harness.advance(Duration::from_secs(11));
+		harness.pump();
+		harness.sync();
+		assert_eq!(harness.data.position, 10);
+		assert_eq!(harness.data.percentage, 1.0);
+		assert_eq!(harness.data.state, VideoPlayerState::Paused);
+		assert!(!harness.fake.is_playing());
 	}
 
-	/// Get the media duration.
-	#[inline(always)]
-	pub fn duration(&self) -> std::time::Duration {
-		self.duration
-	}*/
+	#[test]
+	fn buffering_shows_until_the_buffer_is_full() {
+		let mut harness = Harness::open(vec![(Duration::from_secs(2), buffering(40))]);
+		harness.command(cmd::PLAY_RESUME);
+		harness.advance(Duration::from_secs(3));
+		harness.pump();
+		harness.sync();
+		assert!(harness.data.buffering);
+		assert_eq!(harness.data.buffer_percentage, 40);
+		// Pausing while buffering is up to the player.
+		assert!(harness.fake.is_playing());
+		assert_eq!(harness.data.state, VideoPlayerState::Playing);
 
-	/*	/// Generates a list of thumbnails based on a set of positions in the media.
-	///
-	/// Slow; only needs to be called once for each instance.
-	/// It's best to call this at the very start of playback, otherwise the
-	/// position may shift.
-	pub fn thumbnails(&mut self, positions: &[Position]) -> Result<Vec<img::Handle>, VideoError> {
-		let paused = self.paused();
-		let pos = self.position();
-		self.set_paused(false);
-		let out = positions
-			.iter()
-			.map(|&pos| {
-				self.seek(pos)?;
-				self.wait.recv().map_err(|_| VideoError::Sync)?;
-				Ok(self.frame_image())
-			})
-			.collect();
-		self.set_paused(paused);
-		self.seek(pos)?;
-		out
-	}*/
-
-	/*	/// Restarts a stream; seeks to the first frame and unpauses, sets the `eos`
-	/// flag to false.
-	pub fn restart_stream(&mut self) -> Result<(), VideoError> {
-		self.is_eos = false;
-		self.set_paused(false);
-		self.seek(0)?;
-		Ok(())
-	}*/
-}
-fn send_seek_event(pipeline: &Element, rate: f64) -> bool {
-	// Obtain the current position, needed for the seek event
-	let position = match pipeline.query_position() {
-		Some(pos) => pos,
-		None => {
-			eprintln!("Unable to retrieve current position...\r");
-			return false;
-		}
-	};
+		harness.sink.event(buffering(100)).unwrap();
+		harness.pump();
+		harness.sync();
+		assert!(!harness.data.buffering);
+		assert_eq!(harness.data.buffer_percentage, 100);
+		assert!(harness.fake.is_playing());
+	}
 
-	// Create the seek event
-	let seek_event = if rate > 0. {
-		Seek::new(
-			rate,
-			SeekFlags::FLUSH | SeekFlags::ACCURATE,
-			SeekType::Set,
-			position,
-			SeekType::End,
-			gst::ClockTime::ZERO,
-		)
-	} else {
-		Seek::new(
-			rate,
-			SeekFlags::FLUSH | SeekFlags::ACCURATE,
-			SeekType::Set,
-			position,
-			SeekType::Set,
-			position,
-		)
-	};
+	#[test]
+	fn buffering_of_live_sources_shows_no_spinner() {
+		let mut harness = Harness::open(Vec::new());
+		harness.data.live = true;
+		harness.sink.event(buffering(40)).unwrap();
+		harness.pump();
+		assert!(!harness.data.buffering);
+		assert_eq!(harness.data.buffer_percentage, 40);
+	}
+
+	#[test]
+	fn counts_dropped_and_late_frames() {
+		let mut harness = Harness::open(Vec::new());
+		harness.command(cmd::PLAY_RESUME);
+
+		// Without animation frames every frame but the last of a second is
+		// overtaken in the slot.
+		harness.advance(Duration::from_secs(1));
+		harness.view.update_status(&mut harness.data);
+		assert_eq!(harness.data.presentation, PresentStats { dropped: 24, late: 0 });
 
-	// If we have not done so, obtain the sink through which we will send the seek
-	// events
-	if let Ok(Some(video_sink)) = pipeline.try_property::<Option<Element>>("video-sink") {
-		println!("Current rate: {}\r", rate);
-		// Send the event
-		video_sink.send_event(seek_event)
-	} else {
-		eprintln!("Failed to update rate...\r");
-		false
+		// Frame 26 is due at 1.04 s and shown at 1.07 s.
+		harness.pump();
+		harness.advance(Duration::from_millis(70));
+		harness.pump();
+		harness.view.update_status(&mut harness.data);
+		assert_eq!(harness.data.presentation, PresentStats { dropped: 24, late: 1 });
 	}
 }
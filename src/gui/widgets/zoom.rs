@@ -0,0 +1,184 @@
+use druid::{
+	kurbo::Circle,
+	piet::{InterpolationMode, PietImage},
+	Affine, Color, PaintCtx, Point, Rect, RenderContext, Size, Vec2,
+};
+
+use crate::gui::data::video::ViewTransform;
+
+/// Largest magnification.
+const MAX_ZOOM: f64 = 16.0;
+
+/// Magnification of one step of the mouse wheel.
+pub(crate) const ZOOM_STEP: f64 = 1.25;
+
+/// Beyond this magnification the pixels are shown as blocks, not smoothed.
+const SHARP_ZOOM: f64 = 2.0;
+
+/// Radius of the loupe.
+const LOUPE_RADIUS: f64 = 80.0;
+
+/// Magnification of the loupe, on top of the zoom.
+const LOUPE_ZOOM: f64 = 4.0;
+
+impl ViewTransform {
+	/// Whether the frame is magnified.
+	pub fn is_zoomed(&self) -> bool {
+		self.zoom > 1.0
+	}
+
+	/// Magnify by `factor`, keeping `center` where it is in a view of `size`.
+	pub fn zoom_at(self, factor: f64, center: Point, size: Size) -> Self {
+		let zoom = (self.zoom * factor).clamp(1.0, MAX_ZOOM);
+		let center = center.to_vec2();
+		let pan = center - (center - self.pan) * (zoom / self.zoom);
+		Self { zoom, pan }.clamped(size)
+	}
+
+	/// Move the magnified view by `delta`, within a view of `size`.
+	pub fn pan_by(self, delta: Vec2, size: Size) -> Self {
+		Self { pan: self.pan + delta, ..self }.clamped(size)
+	}
+
+	/// The transform with the view of `size` still covered by the frame.
+	pub fn clamped(self, size: Size) -> Self {
+		let min = size.to_vec2() * (1.0 - self.zoom);
+		let pan = Vec2::new(self.pan.x.clamp(min.x, 0.0), self.pan.y.clamp(min.y, 0.0));
+		Self { pan, ..self }
+	}
+
+	/// From view coordinates to the coordinates of the magnified view.
+	pub fn affine(&self) -> Affine {
+		Affine::translate(self.pan) * Affine::scale(self.zoom)
+	}
+
+	/// How images are scaled at this magnification.
+	pub fn interpolation(&self) -> InterpolationMode {
+		if self.zoom > SHARP_ZOOM {
+			InterpolationMode::NearestNeighbor
+		} else {
+			InterpolationMode::Bilinear
+		}
+	}
+}
+
+/// Draw `image` in `rect` of a view transformed by `transform`, and the loupe
+/// at `pointer` if there is one.
+pub(crate) fn paint_frame(
+	ctx: &mut PaintCtx,
+	image: &PietImage,
+	rect: Rect,
+	transform: ViewTransform,
+	pointer: Option<Point>,
+) {
+	let view = ctx.size().to_rect();
+	let transform = transform.clamped(view.size());
+	// Cropped or magnified frames don't draw over their neighbours.
+	ctx.with_save(|ctx| {
+		ctx.clip(view);
+		ctx.transform(transform.affine());
+		ctx.draw_image(image, rect, transform.interpolation());
+	});
+	if let Some(pointer) = pointer {
+		let loupe = Circle::new(pointer, LOUPE_RADIUS);
+		let magnify = Affine::translate(pointer.to_vec2())
+			* Affine::scale(LOUPE_ZOOM)
+			* Affine::translate(-pointer.to_vec2());
+		ctx.with_save(|ctx| {
+			ctx.clip(view);
+			ctx.clip(loupe);
+			ctx.fill(loupe, &Color::BLACK);
+			ctx.transform(magnify * transform.affine());
+			ctx.draw_image(image, rect, InterpolationMode::NearestNeighbor);
+		});
+		ctx.stroke(loupe, &Color::WHITE, 2.0);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const SIZE: Size = Size::new(400.0, 300.0);
+
+	/// Where `point` of the view is in the untransformed view.
+	fn unzoomed(transform: ViewTransform, point: Point) -> Point {
+		transform.affine().inverse() * point
+	}
+
+	fn assert_near(a: Point, b: Point) {
+		assert!((a - b).hypot() < 1e-9, "{:?} != {:?}", a, b);
+	}
+
+	/// The transformed frame still covers the whole view.
+	fn assert_covered(transform: ViewTransform) {
+		let affine = transform.affine();
+		let origin = affine * Point::ORIGIN;
+		let end = affine * SIZE.to_vec2().to_point();
+		assert!(origin.x <= 0.0 && origin.y <= 0.0, "{:?}", transform);
+		assert!(end.x >= SIZE.width && end.y >= SIZE.height, "{:?}", transform);
+	}
+
+	#[test]
+	fn zoom_keeps_the_point_under_the_cursor() {
+		let cursor = Point::new(100.0, 50.0);
+		let zoomed = ViewTransform::default().zoom_at(2.0, cursor, SIZE);
+		assert_eq!(zoomed.zoom, 2.0);
+		assert_near(unzoomed(zoomed, cursor), cursor);
+
+		let cursor = Point::new(300.0, 200.0);
+		let before = unzoomed(zoomed, cursor);
+		let zoomed = zoomed.zoom_at(1.5, cursor, SIZE);
+		assert_eq!(zoomed.zoom, 3.0);
+		assert_near(unzoomed(zoomed, cursor), before);
+
+		let unzoomed_view = zoomed.zoom_at(1.0 / 1.5, cursor, SIZE);
+		assert_near(unzoomed(unzoomed_view, cursor), before);
+	}
+
+	#[test]
+	fn zoom_is_clamped() {
+		let center = Point::new(200.0, 150.0);
+		let zoomed = ViewTransform::default().zoom_at(100.0, center, SIZE);
+		assert_eq!(zoomed.zoom, MAX_ZOOM);
+		assert_covered(zoomed);
+
+		let zoomed_out = zoomed.zoom_at(0.001, center, SIZE);
+		assert_eq!(zoomed_out, ViewTransform::default());
+		assert!(!zoomed_out.is_zoomed());
+	}
+
+	#[test]
+	fn zooming_out_near_an_edge_keeps_the_view_covered() {
+		let zoomed = ViewTransform::default().zoom_at(4.0, Point::new(390.0, 290.0), SIZE);
+		assert_covered(zoomed);
+		let zoomed_out = zoomed.zoom_at(0.5, Point::new(10.0, 10.0), SIZE);
+		assert_eq!(zoomed_out.zoom, 2.0);
+		assert_covered(zoomed_out);
+	}
+
+	#[test]
+	fn pan_stays_within_the_frame() {
+		let zoomed = ViewTransform::default().zoom_at(2.0, Point::new(200.0, 150.0), SIZE);
+		assert_eq!(zoomed.pan_by(Vec2::new(1000.0, 1000.0), SIZE).pan, Vec2::ZERO);
+		assert_eq!(zoomed.pan_by(Vec2::new(-1000.0, -1000.0), SIZE).pan, Vec2::new(-400.0, -300.0));
+
+		let panned = zoomed.pan_by(Vec2::new(50.0, -20.0), SIZE);
+		assert_eq!(panned.pan, zoomed.pan + Vec2::new(50.0, -20.0));
+		assert_covered(panned);
+	}
+
+	#[test]
+	fn unzoomed_view_does_not_pan() {
+		let panned = ViewTransform::default().pan_by(Vec2::new(30.0, -30.0), SIZE);
+		assert_eq!(panned, ViewTransform::default());
+	}
+
+	#[test]
+	fn clamping_follows_the_view_size() {
+		let zoomed = ViewTransform::default().zoom_at(2.0, Point::new(400.0, 300.0), SIZE);
+		assert_eq!(zoomed.pan, Vec2::new(-400.0, -300.0));
+		let smaller = zoomed.clamped(Size::new(200.0, 100.0));
+		assert_eq!(smaller.pan, Vec2::new(-200.0, -100.0));
+	}
+}
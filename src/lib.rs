@@ -11,3 +11,9 @@
 
 pub mod gui;
 mod media;
+pub mod settings;
+
+pub use media::{
+	actor, backend, devices, error, fake, filters, frame_source, options, player, present, record,
+	types,
+};
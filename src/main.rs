@@ -10,14 +10,17 @@ use druid_video::{
 use gui::{data::AppState, ui::root_widget};
 
 fn main() -> Result<()> {
-	let window = WindowDesc::new(root_widget())
+	let settings = druid_video::settings::Settings::from_args(std::env::args().skip(1))?;
+	let window = WindowDesc::new(root_widget(settings.options))
 		.title(LocalizedString::new("Window-Title").with_placeholder("druid video"))
 		.window_size((640.0, 480.0));
 	let launcher = AppLauncher::with_window(window);
 	let state = AppState {
 		video: VideoViewState {
 			state: VideoPlayerState::Paused,
-			current_item: "/home/damo/rust/yosef/druid_video/.media/test.mp4".to_string(),
+			current_item: settings
+				.item
+				.unwrap_or_else(|| "/home/damo/rust/yosef/druid_video/.media/test.mp4".to_string()),
 			duration: Default::default(),
 			position: Default::default(),
 			percentage: 0.0,
@@ -25,6 +28,27 @@ fn main() -> Result<()> {
 			seeking_enabled: true,
 			rate: 1.0,
 			rate_set: false,
+			buffering: false,
+			buffer_percentage: 0,
+			buffered_ranges: Default::default(),
+			live: false,
+			variants: Default::default(),
+			quality: Default::default(),
+			current_variant: None,
+			bandwidth: 0,
+			reconnect_attempt: None,
+			error: None,
+			devices: Default::default(),
+			capture: Default::default(),
+			recording: None,
+			timeshift: None,
+			presentation: Default::default(),
+			display_mode: Default::default(),
+			view_transform: Default::default(),
+			loupe: false,
+			inspector: false,
+			samples: Default::default(),
+			orientation: Default::default(),
 		},
 		theme: Theme::Light,
 	};
@@ -0,0 +1,374 @@
+//! A thread owning the player, driven through cloneable handles.
+//!
+//! Opening a pipeline waits for it to preroll, seeking while paused waits for
+//! the frame sought to. [`PlayerHandle`] queues such commands to the thread
+//! owning the [`MediaBackend`] and returns right away. Every command returns
+//! a [`Reply`], which can be awaited, waited for or handed a callback. What
+//! the view reads on every frame comes from a [`PlayerStatus`] the thread
+//! keeps up to date:
+//!
+//! ```text
+//! {PlayerHandle} - {commands} - {actor thread} - {MediaBackend}
+//!                                              - {PlayerStatus} - {PlayerHandle}
+//! ```
+use std::{
+	fmt,
+	future::Future,
+	path::PathBuf,
+	pin::Pin,
+	sync::{mpsc, Arc, Condvar, Mutex},
+	task::{Context, Poll, Waker},
+	thread,
+	time::{Duration, Instant},
+};
+
+use crate::media::{
+	backend::{MediaBackend, Track, TrackKind},
+	error::VideoError,
+	record::Progress,
+	types::{Orientation, Quality, RecordStatus, Variant},
+};
+
+/// How often the status of an open backend is refreshed.
+const STATUS_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How often the variants, the recording and the timeshift buffer are
+/// refreshed without a command. Reading them walks the pipeline and the disk.
+const DETAILS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A command run on the actor thread with the backend, if one is open.
+type Job = Box<dyn FnOnce(&mut Option<Box<dyn MediaBackend>>) + Send>;
+
+/// What the actor knows about its backend, as of the last command or
+/// refresh. The variants, the recording and the timeshift buffer are only
+/// refreshed after commands and about every second in between.
+#[derive(Debug, Clone, Default)]
+pub struct PlayerStatus {
+	/// A backend is open.
+	pub open: bool,
+	pub live: bool,
+	pub growing: bool,
+	pub position: Duration,
+	pub duration: Duration,
+	pub variants: Vec<Variant>,
+	/// The recording in progress.
+	pub recording: Option<RecordStatus>,
+	/// The timeshift buffer of a live stream.
+	pub(crate) timeshift: Option<Progress>,
+}
+
+/// Cloneable handle of a player actor thread.
+///
+/// The thread ends, dropping its backend, once every handle is gone.
+#[derive(Clone)]
+pub struct PlayerHandle {
+	commands: mpsc::Sender<Job>,
+	status: Arc<Mutex<PlayerStatus>>,
+}
+
+impl fmt::Debug for PlayerHandle {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("PlayerHandle").field("status", &*self.status.lock().unwrap()).finish()
+	}
+}
+
+impl PlayerHandle {
+	/// Start an actor thread without a backend.
+	pub fn spawn() -> Self {
+		let (commands, jobs) = mpsc::channel::<Job>();
+		let status = Arc::new(Mutex::new(PlayerStatus::default()));
+		let shared = status.clone();
+		thread::spawn(move || {
+			let mut backend = None;
+			let mut details = Instant::now();
+			loop {
+				let ran = match jobs.recv_timeout(STATUS_INTERVAL) {
+					Ok(job) => {
+						job(&mut backend);
+						true
+					}
+					Err(mpsc::RecvTimeoutError::Timeout) => false,
+					Err(mpsc::RecvTimeoutError::Disconnected) => break,
+				};
+				// Commands may have changed the details, so they are read now.
+				let details_due = ran || details.elapsed() >= DETAILS_INTERVAL;
+				if details_due {
+					details = Instant::now();
+				}
+				refresh(&shared, backend.as_deref(), details_due);
+			}
+		});
+		Self { commands, status }
+	}
+
+	/// Open the backend `open` builds on the actor thread, in place of the
+	/// one open so far.
+	pub fn open(
+		&self,
+		open: impl FnOnce() -> Result<Box<dyn MediaBackend>, VideoError> + Send + 'static,
+	) -> Reply<()> {
+		let (reply, resolve) = Reply::new();
+		self.send(Box::new(move |backend| {
+			// The old pipeline is gone before the new one starts.
+			*backend = None;
+			resolve.send(open().map(|opened| *backend = Some(opened)));
+		}));
+		reply
+	}
+
+	/// Drop the backend, stopping playback.
+	pub fn close(&self) -> Reply<()> {
+		let (reply, resolve) = Reply::new();
+		self.send(Box::new(move |backend| {
+			*backend = None;
+			resolve.send(Ok(()));
+		}));
+		reply
+	}
+
+	/// Run `job` with the backend on the actor thread.
+	pub fn run<R: Send + 'static>(
+		&self,
+		job: impl FnOnce(&mut dyn MediaBackend) -> Result<R, VideoError> + Send + 'static,
+	) -> Reply<R> {
+		let (reply, resolve) = Reply::new();
+		self.send(Box::new(move |backend| {
+			resolve.send(match backend {
+				Some(backend) => job(backend.as_mut()),
+				None => Err(VideoError::Closed),
+			})
+		}));
+		reply
+	}
+
+	pub fn play(&self) -> Reply<()> {
+		self.run(|backend| backend.play())
+	}
+
+	pub fn pause(&self) -> Reply<()> {
+		self.run(|backend| backend.pause())
+	}
+
+	pub fn seek(&self, position: Duration) -> Reply<()> {
+		self.run(move |backend| backend.seek(position))
+	}
+
+	pub fn set_rate(&self, rate: f64) -> Reply<()> {
+		self.run(move |backend| backend.set_rate(rate))
+	}
+
+	pub fn set_volume(&self, volume: f64) -> Reply<()> {
+		self.run(move |backend| {
+			backend.set_volume(volume);
+			Ok(())
+		})
+	}
+
+	pub fn set_muted(&self, muted: bool) -> Reply<()> {
+		self.run(move |backend| {
+			backend.set_muted(muted);
+			Ok(())
+		})
+	}
+
+	pub fn set_quality(&self, quality: Quality) -> Reply<()> {
+		self.run(move |backend| {
+			backend.set_quality(quality);
+			Ok(())
+		})
+	}
+
+	pub fn set_render_size(&self, size: Option<(u32, u32)>) -> Reply<()> {
+		self.run(move |backend| {
+			backend.set_render_size(size);
+			Ok(())
+		})
+	}
+
+	pub fn set_orientation(&self, orientation: Orientation) -> Reply<()> {
+		self.run(move |backend| backend.set_orientation(orientation))
+	}
+
+	pub fn jump_to_live_edge(&self) -> Reply<()> {
+		self.run(|backend| backend.jump_to_live_edge())
+	}
+
+	pub fn tracks(&self) -> Reply<Vec<Track>> {
+		self.run(|backend| Ok(backend.tracks()))
+	}
+
+	pub fn select_track(&self, kind: TrackKind, index: usize) -> Reply<()> {
+		self.run(move |backend| backend.select_track(kind, index))
+	}
+
+	pub fn start_recording(&self, path: PathBuf) -> Reply<()> {
+		self.run(move |backend| backend.start_recording(path))
+	}
+
+	pub fn stop_recording(&self) -> Reply<()> {
+		self.run(|backend| {
+			backend.stop_recording();
+			Ok(())
+		})
+	}
+
+	pub fn set_hidden(&self, hidden: bool) -> Reply<()> {
+		self.run(move |backend| {
+			backend.set_hidden(hidden);
+			Ok(())
+		})
+	}
+
+	/// The status as of the last command or refresh, without waiting.
+	pub fn status(&self) -> PlayerStatus {
+		self.status.lock().unwrap().clone()
+	}
+
+	/// Queue `job`, a job which can't be queued is dropped and its reply
+	/// fails.
+	fn send(&self, job: Job) {
+		let _ = self.commands.send(job);
+	}
+}
+
+/// Take the status of `backend` into `status`, with the `details` or
+/// keeping the ones read before.
+fn refresh(status: &Mutex<PlayerStatus>, backend: Option<&dyn MediaBackend>, details: bool) {
+	let backend = match backend {
+		Some(backend) => backend,
+		None => {
+			*status.lock().unwrap() = PlayerStatus::default();
+			return;
+		}
+	};
+	let (live, growing) = (backend.live(), backend.growing());
+	let (position, duration) = (backend.position(), backend.duration());
+	let details =
+		details.then(|| (backend.variants(), backend.recording(), backend.timeshift_progress()));
+	let mut status = status.lock().unwrap();
+	status.open = true;
+	status.live = live;
+	status.growing = growing;
+	status.position = position;
+	status.duration = duration;
+	if let Some((variants, recording, timeshift)) = details {
+		status.variants = variants;
+		status.recording = recording;
+		status.timeshift = timeshift;
+	}
+}
+
+/// Callback a [`Reply`] is handed.
+type Callback<T> = Box<dyn FnOnce(Result<T, VideoError>) + Send>;
+
+struct ReplyState<T> {
+	result: Option<Result<T, VideoError>>,
+	waker: Option<Waker>,
+	callback: Option<Callback<T>>,
+}
+
+struct Shared<T> {
+	state: Mutex<ReplyState<T>>,
+	done: Condvar,
+}
+
+/// The result of a command, once the actor ran it.
+///
+/// Await it, [`wait`](Reply::wait) for it, or hand it a callback with
+/// [`then`](Reply::then). A reply which is dropped leaves the command
+/// running.
+pub struct Reply<T> {
+	shared: Arc<Shared<T>>,
+}
+
+impl<T> fmt::Debug for Reply<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let done = self.shared.state.lock().unwrap().result.is_some();
+		f.debug_struct("Reply").field("done", &done).finish()
+	}
+}
+
+impl<T: Send + 'static> Reply<T> {
+	fn new() -> (Self, Resolve<T>) {
+		let shared = Arc::new(Shared {
+			state: Mutex::new(ReplyState { result: None, waker: None, callback: None }),
+			done: Condvar::new(),
+		});
+		(Self { shared: shared.clone() }, Resolve { shared: Some(shared) })
+	}
+
+	/// Call `callback` with the result. It runs on the actor thread, or
+	/// right away if the result is there already.
+	pub fn then(self, callback: impl FnOnce(Result<T, VideoError>) + Send + 'static) {
+		let mut state = self.shared.state.lock().unwrap();
+		match state.result.take() {
+			Some(result) => {
+				drop(state);
+				callback(result);
+			}
+			None => state.callback = Some(Box::new(callback)),
+		}
+	}
+
+	/// Block until the result is there.
+	pub fn wait(self) -> Result<T, VideoError> {
+		let mut state = self.shared.state.lock().unwrap();
+		loop {
+			if let Some(result) = state.result.take() {
+				return result;
+			}
+			state = self.shared.done.wait(state).unwrap();
+		}
+	}
+}
+
+impl<T> Future for Reply<T> {
+	type Output = Result<T, VideoError>;
+
+	fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+		let mut state = self.shared.state.lock().unwrap();
+		match state.result.take() {
+			Some(result) => Poll::Ready(result),
+			None => {
+				state.waker = Some(cx.waker().clone());
+				Poll::Pending
+			}
+		}
+	}
+}
+
+/// The actor's end of a [`Reply`]. Dropping it unsent fails the reply with
+/// [`VideoError::Closed`].
+struct Resolve<T> {
+	shared: Option<Arc<Shared<T>>>,
+}
+
+impl<T> Resolve<T> {
+	fn send(mut self, result: Result<T, VideoError>) {
+		if let Some(shared) = self.shared.take() {
+			resolve(&shared, result);
+		}
+	}
+}
+
+impl<T> Drop for Resolve<T> {
+	fn drop(&mut self) {
+		if let Some(shared) = self.shared.take() {
+			resolve(&shared, Err(VideoError::Closed));
+		}
+	}
+}
+
+fn resolve<T>(shared: &Shared<T>, result: Result<T, VideoError>) {
+	let mut state = shared.state.lock().unwrap();
+	if let Some(callback) = state.callback.take() {
+		drop(state);
+		callback(result);
+		return;
+	}
+	state.result = Some(result);
+	if let Some(waker) = state.waker.take() {
+		waker.wake();
+	}
+	shared.done.notify_all();
+}
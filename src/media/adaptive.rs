@@ -0,0 +1,354 @@
+// Variant discovery for adaptive streams.
+
+// playbin plays HLS and DASH through hlsdemux/dashdemux, but those don't
+// expose the variants they pick from. The manifest is fetched with the
+// source element GStreamer would use for the URI and parsed here:
+
+// {source for uri} - {appsink}
+use gst::prelude::*;
+use gstreamer as gst;
+use gstreamer_app as gst_app;
+
+use crate::media::{
+	error::VideoError, options::SourceOptions, source::setup_http_source, types::Variant,
+};
+
+/// Time to wait for the next chunk of the manifest.
+const FETCH_TIMEOUT: u64 = 5;
+
+/// Whether `uri` points to a HLS playlist or a DASH manifest.
+pub fn is_adaptive(uri: &url::Url) -> bool {
+	let path = uri.path();
+	path.ends_with(".m3u8") || path.ends_with(".mpd")
+}
+
+/// Fetch the manifest at `uri` and list its video variants, lowest bitrate
+/// first.
+pub fn variants(uri: &url::Url, options: &SourceOptions) -> Result<Vec<Variant>, VideoError> {
+	let manifest = fetch(uri.as_str(), options)?;
+	let variants = if manifest.trim_start().starts_with("#EXTM3U") {
+		parse_hls(&manifest)
+	} else if manifest.contains("<MPD") {
+		parse_dash(&manifest)
+	} else {
+		return Err(VideoError::Uri);
+	};
+	Ok(distinct(variants))
+}
+
+/// Sort `variants` by bitrate and drop those advertised twice. Variants of
+/// the same bitrate at different resolutions are told apart.
+fn distinct(mut variants: Vec<Variant>) -> Vec<Variant> {
+	variants.sort_by_key(|variant| (variant.bitrate, variant.size));
+	variants.dedup_by_key(|variant| (variant.bitrate, variant.size));
+	variants
+}
+
+/// Read the whole resource at `uri` into a string.
+fn fetch(uri: &str, options: &SourceOptions) -> Result<String, VideoError> {
+	gst::init()?;
+	let source = gst::Element::make_from_uri(gst::URIType::Src, uri, None)?;
+	setup_http_source(&source, options);
+	let appsink = gst::ElementFactory::make("appsink", None)?
+		.downcast::<gst_app::AppSink>()
+		.map_err(|_| VideoError::Cast)?;
+	appsink.set_property("sync", false);
+
+	let pipeline = gst::Pipeline::new(None);
+	pipeline.add_many(&[&source, appsink.upcast_ref::<gst::Element>()])?;
+	source.link(&appsink)?;
+	pipeline.set_state(gst::State::Playing)?;
+
+	let mut data = Vec::new();
+	while let Some(sample) = appsink.try_pull_sample(gst::ClockTime::from_seconds(FETCH_TIMEOUT)) {
+		let buffer = sample.buffer().ok_or(VideoError::Sync)?;
+		let map = buffer.map_readable().map_err(|_| VideoError::Sync)?;
+		data.extend_from_slice(map.as_slice());
+	}
+	let eos = appsink.is_eos();
+	pipeline.set_state(gst::State::Null)?;
+	if !eos {
+		return Err(VideoError::Sync);
+	}
+	String::from_utf8(data).map_err(|err| VideoError::Other(err.into()))
+}
+
+/// Parse the video `#EXT-X-STREAM-INF` entries of a HLS master playlist.
+fn parse_hls(playlist: &str) -> Vec<Variant> {
+	let mut variants = Vec::new();
+	let mut uris = Vec::new();
+	let mut lines = playlist.lines().map(str::trim);
+	while let Some(line) = lines.next() {
+		let attributes = match line.strip_prefix("#EXT-X-STREAM-INF:") {
+			Some(attributes) => attributes,
+			None => continue,
+		};
+		let mut bitrate = 0;
+		let mut size = None;
+		let mut codecs = None;
+		for (key, value) in hls_attributes(attributes) {
+			match key {
+				"BANDWIDTH" => bitrate = value.parse().unwrap_or(0),
+				"RESOLUTION" => {
+					size = value.split_once('x').and_then(|(width, height)| {
+						Some((width.parse().ok()?, height.parse().ok()?))
+					})
+				}
+				"CODECS" => codecs = Some(value),
+				_ => {}
+			}
+		}
+		let uri = lines.find(|line| !line.is_empty() && !line.starts_with('#')).unwrap_or_default();
+		// Without a resolution only the codecs tell audio-only variants.
+		let audio_only =
+			codecs.map_or(false, |codecs| codecs.split(',').map(str::trim).all(is_audio_codec));
+		if size.is_none() && audio_only {
+			continue;
+		}
+		variants.push(Variant { bitrate, size, hint: String::new() });
+		uris.push(uri);
+	}
+	for (variant, hint) in variants.iter_mut().zip(hls_hints(&uris)) {
+		variant.hint = hint;
+	}
+	variants
+}
+
+/// Whether `codec`, as named in the `CODECS` attribute, is an audio codec.
+fn is_audio_codec(codec: &str) -> bool {
+	let name = codec.split('.').next().unwrap_or_default();
+	["mp4a", "ac-3", "ec-3", "ac-4", "opus", "Opus", "flac", "fLaC", "mp3", "vorbis"]
+		.contains(&name)
+}
+
+/// Split a HLS attribute list, values may be quoted and contain commas.
+fn hls_attributes(attributes: &str) -> Vec<(&str, &str)> {
+	let mut result = Vec::new();
+	let mut rest = attributes;
+	while let Some((key, tail)) = rest.split_once('=') {
+		let (value, tail) = if let Some(quoted) = tail.strip_prefix('"') {
+			let end = quoted.find('"').unwrap_or(quoted.len());
+			(&quoted[..end], quoted[end..].trim_start_matches('"'))
+		} else {
+			let end = tail.find(',').unwrap_or(tail.len());
+			(&tail[..end], &tail[end..])
+		};
+		result.push((key.trim(), value));
+		rest = tail.trim_start_matches(',');
+	}
+	result
+}
+
+/// Parts of the variant playlist URIs which also show up in their segment
+/// URIs: the path without the extension (`video/720p` of `video/720p.m3u8`),
+/// or the directory when the playlists all have the same file name
+/// (`720p/` of `720p/index.m3u8`).
+fn hls_hints(uris: &[&str]) -> Vec<String> {
+	let paths: Vec<_> = uris
+		.iter()
+		.map(|uri| uri.split(|c| c == '?' || c == '#').next().unwrap_or_default())
+		.collect();
+	let file_name = |path: &str| path.rsplit_once('/').map_or(path, |(_, name)| name).to_owned();
+	let mut names: Vec<_> = paths.iter().map(|path| file_name(path)).collect();
+	names.sort();
+	names.dedup();
+	let same_names = paths.len() > 1 && names.len() == 1;
+	paths
+		.iter()
+		.map(|path| match path.rsplit_once('/') {
+			Some((dir, _)) if same_names => format!("{}/", dir),
+			_ => {
+				let name_start = path.rfind('/').map_or(0, |slash| slash + 1);
+				match path[name_start..].rfind('.') {
+					Some(dot) => path[..name_start + dot].to_owned(),
+					None => path.to_string(),
+				}
+			}
+		})
+		.collect()
+}
+
+/// Parse the video `<Representation>` elements of a DASH manifest.
+fn parse_dash(manifest: &str) -> Vec<Variant> {
+	manifest
+		.split("<Representation")
+		.skip(1)
+		.filter_map(|element| {
+			let tag = &element[..element.find('>')?];
+			let size = xml_attribute(tag, "width").and_then(|width| {
+				Some((width.parse().ok()?, xml_attribute(tag, "height")?.parse().ok()?))
+			});
+			let video = size.is_some()
+				|| xml_attribute(tag, "mimeType").map_or(false, |mime| mime.starts_with("video"));
+			if !video {
+				return None;
+			}
+			Some(Variant {
+				bitrate: xml_attribute(tag, "bandwidth")?.parse().ok()?,
+				size,
+				hint: xml_attribute(tag, "id").unwrap_or_default().to_string(),
+			})
+		})
+		.collect()
+}
+
+/// Value of the attribute `name` of the XML start tag `tag`.
+fn xml_attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+	let pattern = format!("{}=\"", name);
+	let start = tag
+		.match_indices(&pattern)
+		.find(|(index, _)| tag[..*index].ends_with(char::is_whitespace))
+		.map(|(index, _)| index + pattern.len())?;
+	let len = tag[start..].find('"')?;
+	Some(&tag[start..start + len])
+}
+
+/// Find the variant a downloaded fragment belongs to.
+pub fn variant_of_fragment(variants: &[Variant], fragment_uri: &str) -> Option<usize> {
+	variants
+		.iter()
+		.enumerate()
+		.filter(|(_, variant)| !variant.hint.is_empty() && fragment_uri.contains(&variant.hint))
+		.max_by_key(|(_, variant)| variant.hint.len())
+		.map(|(index, _)| index)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const MASTER: &str = "#EXTM3U
+#EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=1280x720,CODECS=\"avc1.4d401f,mp4a.40.2\"
+video/720p.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=2500000,RESOLUTION=1920x1080
+video/1080p.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=64000,CODECS=\"mp4a.40.2\"
+
+audio/only.m3u8
+";
+
+	fn variant(bitrate: u64, size: Option<(u32, u32)>, hint: &str) -> Variant {
+		Variant { bitrate, size, hint: hint.to_owned() }
+	}
+
+	#[test]
+	fn hls_master_playlist() {
+		assert_eq!(
+			parse_hls(MASTER),
+			vec![
+				variant(800_000, Some((1280, 720)), "video/720p"),
+				variant(2_500_000, Some((1920, 1080)), "video/1080p"),
+			]
+		);
+	}
+
+	#[test]
+	fn hls_audio_only_variants_are_skipped() {
+		let playlist = "#EXTM3U
+#EXT-X-STREAM-INF:BANDWIDTH=96000,CODECS=\"ec-3, mp4a.40.5\"
+audio/surround.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=500000,CODECS=\"avc1.42e00a,mp4a.40.2\"
+video/low.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=900000
+video/unknown.m3u8
+#EXT-X-STREAM-INF:BANDWIDTH=1200000,RESOLUTION=960x540,CODECS=\"mp4a.40.2\"
+video/mislabelled.m3u8
+";
+		let bitrates: Vec<_> = parse_hls(playlist).iter().map(|variant| variant.bitrate).collect();
+		// Variants which may have video are kept.
+		assert_eq!(bitrates, [500_000, 900_000, 1_200_000]);
+	}
+
+	#[test]
+	fn audio_codecs() {
+		for codec in ["mp4a.40.2", "mp4a.40.34", "ac-3", "ec-3", "opus", "fLaC"] {
+			assert!(is_audio_codec(codec), "{}", codec);
+		}
+		for codec in ["avc1.4d401f", "hvc1.1.6.L93.B0", "vp09.00.10.08", "av01.0.04M.08", ""] {
+			assert!(!is_audio_codec(codec), "{}", codec);
+		}
+	}
+
+	#[test]
+	fn variants_are_distinct_and_sorted() {
+		let variants = distinct(vec![
+			variant(2_000_000, Some((1280, 720)), "a"),
+			variant(1_000_000, Some((1280, 720)), "b"),
+			variant(2_000_000, Some((1280, 720)), "c"),
+			variant(2_000_000, Some((960, 540)), "d"),
+			variant(1_000_000, None, "e"),
+		]);
+		let hints: Vec<_> = variants.iter().map(|variant| variant.hint.as_str()).collect();
+		assert_eq!(hints, ["e", "b", "d", "a"]);
+	}
+
+	#[test]
+	fn hls_playlists_of_the_same_name() {
+		let playlist = "#EXTM3U
+#EXT-X-STREAM-INF:BANDWIDTH=1000
+low/index.m3u8?token=a
+#EXT-X-STREAM-INF:BANDWIDTH=2000
+high/index.m3u8?token=a
+";
+		let hints: Vec<_> = parse_hls(playlist).into_iter().map(|variant| variant.hint).collect();
+		assert_eq!(hints, ["low/", "high/"]);
+	}
+
+	#[test]
+	fn hls_quoted_attributes() {
+		assert_eq!(
+			hls_attributes("CODECS=\"a,b\",BANDWIDTH=1,NAME=\"x\""),
+			[("CODECS", "a,b"), ("BANDWIDTH", "1"), ("NAME", "x")]
+		);
+	}
+
+	#[test]
+	fn dash_video_representations() {
+		let manifest = r#"<?xml version="1.0"?>
+<MPD><Period>
+<AdaptationSet mimeType="video/mp4">
+<Representation id="v1" bandwidth="500000" width="640" height="360"/>
+<Representation id="v2" bandwidth="1500000" width="1280" height="720">
+</Representation>
+</AdaptationSet>
+<AdaptationSet>
+<Representation id="v3" mimeType="video/mp4" bandwidth="3000000"/>
+<Representation id="a1" mimeType="audio/mp4" bandwidth="128000"/>
+</AdaptationSet>
+</Period></MPD>"#;
+		assert_eq!(
+			parse_dash(manifest),
+			vec![
+				variant(500_000, Some((640, 360)), "v1"),
+				variant(1_500_000, Some((1280, 720)), "v2"),
+				variant(3_000_000, None, "v3"),
+			]
+		);
+	}
+
+	#[test]
+	fn xml_attributes() {
+		let tag = r#" id="v1" xid="no" bandwidth="500" width="" "#;
+		assert_eq!(xml_attribute(tag, "id"), Some("v1"));
+		assert_eq!(xml_attribute(tag, "bandwidth"), Some("500"));
+		assert_eq!(xml_attribute(tag, "width"), Some(""));
+		assert_eq!(xml_attribute(tag, "height"), None);
+		assert_eq!(xml_attribute(r#" xid="no""#, "id"), None);
+	}
+
+	#[test]
+	fn fragments_of_variants_in_one_directory() {
+		let variants = parse_hls(MASTER);
+		let variant = |uri| variant_of_fragment(&variants, uri);
+		assert_eq!(variant("https://cdn.example/video/720p_00042.ts"), Some(0));
+		assert_eq!(variant("https://cdn.example/video/1080p_00042.ts"), Some(1));
+		assert_eq!(variant("https://cdn.example/other/00042.ts"), None);
+	}
+
+	#[test]
+	fn fragments_prefer_the_longest_hint() {
+		let variants = [variant(1, None, "seg"), variant(2, None, "seg-hd"), variant(3, None, "")];
+		assert_eq!(variant_of_fragment(&variants, "/seg-hd-1.m4s"), Some(1));
+		assert_eq!(variant_of_fragment(&variants, "/seg-1.m4s"), Some(0));
+	}
+}
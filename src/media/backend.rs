@@ -0,0 +1,307 @@
+//! Media backends the view plays through.
+//!
+//! [`MediaBackend`] is what the view needs of a player: opening media,
+//! transport, volume, tracks and recording. Frames and events go to the
+//! [`SharedSink`] a backend is opened with. [`VideoPlayer`] is the GStreamer backend,
+//! [`FakeBackend`](crate::fake::FakeBackend) plays synthetic media on a
+//! virtual clock:
+//!
+//! ```text
+//! {VideoView} - {MediaBackend} - {PlayerSink} - {VideoView}
+//! ```
+use std::{path::PathBuf, time::Duration};
+
+use gst::prelude::*;
+use gstreamer as gst;
+
+use crate::media::{
+	error::VideoError,
+	options::PlayerOptions,
+	player::{self, SharedSink, VideoPlayer},
+	record::Progress,
+	types::{Orientation, Quality, RecordStatus, Variant},
+};
+
+/// What a track carries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackKind {
+	Video,
+	Audio,
+	Text,
+}
+
+/// A stream of the media which can be selected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Track {
+	pub kind: TrackKind,
+	/// Index among the tracks of the same kind.
+	pub index: usize,
+	/// ISO 639 code of the language, if the media tells.
+	pub language: Option<String>,
+	/// Name of the codec, if the media tells.
+	pub codec: Option<String>,
+	/// The track is the one of its kind being played.
+	pub selected: bool,
+}
+
+/// A player the view can drive.
+///
+/// Transport methods take effect right away, what comes of them (frames,
+/// buffering, the end of the media, errors) is reported to the sink the
+/// backend was opened with.
+pub trait MediaBackend: Send {
+	/// Open `uri` for playback, paused unless it is live.
+	fn open(uri: &url::Url, options: &PlayerOptions, sink: SharedSink) -> Result<Self, VideoError>
+	where
+		Self: Sized;
+
+	/// Start playing. A pipeline filling its buffer starts once it is full.
+	fn play(&mut self) -> Result<(), VideoError>;
+
+	fn pause(&mut self) -> Result<(), VideoError>;
+
+	/// Go to `position`, showing the frame there even while paused.
+	fn seek(&mut self, position: Duration) -> Result<(), VideoError>;
+
+	/// Play at `rate` times the normal speed, backwards if it is negative.
+	fn set_rate(&mut self, rate: f64) -> Result<(), VideoError>;
+
+	/// Set the linear volume multiplier, `1.0` is 100%.
+	fn set_volume(&mut self, volume: f64);
+
+	fn set_muted(&mut self, muted: bool);
+
+	fn position(&self) -> Duration;
+
+	/// Duration of the media, zero if it has none.
+	fn duration(&self) -> Duration;
+
+	/// The source is live, there is no duration and nothing to seek in.
+	fn live(&self) -> bool;
+
+	/// The duration grows while playing, reaching it doesn't end playback.
+	fn growing(&self) -> bool {
+		false
+	}
+
+	fn tracks(&self) -> Vec<Track>;
+
+	/// Play the track `index` of `kind` instead of the current one.
+	fn select_track(&mut self, kind: TrackKind, index: usize) -> Result<(), VideoError>;
+
+	/// Variants of an adaptive stream, empty for anything else.
+	fn variants(&self) -> Vec<Variant> {
+		Vec::new()
+	}
+
+	/// Select the variant of an adaptive stream.
+	fn set_quality(&self, _quality: Quality) {}
+
+	/// Drop whatever is queued for a live source and continue from the
+	/// current live point.
+	fn jump_to_live_edge(&mut self) -> Result<(), VideoError> {
+		Ok(())
+	}
+
+	/// Scale the frames down to fit into `size` in pixels, `None` for the
+	/// size of the video.
+	fn set_render_size(&mut self, _size: Option<(u32, u32)>) {}
+
+	/// Rotate and mirror the frames by `orientation`.
+	fn set_orientation(&mut self, _orientation: Orientation) -> Result<(), VideoError> {
+		Ok(())
+	}
+
+	/// Record the stream as it comes in to `path`, replacing the recording
+	/// in progress.
+	fn start_recording(&mut self, _path: PathBuf) -> Result<(), VideoError> {
+		Err(VideoError::Unsupported)
+	}
+
+	/// Finish the recording, if there is one.
+	fn stop_recording(&mut self) {}
+
+	/// The recording in progress.
+	fn recording(&self) -> Option<RecordStatus> {
+		None
+	}
+
+	/// What the timeshift buffer of a live stream holds so far.
+	fn timeshift_progress(&self) -> Option<Progress> {
+		None
+	}
+
+	/// Close the file of the timeshift buffer being written, so it can be
+	/// played.
+	fn split_timeshift(&self) {}
+
+	/// Drop the output, or show it again, while the media keeps playing.
+	fn set_hidden(&mut self, _hidden: bool) {}
+}
+
+impl MediaBackend for VideoPlayer {
+	fn open(uri: &url::Url, options: &PlayerOptions, sink: SharedSink) -> Result<Self, VideoError> {
+		VideoPlayer::with_options(uri, false, options.clone(), sink)
+	}
+
+	fn play(&mut self) -> Result<(), VideoError> {
+		// While the buffer fills the bus watch plays the pipeline once it is full.
+		let mut buffering = self.buffering.lock().unwrap();
+		if buffering.play() {
+			self.pipeline.set_state(gst::State::Playing)?;
+		}
+		self.paused = false;
+		Ok(())
+	}
+
+	fn pause(&mut self) -> Result<(), VideoError> {
+		let mut buffering = self.buffering.lock().unwrap();
+		buffering.pause();
+		self.pipeline.set_state(gst::State::Paused)?;
+		self.paused = true;
+		Ok(())
+	}
+
+	fn seek(&mut self, position: Duration) -> Result<(), VideoError> {
+		if self.live {
+			return Err(VideoError::Seek);
+		}
+		let position = gst::ClockTime::from_nseconds(position.as_nanos() as _);
+		let flags = gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT;
+		VideoPlayer::set_muted(self, true);
+		if self.paused {
+			// Paused pipelines don't hand out the frame sought to.
+			self.pipeline.set_state(gst::State::Playing)?;
+			self.pipeline.seek_simple(flags, position)?;
+			self.pipeline.state(gst::ClockTime::from_mseconds(20)).0?;
+			self.pipeline.set_state(gst::State::Paused)?;
+		} else {
+			self.pipeline.seek_simple(flags, position)?;
+		}
+		VideoPlayer::set_muted(self, false);
+		Ok(())
+	}
+
+	fn set_rate(&mut self, rate: f64) -> Result<(), VideoError> {
+		// A rate of zero isn't a valid seek.
+		let rate = if rate == 0.0 { 0.000000001 } else { rate };
+		if player::send_seek_event(&self.pipeline, rate) {
+			Ok(())
+		} else {
+			Err(VideoError::Seek)
+		}
+	}
+
+	fn set_volume(&mut self, volume: f64) {
+		VideoPlayer::set_volume(self, volume);
+	}
+
+	fn set_muted(&mut self, muted: bool) {
+		VideoPlayer::set_muted(self, muted);
+	}
+
+	fn position(&self) -> Duration {
+		VideoPlayer::position(self)
+	}
+
+	fn duration(&self) -> Duration {
+		self.duration
+	}
+
+	fn live(&self) -> bool {
+		self.live
+	}
+
+	fn growing(&self) -> bool {
+		self.growing
+	}
+
+	fn tracks(&self) -> Vec<Track> {
+		// Only playbin tells about its tracks.
+		if self.pipeline.find_property("n-video").is_none() {
+			return Vec::new();
+		}
+		let kinds = [
+			(TrackKind::Video, "video", "video-codec"),
+			(TrackKind::Audio, "audio", "audio-codec"),
+			(TrackKind::Text, "text", "subtitle-codec"),
+		];
+		let mut tracks = Vec::new();
+		for (kind, name, codec) in kinds {
+			let count = self.pipeline.property::<i32>(&format!("n-{}", name));
+			let current = self.pipeline.property::<i32>(&format!("current-{}", name));
+			for index in 0..count {
+				let tags = self
+					.pipeline
+					.emit_by_name::<Option<gst::TagList>>(&format!("get-{}-tags", name), &[&index]);
+				let tag = |name: &str| tags.as_ref()?.generic(name)?.get::<String>().ok();
+				tracks.push(Track {
+					kind,
+					index: index as usize,
+					language: tag("language-code"),
+					codec: tag(codec),
+					selected: index == current,
+				});
+			}
+		}
+		tracks
+	}
+
+	fn select_track(&mut self, kind: TrackKind, index: usize) -> Result<(), VideoError> {
+		let exists = self.tracks().iter().any(|track| track.kind == kind && track.index == index);
+		if !exists {
+			return Err(VideoError::Track);
+		}
+		let name = match kind {
+			TrackKind::Video => "current-video",
+			TrackKind::Audio => "current-audio",
+			TrackKind::Text => "current-text",
+		};
+		self.pipeline.set_property(name, index as i32);
+		Ok(())
+	}
+
+	fn variants(&self) -> Vec<Variant> {
+		self.variants.clone()
+	}
+
+	fn set_quality(&self, quality: Quality) {
+		VideoPlayer::set_quality(self, quality);
+	}
+
+	fn jump_to_live_edge(&mut self) -> Result<(), VideoError> {
+		VideoPlayer::jump_to_live_edge(self)
+	}
+
+	fn set_render_size(&mut self, size: Option<(u32, u32)>) {
+		VideoPlayer::set_render_size(self, size);
+	}
+
+	fn set_orientation(&mut self, orientation: Orientation) -> Result<(), VideoError> {
+		VideoPlayer::set_orientation(self, orientation)
+	}
+
+	fn start_recording(&mut self, path: PathBuf) -> Result<(), VideoError> {
+		VideoPlayer::start_recording(self, path)
+	}
+
+	fn stop_recording(&mut self) {
+		VideoPlayer::stop_recording(self);
+	}
+
+	fn recording(&self) -> Option<RecordStatus> {
+		VideoPlayer::recording(self)
+	}
+
+	fn timeshift_progress(&self) -> Option<Progress> {
+		VideoPlayer::timeshift_progress(self)
+	}
+
+	fn split_timeshift(&self) {
+		VideoPlayer::split_timeshift(self);
+	}
+
+	fn set_hidden(&mut self, hidden: bool) {
+		VideoPlayer::set_hidden(self, hidden);
+	}
+}
@@ -0,0 +1,226 @@
+// Capture devices.
+
+// Cameras and microphones are found with a DeviceMonitor (v4l2, pulse,
+// pipewire, ...). Without any camera a live videotestsrc stands in, so
+// capture works headless too:
+
+// {device source} - {capsfilter} - {leaky queue} - {video sink bin}
+// {device source} - {leaky queue} - {volume} - {audio sink bin}
+use std::{fmt, sync::Arc};
+
+use gst::prelude::*;
+use gstreamer as gst;
+
+use crate::media::error::VideoError;
+
+/// URI scheme of capture items, `capture://?video=<name>&audio=<name>`.
+pub const SCHEME: &str = "capture";
+
+/// Name of the stand-in camera.
+pub const VIRTUAL_DEVICE: &str = "Test pattern";
+
+const VIDEO_CLASS: &str = "Video/Source";
+const AUDIO_CLASS: &str = "Audio/Source";
+
+/// Resolution and framerate a capture device can deliver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureMode {
+	pub width: i32,
+	pub height: i32,
+	/// Framerate as `(numerator, denominator)`.
+	pub framerate: (i32, i32),
+}
+
+impl fmt::Display for CaptureMode {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let (numer, denom) = self.framerate;
+		let fps = (numer as f64 / denom.max(1) as f64 * 100.0).round() / 100.0;
+		write!(f, "{}×{} @ {} fps", self.width, self.height, fps)
+	}
+}
+
+/// A camera, microphone or other capture device.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureDevice {
+	/// Name the device is shown and picked by.
+	pub name: String,
+	/// Video source, otherwise it is an audio source.
+	pub video: bool,
+	/// Raw video modes of a video source, largest first.
+	pub modes: Arc<Vec<CaptureMode>>,
+}
+
+/// Devices and mode to capture from.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CaptureSelection {
+	/// Name of the video device, `None` when not capturing.
+	pub video: Option<String>,
+	/// Mode of the video device, `None` lets the device pick.
+	pub mode: Option<CaptureMode>,
+	/// Name of the audio device, `None` for no audio.
+	pub audio: Option<String>,
+}
+
+/// List the capture devices, video devices first.
+pub fn list() -> Result<Vec<CaptureDevice>, VideoError> {
+	let devices = monitor_devices()?
+		.iter()
+		.map(|device| {
+			let video = device.has_classes(VIDEO_CLASS);
+			CaptureDevice {
+				name: device.display_name().to_string(),
+				video,
+				modes: Arc::new(match device.caps() {
+					Some(ref caps) if video => modes(caps),
+					_ => Vec::new(),
+				}),
+			}
+		})
+		.collect();
+	Ok(with_virtual_device(devices))
+}
+
+/// Add the stand-in camera to `devices` if there is no camera, and sort
+/// the video devices first.
+fn with_virtual_device(mut devices: Vec<CaptureDevice>) -> Vec<CaptureDevice> {
+	if !devices.iter().any(|device| device.video) {
+		devices.push(virtual_device());
+	}
+	devices.sort_by_key(|device| !device.video);
+	devices
+}
+
+fn virtual_device() -> CaptureDevice {
+	let mode = |width, height| CaptureMode { width, height, framerate: (30, 1) };
+	CaptureDevice {
+		name: VIRTUAL_DEVICE.to_string(),
+		video: true,
+		modes: Arc::new(vec![mode(1280, 720), mode(640, 480), mode(320, 240)]),
+	}
+}
+
+fn monitor_devices() -> Result<Vec<gst::Device>, VideoError> {
+	gst::init()?;
+	let monitor = gst::DeviceMonitor::new();
+	monitor.add_filter(Some(VIDEO_CLASS), None);
+	monitor.add_filter(Some(AUDIO_CLASS), None);
+	monitor.start()?;
+	let devices = monitor.devices().into_iter().collect();
+	monitor.stop();
+	Ok(devices)
+}
+
+/// Raw video modes in `caps`, largest and fastest first.
+fn modes(caps: &gst::Caps) -> Vec<CaptureMode> {
+	let mut modes = Vec::new();
+	for s in caps.iter().filter(|s| s.name() == "video/x-raw") {
+		let (width, height) = match (s.get::<i32>("width"), s.get::<i32>("height")) {
+			(Ok(width), Ok(height)) => (width, height),
+			// Ranges don't name a mode.
+			_ => continue,
+		};
+		let framerates = match s.get::<gst::Fraction>("framerate") {
+			Ok(framerate) => vec![framerate],
+			Err(_) => s.get::<gst::List>("framerate").map_or_else(
+				|_| Vec::new(),
+				|list| list.iter().filter_map(|value| value.get::<gst::Fraction>().ok()).collect(),
+			),
+		};
+		for framerate in framerates {
+			let framerate = (framerate.numer(), framerate.denom());
+			modes.push(CaptureMode { width, height, framerate });
+		}
+	}
+	modes.sort_by(|a, b| {
+		let rate = |mode: &CaptureMode| mode.framerate.0 as f64 / mode.framerate.1.max(1) as f64;
+		(b.width * b.height).cmp(&(a.width * a.height)).then(rate(b).total_cmp(&rate(a)))
+	});
+	modes.dedup();
+	modes
+}
+
+/// Read the devices to capture from a `capture://` URI, the first camera of
+/// `devices` if it names none.
+pub fn parse_uri(
+	uri: &url::Url,
+	devices: &[CaptureDevice],
+) -> Result<CaptureSelection, VideoError> {
+	let mut selection = CaptureSelection::default();
+	for (key, value) in uri.query_pairs() {
+		match &*key {
+			"video" => selection.video = Some(value.into_owned()),
+			"audio" => selection.audio = Some(value.into_owned()),
+			_ => return Err(VideoError::Uri),
+		}
+	}
+	if selection.video.is_none() {
+		selection.video =
+			devices.iter().find(|device| device.video).map(|device| device.name.clone());
+	}
+	Ok(selection)
+}
+
+/// Create the source element of the device called `name`.
+pub fn source(name: &str, video: bool) -> Result<gst::Element, VideoError> {
+	if video && name == VIRTUAL_DEVICE {
+		let source = gst::ElementFactory::make("videotestsrc", None)?;
+		source.set_property("is-live", true);
+		source.set_property_from_str("pattern", "ball");
+		return Ok(source);
+	}
+	let class = if video { VIDEO_CLASS } else { AUDIO_CLASS };
+	let device = monitor_devices()?
+		.into_iter()
+		.find(|device| device.has_classes(class) && device.display_name() == name)
+		.ok_or(VideoError::Uri)?;
+	Ok(device.create_element(None)?)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn device(name: &str, video: bool) -> CaptureDevice {
+		CaptureDevice { name: name.to_owned(), video, modes: Arc::default() }
+	}
+
+	fn names(devices: &[CaptureDevice]) -> Vec<&str> {
+		devices.iter().map(|device| device.name.as_str()).collect()
+	}
+
+	#[test]
+	fn no_camera_falls_back_to_the_test_pattern() {
+		assert_eq!(names(&with_virtual_device(Vec::new())), [VIRTUAL_DEVICE]);
+		let devices = with_virtual_device(vec![device("Microphone", false)]);
+		assert_eq!(names(&devices), [VIRTUAL_DEVICE, "Microphone"]);
+		assert!(!devices[0].modes.is_empty());
+	}
+
+	#[test]
+	fn cameras_come_first() {
+		let devices = with_virtual_device(vec![
+			device("Microphone", false),
+			device("Webcam", true),
+			device("Headset", false),
+			device("Capture card", true),
+		]);
+		assert_eq!(names(&devices), ["Webcam", "Capture card", "Microphone", "Headset"]);
+	}
+
+	#[test]
+	fn uri_without_a_camera_picks_the_first_one() {
+		let uri = url::Url::parse("capture://").unwrap();
+		let devices = with_virtual_device(vec![device("Microphone", false)]);
+		let selection = parse_uri(&uri, &devices).unwrap();
+		assert_eq!(selection.video.as_deref(), Some(VIRTUAL_DEVICE));
+		assert_eq!(selection.audio, None);
+
+		let uri = url::Url::parse("capture://?video=Webcam&audio=Microphone").unwrap();
+		let selection = parse_uri(&uri, &devices).unwrap();
+		assert_eq!(selection.video.as_deref(), Some("Webcam"));
+		assert_eq!(selection.audio.as_deref(), Some("Microphone"));
+
+		let uri = url::Url::parse("capture://?camera=Webcam").unwrap();
+		assert!(matches!(parse_uri(&uri, &devices), Err(VideoError::Uri)));
+	}
+}
@@ -0,0 +1,48 @@
+//! Errors of the players.
+use gstreamer as gst;
+use thiserror::Error;
+
+/// Why a player couldn't be built or a command failed.
+#[derive(Debug, Error)]
+pub enum VideoError {
+	#[error("{0}")]
+	Glib(#[from] glib::Error),
+	#[error("{0}")]
+	Bool(#[from] glib::BoolError),
+	#[error("failed to get the gstreamer bus")]
+	Bus,
+	#[error("{0}")]
+	StateChange(#[from] gst::StateChangeError),
+	#[error("failed to cast gstreamer element")]
+	Cast,
+	#[error("{0}")]
+	Io(#[from] std::io::Error),
+	#[error("invalid URI")]
+	Uri,
+	#[error("failed to get media capabilities")]
+	Caps,
+	#[error("failed to query media duration or position")]
+	Duration,
+	#[error("failed to sync with playback")]
+	Sync,
+	/// A pipeline description which can't be played, `position` is the byte
+	/// offset of the culprit if it is known.
+	#[error("{message}{}", .position.map(|p| format!(" (at position {})", p)).unwrap_or_default())]
+	Launch { message: String, position: Option<usize> },
+	#[error("there is no stream to record")]
+	Record,
+	#[error("the media can't be seeked")]
+	Seek,
+	#[error("there is no such track")]
+	Track,
+	#[error("the player is closed")]
+	Closed,
+	#[error("the media backend can't do this")]
+	Unsupported,
+	#[error("{0}")]
+	PadLinkError(#[from] gst::PadLinkError),
+	#[error("{0}")]
+	FlowError(#[from] gstreamer::FlowError),
+	#[error("{0}")]
+	Other(#[from] anyhow::Error),
+}
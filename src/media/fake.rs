@@ -0,0 +1,395 @@
+//! A deterministic backend without GStreamer, for testing what drives it.
+//!
+//! [`FakeBackend`] plays synthetic media on a virtual clock which only moves
+//! when [`FakeBackend::advance`] is called. Every frame passed on the way is
+//! sent to the sink, so are the events scripted for the positions passed:
+//!
+//! ```text
+//! {advance} - {virtual clock} - {frames} - {PlayerSink}
+//!                             - {script} - {PlayerSink}
+//! ```
+use std::{
+	fmt,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc, Mutex,
+	},
+	time::Duration,
+};
+
+use crate::media::{
+	backend::{MediaBackend, Track, TrackKind},
+	error::VideoError,
+	options::PlayerOptions,
+	player::{FrameData, PlayerEvent, SharedSink, VideoFrame},
+	present::{PresentationClock, SharedClock},
+	types::Colorimetry,
+};
+
+/// The media a [`FakeBackend`] plays.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FakeMedia {
+	pub width: u32,
+	pub height: u32,
+	/// Frames per second.
+	pub framerate: f64,
+	/// Length of the media, ignored if it is `live`.
+	pub duration: Duration,
+	pub live: bool,
+	pub tracks: Vec<Track>,
+}
+
+impl Default for FakeMedia {
+	fn default() -> Self {
+		Self {
+			width: 64,
+			height: 48,
+			framerate: 25.0,
+			duration: Duration::from_secs(10),
+			live: false,
+			tracks: vec![
+				Track {
+					kind: TrackKind::Video,
+					index: 0,
+					language: None,
+					codec: Some("fake video".to_string()),
+					selected: true,
+				},
+				Track {
+					kind: TrackKind::Audio,
+					index: 0,
+					language: None,
+					codec: Some("fake audio".to_string()),
+					selected: true,
+				},
+			],
+		}
+	}
+}
+
+/// Backend playing a [`FakeMedia`] on a virtual clock.
+///
+/// Clones share the playback, so a test can keep one to move the clock while
+/// the view owns another.
+#[derive(Clone)]
+pub struct FakeBackend {
+	fake: Arc<Mutex<Fake>>,
+}
+
+/// Playback state of a [`FakeBackend`].
+struct Fake {
+	media: FakeMedia,
+	sink: SharedSink,
+	position: Duration,
+	playing: bool,
+	rate: f64,
+	volume: f64,
+	muted: bool,
+	/// Events not sent yet and the positions they are sent at, in order.
+	script: Vec<(Duration, PlayerEvent)>,
+	/// Index of the frame shown last, none before the first one.
+	frame: Option<u64>,
+	/// Frames sent so far.
+	sent: u64,
+	ended: bool,
+	/// Running time of the virtual clock, which frames are due at.
+	clock: Arc<FakeClock>,
+}
+
+/// Running time of a [`FakeBackend`], moved by
+/// [`advance`](FakeBackend::advance) only.
+#[derive(Debug, Default)]
+struct FakeClock(AtomicU64);
+
+impl FakeClock {
+	fn set(&self, running_time: Duration) {
+		self.0.store(running_time.as_nanos() as u64, Ordering::Relaxed);
+	}
+}
+
+impl PresentationClock for FakeClock {
+	fn running_time(&self) -> Option<Duration> {
+		Some(Duration::from_nanos(self.0.load(Ordering::Relaxed)))
+	}
+}
+
+impl fmt::Debug for FakeBackend {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let fake = self.fake.lock().unwrap();
+		f.debug_struct("FakeBackend")
+			.field("media", &fake.media)
+			.field("position", &fake.position)
+			.field("playing", &fake.playing)
+			.field("rate", &fake.rate)
+			.finish()
+	}
+}
+
+impl FakeBackend {
+	/// Open `media`, paused at its start unless it is live. The first frame
+	/// is sent right away, as a prerolled pipeline would show it.
+	pub fn new(media: FakeMedia, sink: SharedSink) -> Self {
+		let playing = media.live;
+		let mut fake = Fake {
+			media,
+			sink,
+			position: Duration::ZERO,
+			playing,
+			rate: 1.0,
+			volume: 1.0,
+			muted: false,
+			script: Vec::new(),
+			frame: None,
+			sent: 0,
+			ended: false,
+			clock: Arc::default(),
+		};
+		fake.show(0);
+		Self { fake: Arc::new(Mutex::new(fake)) }
+	}
+
+	/// Send `event` once playback passes `at`.
+	pub fn script(&self, at: Duration, event: PlayerEvent) {
+		let mut fake = self.fake.lock().unwrap();
+		let index = fake.script.partition_point(|(time, _)| *time <= at);
+		fake.script.insert(index, (at, event));
+	}
+
+	/// Builder form of [`FakeBackend::script`].
+	pub fn with_event(self, at: Duration, event: PlayerEvent) -> Self {
+		self.script(at, event);
+		self
+	}
+
+	/// Let `elapsed` pass on the virtual clock.
+	///
+	/// While playing, the position moves by `elapsed` times the rate. The
+	/// frames passed and the scripted events due are sent in the order of
+	/// their positions. Reaching the end pauses and sends
+	/// [`PlayerEvent::Ended`].
+	pub fn advance(&self, elapsed: Duration) {
+		self.fake.lock().unwrap().advance(elapsed);
+	}
+
+	/// Whether the clock moves the position.
+	pub fn is_playing(&self) -> bool {
+		self.fake.lock().unwrap().playing
+	}
+
+	pub fn rate(&self) -> f64 {
+		self.fake.lock().unwrap().rate
+	}
+
+	pub fn volume(&self) -> f64 {
+		self.fake.lock().unwrap().volume
+	}
+
+	pub fn muted(&self) -> bool {
+		self.fake.lock().unwrap().muted
+	}
+
+	/// Frames sent to the sink so far.
+	pub fn frames_sent(&self) -> u64 {
+		self.fake.lock().unwrap().sent
+	}
+}
+
+impl Fake {
+	fn advance(&mut self, elapsed: Duration) {
+		if !self.playing {
+			return;
+		}
+		let running_time = self.running_time();
+		let forward = self.rate >= 0.0;
+		let distance = elapsed.mul_f64(self.rate.abs());
+		let target = if !forward {
+			self.position.saturating_sub(distance)
+		} else if self.media.live {
+			self.position + distance
+		} else {
+			(self.position + distance).min(self.media.duration)
+		};
+
+		// Frames and events in between go out in order. Playing backwards
+		// passes no scripted events.
+		while let Some(at) =
+			self.script.first().map(|(at, _)| *at).filter(|at| forward && *at <= target)
+		{
+			self.run_to(at);
+			let (_, event) = self.script.remove(0);
+			let _ = self.sink.event(event);
+		}
+		self.run_to(target);
+		self.clock.set(running_time + elapsed);
+
+		let end = !self.media.live && forward && target >= self.media.duration;
+		let start = !forward && target == Duration::ZERO;
+		if (end || start) && !self.ended {
+			self.ended = true;
+			self.playing = false;
+			let _ = self.sink.event(PlayerEvent::Ended);
+		}
+	}
+
+	/// Move the position to `target`, sending the frames passed. Each is due
+	/// when the clock reaches its position.
+	fn run_to(&mut self, target: Duration) {
+		let (from, running_time) = (self.position, self.running_time());
+		let rate = self.rate.abs().max(f64::EPSILON);
+		let due = |position: Duration| {
+			let distance = if position > from { position - from } else { from - position };
+			running_time + distance.div_f64(rate)
+		};
+		self.position = target;
+		let index = self.frame_index(target);
+		match self.frame {
+			Some(frame) if frame == index => {}
+			Some(frame) if frame < index && self.rate >= 0.0 => {
+				for index in frame + 1..=index {
+					self.clock.set(due(self.frame_start(index)));
+					self.show(index);
+				}
+			}
+			_ => {
+				self.clock.set(due(target));
+				self.show(index);
+			}
+		}
+		self.clock.set(due(target));
+	}
+
+	fn running_time(&self) -> Duration {
+		self.clock.running_time().unwrap_or_default()
+	}
+
+	fn frame_start(&self, index: u64) -> Duration {
+		Duration::from_secs_f64(index as f64 / self.media.framerate)
+	}
+
+	fn frame_index(&self, position: Duration) -> u64 {
+		let index = (position.as_secs_f64() * self.media.framerate) as u64;
+		// The last frame starts before the end of the media.
+		if !self.media.live && position >= self.media.duration {
+			index.saturating_sub(1)
+		} else {
+			index
+		}
+	}
+
+	/// Send frame `index`: a gradient with the blue channel counting frames,
+	/// so every frame can be told apart.
+	fn show(&mut self, index: u64) {
+		let (width, height) = (self.media.width, self.media.height);
+		let mut data = Vec::with_capacity(width as usize * height as usize * 4);
+		for y in 0..height {
+			for x in 0..width {
+				data.extend_from_slice(&[
+					(x * 255 / width.max(1)) as u8,
+					(y * 255 / height.max(1)) as u8,
+					index as u8,
+					255,
+				]);
+			}
+		}
+		let frame = VideoFrame {
+			data: FrameData::from(data),
+			width,
+			height,
+			pixel_aspect_ratio: (1, 1),
+			pts: Some(self.frame_start(index)),
+			running_time: self.clock.running_time(),
+			clock: Some(self.clock.clone() as SharedClock),
+			number: self.sent,
+			colorimetry: Colorimetry::default(),
+		};
+		self.frame = Some(index);
+		self.sent += 1;
+		let _ = self.sink.frame(frame);
+	}
+}
+
+impl MediaBackend for FakeBackend {
+	/// Open the default [`FakeMedia`], whatever `uri` is.
+	fn open(
+		_uri: &url::Url,
+		_options: &PlayerOptions,
+		sink: SharedSink,
+	) -> Result<Self, VideoError> {
+		Ok(Self::new(FakeMedia::default(), sink))
+	}
+
+	fn play(&mut self) -> Result<(), VideoError> {
+		self.fake.lock().unwrap().playing = true;
+		Ok(())
+	}
+
+	fn pause(&mut self) -> Result<(), VideoError> {
+		self.fake.lock().unwrap().playing = false;
+		Ok(())
+	}
+
+	fn seek(&mut self, position: Duration) -> Result<(), VideoError> {
+		let mut fake = self.fake.lock().unwrap();
+		if fake.media.live {
+			return Err(VideoError::Seek);
+		}
+		fake.ended = false;
+		let position = position.min(fake.media.duration);
+		// Events scripted before the new position are skipped, like the media is.
+		fake.script.retain(|(at, _)| *at > position);
+		fake.position = position;
+		let index = fake.frame_index(position);
+		fake.show(index);
+		Ok(())
+	}
+
+	fn set_rate(&mut self, rate: f64) -> Result<(), VideoError> {
+		let mut fake = self.fake.lock().unwrap();
+		if fake.media.live && rate != 1.0 {
+			return Err(VideoError::Seek);
+		}
+		fake.rate = rate;
+		Ok(())
+	}
+
+	fn set_volume(&mut self, volume: f64) {
+		self.fake.lock().unwrap().volume = volume;
+	}
+
+	fn set_muted(&mut self, muted: bool) {
+		self.fake.lock().unwrap().muted = muted;
+	}
+
+	fn position(&self) -> Duration {
+		self.fake.lock().unwrap().position
+	}
+
+	fn duration(&self) -> Duration {
+		let fake = self.fake.lock().unwrap();
+		if fake.media.live {
+			Duration::ZERO
+		} else {
+			fake.media.duration
+		}
+	}
+
+	fn live(&self) -> bool {
+		self.fake.lock().unwrap().media.live
+	}
+
+	fn tracks(&self) -> Vec<Track> {
+		self.fake.lock().unwrap().media.tracks.clone()
+	}
+
+	fn select_track(&mut self, kind: TrackKind, index: usize) -> Result<(), VideoError> {
+		let mut fake = self.fake.lock().unwrap();
+		let tracks = &mut fake.media.tracks;
+		if !tracks.iter().any(|track| track.kind == kind && track.index == index) {
+			return Err(VideoError::Track);
+		}
+		for track in tracks.iter_mut().filter(|track| track.kind == kind) {
+			track.selected = track.index == index;
+		}
+		Ok(())
+	}
+}
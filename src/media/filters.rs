@@ -0,0 +1,222 @@
+//! Rust filters transforming the decoded frames.
+//!
+//! Filters run in a [`FilterChain`] on the RGBA frames right before they are
+//! shown, handed to frame taps or exported. Recordings of a stream are written
+//! as it comes in, before decoding, so they don't go through the filters.
+use std::{
+	fmt,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+/// A decoded frame handed to video filters, only valid during the call.
+#[derive(Debug)]
+pub struct FrameMut<'a> {
+	pub(crate) data: &'a mut [u8],
+	pub(crate) width: u32,
+	pub(crate) height: u32,
+	pub(crate) stride: usize,
+	pub(crate) pts: Option<Duration>,
+	pub(crate) number: u64,
+}
+
+impl<'a> FrameMut<'a> {
+	/// Wrap RGBA pixels, `height` rows of `stride` bytes.
+	pub fn new(data: &'a mut [u8], width: u32, height: u32, stride: usize) -> Self {
+		Self { data, width, height, stride, pts: None, number: 0 }
+	}
+
+	/// Pixel data, `height` rows of `stride` bytes.
+	pub fn data(&mut self) -> &mut [u8] {
+		self.data
+	}
+
+	/// The pixels of row `y`, without padding.
+	pub fn row(&mut self, y: u32) -> &mut [u8] {
+		let start = y as usize * self.stride;
+		&mut self.data[start..start + self.width as usize * 4]
+	}
+
+	/// Width in pixels.
+	pub fn width(&self) -> u32 {
+		self.width
+	}
+
+	/// Height in pixels.
+	pub fn height(&self) -> u32 {
+		self.height
+	}
+
+	/// Bytes from the start of one row to the next.
+	pub fn stride(&self) -> usize {
+		self.stride
+	}
+
+	/// Presentation timestamp in the media.
+	pub fn pts(&self) -> Option<Duration> {
+		self.pts
+	}
+
+	/// Number of the frame since the player started, counting from zero.
+	pub fn number(&self) -> u64 {
+		self.number
+	}
+
+	/// Call `f` with the RGBA values of every pixel.
+	pub fn for_each_pixel(&mut self, mut f: impl FnMut(&mut [u8])) {
+		for y in 0..self.height {
+			self.row(y).chunks_exact_mut(4).for_each(&mut f);
+		}
+	}
+}
+
+/// Transforms decoded frames in place.
+pub trait VideoFilter: Send {
+	/// Transform `frame`, on the streaming thread. Slow filters hold up
+	/// playback.
+	fn process(&mut self, frame: &mut FrameMut);
+}
+
+impl<F: FnMut(&mut FrameMut) + Send> VideoFilter for F {
+	fn process(&mut self, frame: &mut FrameMut) {
+		self(frame)
+	}
+}
+
+/// Handle of a filter in a [`FilterChain`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FilterId(u64);
+
+struct Entry {
+	id: FilterId,
+	filter: Box<dyn VideoFilter>,
+	enabled: bool,
+}
+
+#[derive(Default)]
+struct Chain {
+	next_id: u64,
+	entries: Vec<Entry>,
+}
+
+/// Filters run one after the other, in the order they were added.
+///
+/// Clones share the same filters, so a chain can be used by the player and
+/// for exports at the same time.
+#[derive(Clone, Default)]
+pub struct FilterChain {
+	chain: Arc<Mutex<Chain>>,
+}
+
+impl fmt::Debug for FilterChain {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("FilterChain")
+			.field("len", &self.chain.lock().unwrap().entries.len())
+			.finish()
+	}
+}
+
+impl FilterChain {
+	/// Append `filter` to the chain, enabled.
+	pub fn add(&self, filter: impl VideoFilter + 'static) -> FilterId {
+		let mut chain = self.chain.lock().unwrap();
+		let id = FilterId(chain.next_id);
+		chain.next_id += 1;
+		chain.entries.push(Entry { id, filter: Box::new(filter), enabled: true });
+		id
+	}
+
+	/// Turn a filter on or off without losing its state.
+	pub fn set_enabled(&self, id: FilterId, enabled: bool) {
+		let mut chain = self.chain.lock().unwrap();
+		if let Some(entry) = chain.entries.iter_mut().find(|entry| entry.id == id) {
+			entry.enabled = enabled;
+		}
+	}
+
+	/// Take a filter out of the chain.
+	pub fn remove(&self, id: FilterId) {
+		self.chain.lock().unwrap().entries.retain(|entry| entry.id != id);
+	}
+
+	/// Whether any filter would change a frame.
+	pub fn is_active(&self) -> bool {
+		self.chain.lock().unwrap().entries.iter().any(|entry| entry.enabled)
+	}
+
+	/// Run the enabled filters on `frame`.
+	pub fn apply(&self, frame: &mut FrameMut) {
+		let mut chain = self.chain.lock().unwrap();
+		for entry in chain.entries.iter_mut().filter(|entry| entry.enabled) {
+			entry.filter.process(frame);
+		}
+	}
+}
+
+/// Rec. 601 luma of a RGBA pixel.
+fn luma(pixel: &[u8]) -> u8 {
+	((pixel[0] as u32 * 299 + pixel[1] as u32 * 587 + pixel[2] as u32 * 114) / 1000) as u8
+}
+
+/// Drops the colors, keeping the luma.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Grayscale;
+
+impl VideoFilter for Grayscale {
+	fn process(&mut self, frame: &mut FrameMut) {
+		frame.for_each_pixel(|pixel| {
+			let luma = luma(pixel);
+			pixel[..3].fill(luma);
+		});
+	}
+}
+
+/// Inverts the colors, keeping alpha.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Invert;
+
+impl VideoFilter for Invert {
+	fn process(&mut self, frame: &mut FrameMut) {
+		frame.for_each_pixel(|pixel| {
+			for channel in &mut pixel[..3] {
+				*channel = 255 - *channel;
+			}
+		});
+	}
+}
+
+/// Sobel edge detection, edges come out white on black.
+#[derive(Debug, Clone, Default)]
+pub struct EdgeDetect {
+	/// Luma of the frame being processed, kept to not allocate every frame.
+	luma: Vec<u8>,
+}
+
+impl VideoFilter for EdgeDetect {
+	fn process(&mut self, frame: &mut FrameMut) {
+		let (width, height) = (frame.width as usize, frame.height as usize);
+		self.luma.clear();
+		for y in 0..frame.height {
+			self.luma.extend(frame.row(y).chunks_exact(4).map(luma));
+		}
+		for y in 0..height {
+			let row = frame.row(y as u32);
+			for x in 0..width {
+				let magnitude = if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+					0
+				} else {
+					sobel(&self.luma, width, x, y)
+				};
+				row[x * 4..x * 4 + 3].fill(magnitude);
+			}
+		}
+	}
+}
+
+/// Gradient magnitude at `(x, y)` of the `width` wide luma plane.
+fn sobel(luma: &[u8], width: usize, x: usize, y: usize) -> u8 {
+	let at = |dx: usize, dy: usize| luma[(y + dy - 1) * width + x + dx - 1] as i32;
+	let gx = at(2, 0) + 2 * at(2, 1) + at(2, 2) - at(0, 0) - 2 * at(0, 1) - at(0, 2);
+	let gy = at(0, 2) + 2 * at(1, 2) + at(2, 2) - at(0, 0) - 2 * at(1, 0) - at(2, 0);
+	(((gx * gx + gy * gy) as f32).sqrt() as i32).min(255) as u8
+}
@@ -0,0 +1,266 @@
+// A player of frames and audio pushed by the application, playing the tracks
+// of `frames` and writing them to files.
+use std::{
+	fmt,
+	path::Path,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+use gst::prelude::*;
+use gstreamer as gst;
+use gstreamer_app as gst_app;
+
+use crate::media::{
+	error::VideoError,
+	filters::{FilterChain, FrameMut},
+	frames::{self, SharedTrack, Track},
+	options::{FrameSourceConfig, PlayerOptions},
+	player::{audio_sink_bin, video_sink_bin, PlayerEvent, SharedSink, VideoPlayer},
+	tap::SharedTaps,
+};
+
+/// Plays frames and audio generated by the application.
+///
+/// Clones share the same streams, so one clone can be handed to the view
+/// while another one keeps pushing.
+#[derive(Clone)]
+pub struct FrameSourcePlayer {
+	pub(crate) config: FrameSourceConfig,
+	pub(crate) video: SharedTrack,
+	pub(crate) audio: Option<SharedTrack>,
+	/// Sink of the view playing the source, to report the growing duration.
+	pub(crate) event_sink: Arc<Mutex<Option<SharedSink>>>,
+}
+
+impl fmt::Debug for FrameSourcePlayer {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("FrameSourcePlayer").field("config", &self.config).finish()
+	}
+}
+
+impl FrameSourcePlayer {
+	/// Create a source for media in the format described by `config`.
+	pub fn new(config: FrameSourceConfig) -> Self {
+		let audio = config.audio.map(|_| Track::new(config.history));
+		Self { video: Track::new(config.history), audio, config, event_sink: Arc::default() }
+	}
+
+	/// Push a frame of tightly packed RGBA pixels to be shown at `pts`.
+	///
+	/// Timestamps are relative to the start of the stream, which is zero.
+	pub fn push_rgba(&self, data: Vec<u8>, pts: Duration) -> Result<(), VideoError> {
+		let (width, height) = (self.config.width as usize, self.config.height as usize);
+		if data.len() != width * height * 4 {
+			return Err(VideoError::Caps);
+		}
+		let (numer, denom) = self.config.framerate;
+		let mut buffer = gst::Buffer::from_mut_slice(data);
+		if numer > 0 {
+			buffer.get_mut().unwrap().set_duration(gst::ClockTime::from_nseconds(
+				gst::ClockTime::SECOND.nseconds() * denom as u64 / numer as u64,
+			));
+		}
+		let previous = self.video.lock().unwrap().newest();
+		frames::push(&self.video, buffer, pts);
+		self.report_duration(previous, pts);
+		Ok(())
+	}
+
+	/// Push interleaved audio samples starting at `pts`.
+	pub fn push_audio(&self, samples: &[f32], pts: Duration) -> Result<(), VideoError> {
+		let (format, track) = match (self.config.audio, self.audio.as_ref()) {
+			(Some(format), Some(track)) => (format, track),
+			_ => return Err(VideoError::Caps),
+		};
+		let sample_frames = samples.len() as u64 / format.channels.max(1) as u64;
+		let data: Vec<u8> = samples.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+		let mut buffer = gst::Buffer::from_mut_slice(data);
+		buffer.get_mut().unwrap().set_duration(gst::ClockTime::from_nseconds(
+			gst::ClockTime::SECOND.nseconds() * sample_frames / format.rate.max(1) as u64,
+		));
+		frames::push(track, buffer, pts);
+		Ok(())
+	}
+
+	/// End the streams after the media pushed so far.
+	pub fn finish(&self) {
+		frames::finish(&self.video);
+		if let Some(ref audio) = self.audio {
+			frames::finish(audio);
+		}
+	}
+
+	/// Create a player for the pushed media, reporting to `event_sink`.
+	///
+	/// Playback starts with the oldest frame in the history. Opening waits for
+	/// the first frame (and audio, if configured) for up to five seconds.
+	pub fn open(
+		&self,
+		options: &PlayerOptions,
+		event_sink: SharedSink,
+	) -> Result<VideoPlayer, VideoError> {
+		gst::init()?;
+		let pipeline = gst::Pipeline::new(None);
+
+		let video_src = app_src("video")?;
+		let frame_taps = SharedTaps::default();
+		let audio_taps = SharedTaps::default();
+		let filters = FilterChain::default();
+		let video_sink = video_sink_bin(event_sink.clone(), frame_taps.clone(), filters.clone())?;
+		pipeline.add_many(&[
+			video_src.upcast_ref::<gst::Element>(),
+			video_sink.upcast_ref::<gst::Element>(),
+		])?;
+		video_src.link(&video_sink)?;
+		frames::attach(&self.video, &video_src, &self.video_caps());
+
+		if let (Some(track), Some(caps)) = (self.audio.as_ref(), self.audio_caps()) {
+			let audio_src = app_src("audio")?;
+			// Named so the player finds it for volume and mute.
+			let volume = gst::ElementFactory::make("volume", Some("volume"))?;
+			let audio_sink = audio_sink_bin(audio_taps.clone())?;
+			let elements = [
+				audio_src.upcast_ref::<gst::Element>(),
+				&volume,
+				audio_sink.upcast_ref::<gst::Element>(),
+			];
+			pipeline.add_many(&elements)?;
+			gst::Element::link_many(&elements)?;
+			frames::attach(track, &audio_src, &caps);
+		}
+
+		*self.event_sink.lock().unwrap() = Some(event_sink.clone());
+		let mut player =
+			VideoPlayer::from_pipeline(pipeline.upcast(), &video_sink, false, options, event_sink)?;
+		player.growing = true;
+		player.frame_taps = frame_taps;
+		player.audio_taps = audio_taps;
+		player.filters = filters;
+		if player.width == 0 {
+			player.width = self.config.width as i32;
+			player.height = self.config.height as i32;
+		}
+		Ok(player)
+	}
+
+	/// Encode the media in the history to a Matroska file at `path`, H.264
+	/// video and Opus audio. Blocks until the file is written.
+	pub fn encode(&self, path: impl AsRef<Path>) -> Result<(), VideoError> {
+		self.encode_filtered(path, &FilterChain::default())
+	}
+
+	/// Same as [`FrameSourcePlayer::encode`] but runs the frames through
+	/// `filters`, like [`VideoPlayer::filters`] of the player showing them.
+	pub fn encode_filtered(
+		&self,
+		path: impl AsRef<Path>,
+		filters: &FilterChain,
+	) -> Result<(), VideoError> {
+		gst::init()?;
+		let mut description =
+			String::from("appsrc name=video ! videoconvert ! x264enc ! matroskamux name=mux");
+		description.push_str(" ! filesink name=sink");
+		if self.audio.is_some() {
+			description
+				.push_str(" appsrc name=audio ! audioconvert ! audioresample ! opusenc ! mux.");
+		}
+		let pipeline = gst::parse_launch(&description)?
+			.downcast::<gst::Pipeline>()
+			.map_err(|_| VideoError::Cast)?;
+		let sink = pipeline.by_name("sink").ok_or(VideoError::Cast)?;
+		sink.set_property("location", path.as_ref().to_string_lossy().as_ref());
+
+		// Buffers are reference counted, queuing the whole history copies nothing.
+		let mut streams = vec![("video", &self.video, self.video_caps())];
+		if let (Some(track), Some(caps)) = (self.audio.as_ref(), self.audio_caps()) {
+			streams.push(("audio", track, caps));
+		}
+		for (name, track, caps) in streams {
+			let appsrc = pipeline
+				.by_name(name)
+				.and_then(|element| element.downcast::<gst_app::AppSrc>().ok())
+				.ok_or(VideoError::Cast)?;
+			appsrc.set_caps(Some(&caps));
+			appsrc.set_format(gst::Format::Time);
+			let buffers = track.lock().unwrap().buffers();
+			let start = buffers.first().and_then(|buffer| buffer.pts()).unwrap_or_default();
+			let filtered = name == "video" && filters.is_active();
+			for (number, mut buffer) in buffers.into_iter().enumerate() {
+				// The file starts with the oldest frame in the history.
+				let buffer_mut = buffer.make_mut();
+				let pts = buffer_mut.pts().map(|pts| pts.saturating_sub(start));
+				buffer_mut.set_pts(pts);
+				if filtered {
+					// Writing copies the pixels, the history keeps the original frame.
+					let mut map = buffer_mut.map_writable()?;
+					filters.apply(&mut FrameMut {
+						data: map.as_mut_slice(),
+						width: self.config.width,
+						height: self.config.height,
+						stride: self.config.width as usize * 4,
+						pts: pts.map(|pts| Duration::from_nanos(pts.nseconds())),
+						number: number as u64,
+					});
+				}
+				appsrc.push_buffer(buffer)?;
+			}
+			appsrc.end_of_stream()?;
+		}
+
+		pipeline.set_state(gst::State::Playing)?;
+		let bus = pipeline.bus().ok_or(VideoError::Bus)?;
+		let message = bus.timed_pop_filtered(
+			gst::ClockTime::NONE,
+			&[gst::MessageType::Eos, gst::MessageType::Error],
+		);
+		pipeline.set_state(gst::State::Null)?;
+		match message.as_ref().map(|message| message.view()) {
+			Some(gst::MessageView::Error(err)) => Err(err.error().into()),
+			Some(_) => Ok(()),
+			None => Err(VideoError::Sync),
+		}
+	}
+
+	fn video_caps(&self) -> gst::Caps {
+		let (numer, denom) = self.config.framerate;
+		gst::Caps::new_simple(
+			"video/x-raw",
+			&[
+				("format", &"RGBA"),
+				("width", &(self.config.width as i32)),
+				("height", &(self.config.height as i32)),
+				("framerate", &gst::Fraction::new(numer, denom)),
+			],
+		)
+	}
+
+	fn audio_caps(&self) -> Option<gst::Caps> {
+		let format = self.config.audio?;
+		Some(gst::Caps::new_simple(
+			"audio/x-raw",
+			&[
+				("format", &"F32LE"),
+				("layout", &"interleaved"),
+				("rate", &format.rate),
+				("channels", &format.channels),
+			],
+		))
+	}
+
+	/// Tell the player sink how far the media goes, once per second of media.
+	fn report_duration(&self, previous: Option<Duration>, pts: Duration) {
+		if previous.map_or(false, |previous| previous.as_secs() == pts.as_secs()) {
+			return;
+		}
+		if let Some(ref event_sink) = *self.event_sink.lock().unwrap() {
+			let _ = event_sink.event(PlayerEvent::Duration(pts));
+		}
+	}
+}
+
+fn app_src(name: &str) -> Result<gst_app::AppSrc, VideoError> {
+	gst::ElementFactory::make("appsrc", Some(name))?
+		.downcast::<gst_app::AppSrc>()
+		.map_err(|_| VideoError::Cast)
+}
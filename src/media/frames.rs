@@ -0,0 +1,268 @@
+// Playback of frames pushed from Rust.
+
+// Every pushed buffer is kept in a bounded history and fed to a seekable
+// appsrc. While the pipeline keeps up buffers go out as they are pushed,
+// when it is paused or seeked they are replayed from the history:
+
+// {appsrc (seekable)} - {video sink bin}
+// {appsrc (seekable)} - {volume} - {audio sink bin}
+use std::{
+	collections::VecDeque,
+	sync::{Arc, Mutex},
+	time::Duration,
+};
+
+use gstreamer as gst;
+use gstreamer_app as gst_app;
+
+/// Buffers of one stream, shared between the pushing side and the appsrc
+/// callbacks.
+pub type SharedTrack = Arc<Mutex<Track>>;
+
+#[derive(Debug)]
+struct Entry {
+	pts: Duration,
+	buffer: gst::Buffer,
+}
+
+/// History of a pushed stream and the appsrc it plays through.
+#[derive(Debug)]
+pub struct Track {
+	history: VecDeque<Entry>,
+	max_history: Duration,
+	/// Sequence number of the oldest entry in the history.
+	first_seq: u64,
+	/// Next entry to replay, `None` while buffers go out as they are pushed.
+	cursor: Option<u64>,
+	finished: bool,
+	appsrc: Option<gst_app::AppSrc>,
+}
+
+impl Track {
+	/// Create a track which keeps `max_history` worth of buffers.
+	pub fn new(max_history: Duration) -> SharedTrack {
+		Arc::new(Mutex::new(Self {
+			history: VecDeque::new(),
+			max_history,
+			first_seq: 0,
+			cursor: None,
+			finished: false,
+			appsrc: None,
+		}))
+	}
+
+	/// Timestamp of the newest buffer.
+	pub fn newest(&self) -> Option<Duration> {
+		self.history.back().map(|entry| entry.pts)
+	}
+
+	/// All buffers in the history, oldest first.
+	pub fn buffers(&self) -> Vec<gst::Buffer> {
+		self.history.iter().map(|entry| entry.buffer.clone()).collect()
+	}
+
+	/// Sequence number the next pushed entry gets.
+	fn end_seq(&self) -> u64 {
+		self.first_seq + self.history.len() as u64
+	}
+
+	/// Take the entry at the cursor, or go back to pushing directly once the
+	/// replay caught up.
+	fn next_replayed(&mut self) -> Option<gst::Buffer> {
+		let cursor = self.cursor?.max(self.first_seq);
+		match self.history.get((cursor - self.first_seq) as usize) {
+			Some(entry) => {
+				self.cursor = Some(cursor + 1);
+				Some(entry.buffer.clone())
+			}
+			None => {
+				self.cursor = None;
+				None
+			}
+		}
+	}
+
+	/// The appsrc queue is full: buffers pushed from now on wait in the
+	/// history until the appsrc asks for them.
+	fn hold_back(&mut self) {
+		if self.cursor.is_none() {
+			self.cursor = Some(self.end_seq());
+		}
+	}
+
+	/// Replay from the first buffer at or after `offset`.
+	fn seek(&mut self, offset: Duration) {
+		// Seeking before the history starts plays its oldest buffer.
+		let index = self.history.partition_point(|entry| entry.pts < offset);
+		self.cursor = Some(self.first_seq + index as u64);
+	}
+}
+
+/// Add `buffer` with timestamp `pts` to the track, and push it right away if
+/// the pipeline isn't behind.
+pub fn push(track: &SharedTrack, mut buffer: gst::Buffer, pts: Duration) {
+	buffer.make_mut().set_pts(gst::ClockTime::from_nseconds(pts.as_nanos() as u64));
+	// The appsrc may call back into the track while pushing, so push unlocked.
+	let appsrc = {
+		let mut track = track.lock().unwrap();
+		track.history.push_back(Entry { pts, buffer: buffer.clone() });
+		while let Some(oldest) = track.history.front() {
+			if pts.saturating_sub(oldest.pts) <= track.max_history {
+				break;
+			}
+			track.history.pop_front();
+			track.first_seq += 1;
+		}
+		track.appsrc.clone().filter(|_| track.cursor.is_none())
+	};
+	if let Some(appsrc) = appsrc {
+		let _ = appsrc.push_buffer(buffer);
+	}
+}
+
+/// No more buffers get pushed, the stream ends after the last one.
+pub fn finish(track: &SharedTrack) {
+	let appsrc = {
+		let mut track = track.lock().unwrap();
+		track.finished = true;
+		track.appsrc.clone().filter(|_| track.cursor.is_none())
+	};
+	if let Some(appsrc) = appsrc {
+		let _ = appsrc.end_of_stream();
+	}
+}
+
+/// Play `track` through `appsrc`, starting with the oldest buffer in the
+/// history.
+pub fn attach(track: &SharedTrack, appsrc: &gst_app::AppSrc, caps: &gst::Caps) {
+	appsrc.set_caps(Some(caps));
+	appsrc.set_format(gst::Format::Time);
+	appsrc.set_stream_type(gst_app::AppStreamType::Seekable);
+	{
+		let mut track = track.lock().unwrap();
+		track.cursor = Some(track.first_seq);
+		track.appsrc = Some(appsrc.clone());
+	}
+
+	let need_track = track.clone();
+	let enough_track = track.clone();
+	let seek_track = track.clone();
+	appsrc.set_callbacks(
+		gst_app::AppSrcCallbacks::builder()
+			.need_data(move |appsrc, _| {
+				let (buffer, finished) = {
+					let mut track = need_track.lock().unwrap();
+					(track.next_replayed(), track.finished)
+				};
+				match buffer {
+					Some(buffer) => {
+						let _ = appsrc.push_buffer(buffer);
+					}
+					None if finished => {
+						let _ = appsrc.end_of_stream();
+					}
+					// Caught up, the next pushed buffer goes out directly.
+					None => {}
+				}
+			})
+			.enough_data(move |_| enough_track.lock().unwrap().hold_back())
+			.seek_data(move |_, offset| {
+				seek_track.lock().unwrap().seek(Duration::from_nanos(offset));
+				true
+			})
+			.build(),
+	);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn secs(secs: u64) -> Duration {
+		Duration::from_secs(secs)
+	}
+
+	/// A track holding `max_history`, with a buffer pushed at each of `pts`.
+	fn track(max_history: Duration, pts: &[u64]) -> SharedTrack {
+		gst::init().unwrap();
+		let track = Track::new(max_history);
+		for &pts in pts {
+			push(&track, gst::Buffer::new(), secs(pts));
+		}
+		track
+	}
+
+	fn pts(buffer: Option<gst::Buffer>) -> Option<Duration> {
+		buffer.and_then(|buffer| buffer.pts()).map(|pts| Duration::from_nanos(pts.nseconds()))
+	}
+
+	#[test]
+	fn history_drops_the_oldest_buffers() {
+		let track = track(secs(2), &[0, 1, 2, 3, 4]);
+		let track = track.lock().unwrap();
+		let kept: Vec<_> = track.buffers().into_iter().map(|buffer| pts(Some(buffer))).collect();
+		assert_eq!(kept, [Some(secs(2)), Some(secs(3)), Some(secs(4))]);
+		assert_eq!(track.first_seq, 2);
+		assert_eq!(track.newest(), Some(secs(4)));
+	}
+
+	#[test]
+	fn pushed_buffers_wait_after_enough_data() {
+		let track = track(secs(10), &[0, 1]);
+		track.lock().unwrap().hold_back();
+		push(&track, gst::Buffer::new(), secs(2));
+		push(&track, gst::Buffer::new(), secs(3));
+
+		let mut track = track.lock().unwrap();
+		assert_eq!(pts(track.next_replayed()), Some(secs(2)));
+		assert_eq!(pts(track.next_replayed()), Some(secs(3)));
+		// Caught up, the next buffers go out as they are pushed.
+		assert_eq!(pts(track.next_replayed()), None);
+		assert_eq!(track.cursor, None);
+	}
+
+	#[test]
+	fn enough_data_keeps_a_replay_going() {
+		let track = track(secs(10), &[0, 1, 2]);
+		let mut track = track.lock().unwrap();
+		track.seek(secs(1));
+		track.hold_back();
+		assert_eq!(pts(track.next_replayed()), Some(secs(1)));
+	}
+
+	#[test]
+	fn seek_replays_from_the_offset() {
+		let track = track(secs(10), &[0, 1, 2, 3]);
+		let mut track = track.lock().unwrap();
+		track.seek(Duration::from_millis(1500));
+		assert_eq!(pts(track.next_replayed()), Some(secs(2)));
+		assert_eq!(pts(track.next_replayed()), Some(secs(3)));
+		assert_eq!(pts(track.next_replayed()), None);
+
+		track.seek(secs(3));
+		assert_eq!(pts(track.next_replayed()), Some(secs(3)));
+	}
+
+	#[test]
+	fn seek_outside_the_history() {
+		let track = track(secs(1), &[0, 1, 2, 3]);
+		let mut track = track.lock().unwrap();
+		// Before the oldest buffer kept.
+		track.seek(secs(0));
+		assert_eq!(pts(track.next_replayed()), Some(secs(2)));
+		// Past the newest one, playback waits for the next push.
+		track.seek(secs(5));
+		assert_eq!(pts(track.next_replayed()), None);
+		assert_eq!(track.cursor, None);
+	}
+
+	#[test]
+	fn replay_skips_evicted_buffers() {
+		let track = track(secs(1), &[0, 1]);
+		track.lock().unwrap().seek(secs(0));
+		push(&track, gst::Buffer::new(), secs(2));
+		push(&track, gst::Buffer::new(), secs(3));
+		let mut track = track.lock().unwrap();
+		assert_eq!(pts(track.next_replayed()), Some(secs(2)));
+	}
+}
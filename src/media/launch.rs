@@ -0,0 +1,103 @@
+// Pipelines from gst-launch descriptions.
+
+// The description is parsed as gst-launch-1.0 would, the frames are taken
+// from the appsink named `videosink` which it has to end in:
+
+// {description ...} - {appsink name=videosink}
+use gst::prelude::*;
+use gstreamer as gst;
+use gstreamer_app as gst_app;
+
+use crate::media::error::VideoError;
+
+/// Media items starting with this are pipeline descriptions.
+pub const SCHEME: &str = "launch:";
+
+/// Name of the appsink the frames are taken from.
+pub const VIDEO_SINK: &str = "videosink";
+
+/// Parse `description` into a pipeline and find its video appsink.
+pub fn parse(description: &str) -> Result<(gst::Pipeline, gst_app::AppSink), VideoError> {
+	gst::init()?;
+	let mut context = gst::ParseContext::new();
+	let element =
+		gst::parse_launch_full(description, Some(&mut context), gst::ParseFlags::FATAL_ERRORS)
+			.map_err(|err| VideoError::Launch {
+				position: error_position(description, &context),
+				message: err.to_string(),
+			})?;
+	let missing_sink = || VideoError::Launch {
+		message: format!("the pipeline has to end in `appsink name={}`", VIDEO_SINK),
+		position: Some(description.trim_end().len()),
+	};
+	let pipeline = element.downcast::<gst::Pipeline>().map_err(|_| missing_sink())?;
+	let appsink = pipeline
+		.by_name(VIDEO_SINK)
+		.and_then(|sink| sink.downcast::<gst_app::AppSink>().ok())
+		.ok_or_else(missing_sink)?;
+	Ok((pipeline, appsink))
+}
+
+/// Byte offset in `description` of what failed to parse.
+fn error_position(description: &str, context: &gst::ParseContext) -> Option<usize> {
+	// Unknown elements are the only errors GStreamer names the culprit of.
+	match context.missing_elements().first() {
+		Some(name) => token_position(description, name),
+		None => syntax_error_position(description),
+	}
+}
+
+/// Find `token` as a whole word of the description.
+fn token_position(description: &str, token: &str) -> Option<usize> {
+	let delimiter = |c: char| c.is_whitespace() || c == '!';
+	description.match_indices(token).map(|(index, _)| index).find(|&index| {
+		let before = description[..index].chars().next_back();
+		let after = description[index + token.len()..].chars().next();
+		before.map_or(true, delimiter) && after.map_or(true, |c| delimiter(c) || c == '.')
+	})
+}
+
+/// Find links missing an element on either side and unterminated quotes.
+fn syntax_error_position(description: &str) -> Option<usize> {
+	let mut quote = None;
+	let mut after_link = Some(0);
+	for (index, c) in description.char_indices() {
+		match c {
+			'"' if quote.is_some() => quote = None,
+			'"' => quote = Some(index),
+			_ if quote.is_some() => {}
+			'!' if after_link.is_some() => return Some(index),
+			'!' => after_link = Some(index),
+			_ if c.is_whitespace() => {}
+			_ => after_link = None,
+		}
+	}
+	quote.or(after_link.filter(|&index| index > 0))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn tokens_are_whole_words() {
+		let description = "videotestsrc ! video ! videoconvert ! appsink name=videosink";
+		assert_eq!(token_position(description, "video"), Some(15));
+		assert_eq!(token_position(description, "videoconvert"), Some(23));
+		assert_eq!(token_position("src!sink", "sink"), Some(4));
+		// Pads of a named element follow a dot.
+		assert_eq!(token_position("demux.video_0 ! queue", "demux"), Some(0));
+		assert_eq!(token_position(description, "convert"), None);
+	}
+
+	#[test]
+	fn syntax_errors() {
+		assert_eq!(syntax_error_position("videotestsrc ! ! appsink"), Some(15));
+		assert_eq!(syntax_error_position("! videotestsrc"), Some(0));
+		assert_eq!(syntax_error_position("videotestsrc ! appsink !  "), Some(23));
+		assert_eq!(syntax_error_position(r#"textoverlay text="a ! b"#), Some(17));
+		// Links within quotes don't count.
+		assert_eq!(syntax_error_position(r#"textoverlay text="!" ! appsink"#), None);
+		assert_eq!(syntax_error_position("videotestsrc ! appsink"), None);
+	}
+}
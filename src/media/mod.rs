@@ -1 +1,20 @@
+pub mod actor;
+pub mod adaptive;
+pub mod backend;
+pub mod devices;
+pub mod error;
+pub mod fake;
+pub mod filters;
+pub mod frame_source;
+pub mod frames;
+pub mod launch;
+pub mod options;
+pub mod player;
+pub mod present;
+pub mod reader;
+pub mod record;
+pub mod source;
+pub mod tap;
+pub mod testsrc;
 pub mod thumbnail;
+pub mod types;
@@ -0,0 +1,233 @@
+//! Options the players are built with.
+
+/// Network buffering tunables applied to `playbin`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BufferingOptions {
+	/// Maximum buffer size in bytes (`buffer-size`), `None` keeps the default.
+	pub buffer_size: Option<i32>,
+	/// Maximum buffered duration (`buffer-duration`), `None` keeps the default.
+	pub buffer_duration: Option<std::time::Duration>,
+	/// Buffer the whole stream to disk (the playbin `download` flag).
+	pub download: bool,
+}
+
+/// Lower transport used for RTSP sources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+	/// Let `rtspsrc` negotiate, trying UDP first.
+	Auto,
+	Tcp,
+	Udp,
+}
+
+impl Default for Transport {
+	fn default() -> Self {
+		Self::Auto
+	}
+}
+
+/// Options for RTSP, RTP/UDP and SRT sources.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkOptions {
+	/// RTSP transport.
+	pub transport: Transport,
+	/// Jitterbuffer latency for RTSP/RTP and the SRT receive latency.
+	pub latency: std::time::Duration,
+	/// Keep RTSP sessions alive and let SRT wait for the sender to come back.
+	pub reconnect: bool,
+}
+
+impl Default for NetworkOptions {
+	fn default() -> Self {
+		Self {
+			transport: Transport::default(),
+			latency: std::time::Duration::from_millis(200),
+			reconnect: true,
+		}
+	}
+}
+
+/// Options for HTTP(S) sources, applied to `souphttpsrc`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceOptions {
+	/// `User-Agent` header, `None` keeps the GStreamer default.
+	pub user_agent: Option<String>,
+	/// Extra request headers, e.g. `("Authorization", "Bearer ...")`.
+	pub headers: Vec<(String, String)>,
+	/// User name for HTTP authentication.
+	pub user_id: Option<String>,
+	/// Password for HTTP authentication.
+	pub user_pw: Option<String>,
+	/// Cookies as `name=value` strings.
+	pub cookies: Vec<String>,
+	/// Proxy URI, `None` uses the `http_proxy` environment variable.
+	pub proxy: Option<String>,
+	/// Refuse connections with invalid certificates.
+	pub ssl_strict: bool,
+	/// CA certificates to trust, e.g. for a self-signed server.
+	pub ssl_ca_file: Option<std::path::PathBuf>,
+}
+
+impl Default for SourceOptions {
+	fn default() -> Self {
+		Self {
+			user_agent: None,
+			headers: Vec::new(),
+			user_id: None,
+			user_pw: None,
+			cookies: Vec::new(),
+			proxy: None,
+			ssl_strict: true,
+			ssl_ca_file: None,
+		}
+	}
+}
+
+/// How to recover when the source of a stream drops.
+///
+/// Only resource errors are retried, broken or unsupported media fails
+/// right away.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+	/// Attempts before giving up, `0` disables retrying.
+	pub max_attempts: u32,
+	/// Wait before the first attempt, doubled for every further one.
+	pub initial_backoff: std::time::Duration,
+	/// Upper bound of the wait between attempts.
+	pub max_backoff: std::time::Duration,
+	/// Continue VOD streams where they dropped instead of the start.
+	pub resume_position: bool,
+}
+
+impl RetryPolicy {
+	/// Wait before the given attempt, starting with `1`.
+	pub fn backoff(&self, attempt: u32) -> std::time::Duration {
+		let factor = 1u32.checked_shl(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+		self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+	}
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		Self {
+			max_attempts: 5,
+			initial_backoff: std::time::Duration::from_millis(500),
+			max_backoff: std::time::Duration::from_secs(30),
+			resume_position: true,
+		}
+	}
+}
+
+/// Where live streams are recorded to and how much is kept to rewind in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordingOptions {
+	/// Directory recordings are written to.
+	pub dir: std::path::PathBuf,
+	/// Length of the timeshift buffer of live streams, `None` disables
+	/// timeshift.
+	pub timeshift: Option<std::time::Duration>,
+	/// Length of the files the timeshift buffer is split into, rewinding
+	/// reaches up to the last closed one.
+	pub segment: std::time::Duration,
+}
+
+impl Default for RecordingOptions {
+	fn default() -> Self {
+		Self {
+			dir: std::path::PathBuf::from("."),
+			timeshift: None,
+			segment: std::time::Duration::from_secs(10),
+		}
+	}
+}
+
+/// Options used when building a [`VideoPlayer`](crate::media::player::VideoPlayer).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlayerOptions {
+	pub buffering: BufferingOptions,
+	/// Pipeline latency for live sources, `None` lets the pipeline compute it.
+	pub latency: Option<std::time::Duration>,
+	pub network: NetworkOptions,
+	pub retry: RetryPolicy,
+	pub source: SourceOptions,
+	pub recording: RecordingOptions,
+	/// Keep the frames at the size of the video instead of scaling them down
+	/// to the view in the pipeline, for pixel-peeping.
+	pub native_resolution: bool,
+}
+
+/// Format of the audio pushed into a
+/// [`FrameSourcePlayer`](crate::media::frame_source::FrameSourcePlayer), samples are
+/// interleaved `f32`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioFormat {
+	pub rate: i32,
+	pub channels: i32,
+}
+
+/// Format of the media pushed into a
+/// [`FrameSourcePlayer`](crate::media::frame_source::FrameSourcePlayer).
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameSourceConfig {
+	pub width: u32,
+	pub height: u32,
+	/// Nominal framerate as `(numerator, denominator)`.
+	pub framerate: (i32, i32),
+	/// Format of the pushed audio, `None` if there is no audio.
+	pub audio: Option<AudioFormat>,
+	/// How much of the pushed media is kept to pause and seek in.
+	pub history: std::time::Duration,
+}
+
+impl FrameSourceConfig {
+	/// Video of the given size at 30 frames per second without audio, with a
+	/// minute of history.
+	pub fn new(width: u32, height: u32) -> Self {
+		Self {
+			width,
+			height,
+			framerate: (30, 1),
+			audio: None,
+			history: std::time::Duration::from_secs(60),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::*;
+
+	#[test]
+	fn backoff_doubles_up_to_the_cap() {
+		let retry = RetryPolicy::default();
+		let waits: Vec<_> = (1..=8).map(|attempt| retry.backoff(attempt).as_millis()).collect();
+		assert_eq!(waits, [500, 1000, 2000, 4000, 8000, 16000, 30000, 30000]);
+		// Attempt 0 is treated like the first.
+		assert_eq!(retry.backoff(0), Duration::from_millis(500));
+	}
+
+	#[test]
+	fn backoff_shift_overflow() {
+		let retry = RetryPolicy {
+			initial_backoff: Duration::from_nanos(1),
+			max_backoff: Duration::MAX,
+			..RetryPolicy::default()
+		};
+		assert_eq!(retry.backoff(32), Duration::from_nanos(1 << 31));
+		// 1 << 32 overflows a u32, the factor saturates instead of wrapping.
+		assert_eq!(retry.backoff(33), Duration::from_nanos(u32::MAX as u64));
+		assert_eq!(retry.backoff(u32::MAX), Duration::from_nanos(u32::MAX as u64));
+	}
+
+	#[test]
+	fn backoff_multiplication_overflow() {
+		let retry = RetryPolicy {
+			initial_backoff: Duration::MAX / 2,
+			max_backoff: Duration::from_secs(30),
+			..RetryPolicy::default()
+		};
+		assert_eq!(retry.backoff(3), Duration::from_secs(30));
+	}
+}
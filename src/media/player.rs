@@ -0,0 +1,1714 @@
+//! Playback core, independent of the UI toolkit.
+//!
+//! [`VideoPlayer`] builds and drives the pipeline. What it puts out, the
+//! decoded frames and the events of the pipeline, goes to a [`PlayerSink`].
+//! The druid widget implements it to fill its frame slot, anything else (a
+//! headless analyser, another toolkit) can bring its own:
+//!
+//! ```text
+//! {pipeline} - {appsink} - {PlayerSink::frame}
+//!            - {bus watch} - {PlayerSink::event}
+//! ```
+use std::{
+	fmt,
+	io::{self, Read},
+	path::PathBuf,
+	sync::{Arc, Condvar, Mutex},
+	time::{Duration, SystemTime},
+};
+
+use anyhow::Error;
+use gst::prelude::*;
+use gstreamer as gst;
+use gstreamer::{event::Seek, Element, SeekFlags, SeekType};
+use gstreamer_app as gst_app;
+use num_traits::ToPrimitive;
+
+use crate::media::{
+	adaptive, devices, launch,
+	present::{ElementClock, SharedClock},
+	reader::{self, RangeReader, SharedReader},
+	record::{self, Fragment, Progress, RecordingId, SharedStreams},
+	source::setup_http_source,
+	tap::{self, FrameSample, SharedTaps},
+	testsrc,
+};
+pub use crate::media::{
+	devices::CaptureSelection,
+	error::VideoError,
+	filters::{FilterChain, FilterId, FrameMut, VideoFilter},
+	options::{NetworkOptions, PlayerOptions, RetryPolicy, Transport},
+	tap::{AudioSamples, FrameRef, TapId, TapOptions},
+	testsrc::TestSourceConfig,
+	types::{
+		AdaptiveStats, BufferingInfo, Colorimetry, Orientation, Position, Quality, RecordStatus,
+		Variant, YuvMatrix,
+	},
+};
+
+/// What the player reports besides the frames.
+#[derive(Debug, Clone)]
+pub enum PlayerEvent {
+	/// Bandwidth and buffer level of an adaptive stream.
+	AdaptiveStats(AdaptiveStats),
+	/// The adaptive demuxer switched to the variant at this index.
+	VariantChanged(usize),
+	/// The pipeline is filling its buffers, or done with it.
+	Buffering(BufferingInfo),
+	/// A dropped network stream is being reopened, this is the attempt.
+	Reconnecting(u32),
+	/// The network stream plays again after reconnecting.
+	Reconnected,
+	/// Playback failed for good.
+	Error(String),
+	/// The end of the media was reached.
+	Ended,
+	/// The duration of a growing media changed.
+	Duration(Duration),
+}
+
+/// Pixels of a frame, shared instead of copied.
+///
+/// Decoded frames keep the GStreamer buffer they were decoded into mapped,
+/// cloning one only counts a reference.
+#[derive(Clone)]
+pub struct FrameData(Arc<dyn AsRef<[u8]> + Send + Sync>);
+
+impl FrameData {
+	/// The pixels, `width * 4` bytes per row.
+	pub fn as_slice(&self) -> &[u8] {
+		(*self.0).as_ref()
+	}
+}
+
+impl std::ops::Deref for FrameData {
+	type Target = [u8];
+
+	fn deref(&self) -> &[u8] {
+		self.as_slice()
+	}
+}
+
+impl fmt::Debug for FrameData {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "FrameData({} bytes)", self.as_slice().len())
+	}
+}
+
+impl From<Vec<u8>> for FrameData {
+	fn from(data: Vec<u8>) -> Self {
+		Self(Arc::new(data))
+	}
+}
+
+impl From<gst::MappedBuffer<gst::buffer::Readable>> for FrameData {
+	fn from(buffer: gst::MappedBuffer<gst::buffer::Readable>) -> Self {
+		Self(Arc::new(buffer))
+	}
+}
+
+/// A decoded frame as shown, tightly packed RGBA.
+#[derive(Debug, Clone)]
+pub struct VideoFrame {
+	/// RGBA pixels, `width * 4` bytes per row.
+	pub data: FrameData,
+	/// Width in pixels.
+	pub width: u32,
+	/// Height in pixels.
+	pub height: u32,
+	/// Width to height of a pixel as `(numerator, denominator)`, `(1, 1)` for
+	/// square pixels.
+	pub pixel_aspect_ratio: (i32, i32),
+	/// Presentation timestamp, if the buffer had one.
+	pub pts: Option<Duration>,
+	/// Running time of the pipeline the frame is due at, if it has a
+	/// timestamp.
+	pub running_time: Option<Duration>,
+	/// Clock of the pipeline to wait for the running time on.
+	pub clock: Option<SharedClock>,
+	/// Count of the frames shown before this one.
+	pub number: u64,
+	/// How the frame was coded before it was converted to RGBA.
+	pub colorimetry: Colorimetry,
+}
+
+/// The receiving side of a [`PlayerSink`] is gone, the player stops
+/// producing for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Disconnected;
+
+/// Receiver of what a [`VideoPlayer`] puts out.
+///
+/// Both methods are called from GStreamer threads and shouldn't block.
+pub trait PlayerSink: Send + Sync {
+	/// Take a decoded frame.
+	fn frame(&self, frame: VideoFrame) -> Result<(), Disconnected>;
+	/// Take an event of the pipeline.
+	fn event(&self, event: PlayerEvent) -> Result<(), Disconnected>;
+}
+
+/// [`PlayerSink`] shared between the threads of a player.
+pub type SharedSink = Arc<dyn PlayerSink>;
+
+/// Video player which handles multimedia playback.
+pub struct VideoPlayer {
+	pub bus: gst::Bus,
+	pub pipeline: gst::Element,
+
+	pub volume: f64,
+	pub width: i32,
+	pub height: i32,
+	pub framerate: f64,
+	pub duration: Duration,
+	/// The source is live, there is no duration and nothing to seek in.
+	pub live: bool,
+	/// Variants of an adaptive stream, empty for anything else.
+	pub variants: Vec<Variant>,
+	/// The adaptive demuxer playbin plugged, once there is one.
+	pub demuxer: Arc<Mutex<Option<gst::Element>>>,
+	/// The duration grows while playing, reaching it doesn't end playback.
+	pub growing: bool,
+	/// Subscribers to the decoded frames.
+	pub(crate) frame_taps: SharedTaps<FrameSample>,
+	/// Subscribers to the decoded audio.
+	pub(crate) audio_taps: SharedTaps<AudioSamples>,
+	/// Filters transforming the frames before they are shown.
+	pub(crate) filters: FilterChain,
+	/// Encoded streams of a playbin, for recording.
+	pub(crate) streams: SharedStreams,
+	/// Recording started by [`VideoPlayer::start_recording`] and its file.
+	pub(crate) recording: Option<(RecordingId, PathBuf)>,
+	/// Recording into the timeshift buffer.
+	pub(crate) timeshift: Option<RecordingId>,
+	/// Probes dropping the output while the player is hidden.
+	pub(crate) hidden: Vec<(gst::Pad, gst::PadProbeId)>,
+	/// Appsink of a [`video_sink_bin`], which can scale the frames down.
+	pub(crate) video_sink: Option<gst_app::AppSink>,
+	/// `videoflip` of a [`video_sink_bin`], rotating and mirroring the frames.
+	pub(crate) video_flip: Option<Element>,
+	/// Stops the bus watch from changing the state of the pipeline once the
+	/// player is dropped.
+	stop: Arc<StopFlag>,
+	/// Holds the pipeline paused while the bus watch sees its buffer filling.
+	pub(crate) buffering: Arc<Mutex<BufferingGate>>,
+	pub paused: bool,
+	pub muted: bool,
+	pub looping: bool,
+	pub is_eos: bool,
+	pub restart_stream: bool,
+}
+
+/// Name of the application message which stops the bus watch thread.
+const BUS_WATCH_QUIT: &str = "druid-video-quit";
+
+/// Name of the appsink in a [`video_sink_bin`].
+const VIDEO_APPSINK: &str = "video_appsink";
+
+/// Name of the `videoflip` in a [`video_sink_bin`] the user orients with.
+const VIDEO_FLIP: &str = "video_flip";
+
+/// `GST_FORMAT_PERCENT_MAX`, the scale of percent formatted values.
+const PERCENT_MAX: f64 = 1_000_000.0;
+
+impl Drop for VideoPlayer {
+	fn drop(&mut self) {
+		self.stop_recording();
+		if let Some(id) = self.timeshift.take() {
+			self.streams.lock().unwrap().stop(id);
+		}
+		// The bus watch may be about to restart the pipeline after a dropped connection.
+		self.stop.stop();
+		self.pipeline.set_state(gst::State::Null).expect("failed to set state");
+		let quit = gst::message::Application::new(gst::Structure::new_empty(BUS_WATCH_QUIT));
+		if self.bus.post(&quit).is_err() {
+			log::error!("Could not stop bus watch");
+		}
+	}
+}
+
+impl VideoPlayer {
+	/// Create a new video player from a given video which loads from `uri`.
+	///
+	/// If `live` is set then no duration is queried (as this will result in an
+	/// error and is non-sensical for live streams). Set `live` if the streaming
+	/// source is indefinite (e.g. a live stream). Note that this will cause the
+	/// duration to be zero. Sources which don't preroll are detected as live
+	/// even if `live` isn't set.
+	pub fn new(uri: &url::Url, live: bool, player_sink: SharedSink) -> Result<Self, VideoError> {
+		Self::with_options(uri, live, PlayerOptions::default(), player_sink)
+	}
+
+	/// Same as [`VideoPlayer::new`] but configures the pipeline from `options`.
+	pub fn with_options(
+		uri: &url::Url,
+		live: bool,
+		options: PlayerOptions,
+		player_sink: SharedSink,
+	) -> Result<Self, VideoError> {
+		if uri.scheme() == testsrc::SCHEME {
+			return Self::from_test_source(&testsrc::parse_uri(uri)?, options, player_sink);
+		}
+		Self::playbin(uri, live, options, player_sink, None)
+	}
+
+	/// Create a video player which plays a test pattern and tone.
+	///
+	/// This is what `testsrc://` URIs play, see [`TestSourceConfig`]. The
+	/// output only depends on the configuration, so it works as a fixture.
+	pub fn from_test_source(
+		config: &TestSourceConfig,
+		options: PlayerOptions,
+		player_sink: SharedSink,
+	) -> Result<Self, VideoError> {
+		gst::init()?;
+		let pipeline = gst::Pipeline::new(None);
+
+		let source = gst::ElementFactory::make("videotestsrc", None)?;
+		source.set_property_from_str("pattern", testsrc::pattern_nick(config.pattern));
+		let capsfilter = gst::ElementFactory::make("capsfilter", None)?;
+		capsfilter.set_property(
+			"caps",
+			gst::Caps::new_simple(
+				"video/x-raw",
+				&[
+					("width", &config.width),
+					("height", &config.height),
+					("framerate", &gst::Fraction::new(config.framerate.0, config.framerate.1)),
+				],
+			),
+		);
+		let frame_taps = SharedTaps::default();
+		let filters = FilterChain::default();
+		let video_sink = video_sink_bin(player_sink.clone(), frame_taps.clone(), filters.clone())?;
+		let elements = [&source, &capsfilter, video_sink.upcast_ref::<Element>()];
+		pipeline.add_many(&elements)?;
+		Element::link_many(&elements)?;
+
+		let audio_taps = SharedTaps::default();
+		if let Some(frequency) = config.frequency {
+			let source = gst::ElementFactory::make("audiotestsrc", None)?;
+			source.set_property("freq", frequency);
+			// Named so the player finds it for volume and mute.
+			let volume = gst::ElementFactory::make("volume", Some("volume"))?;
+			let audio_sink = audio_sink_bin(audio_taps.clone())?;
+			let elements = [&source, &volume, audio_sink.upcast_ref::<Element>()];
+			pipeline.add_many(&elements)?;
+			Element::link_many(&elements)?;
+		}
+
+		let mut player =
+			Self::from_pipeline(pipeline.upcast(), &video_sink, false, &options, player_sink)?;
+		player.frame_taps = frame_taps;
+		player.audio_taps = audio_taps;
+		player.filters = filters;
+		Ok(player)
+	}
+
+	/// Create a video player which plays the media read from `reader`.
+	///
+	/// The reader is read in random-access mode, so seeking works as with
+	/// files. It can be anything from an in-memory buffer to an entry of an
+	/// archive or a decrypting stream.
+	pub fn from_reader(
+		reader: impl Read + io::Seek + Send + 'static,
+		options: PlayerOptions,
+		player_sink: SharedSink,
+	) -> Result<Self, VideoError> {
+		let reader: SharedReader = Arc::new(Mutex::new(Box::new(reader)));
+		let uri = url::Url::parse("appsrc://").map_err(|_| VideoError::Uri)?;
+		Self::playbin(&uri, false, options, player_sink, Some(reader))
+	}
+
+	/// Create a video player which plays `len` bytes of media served by
+	/// `read_range`.
+	///
+	/// `read_range` fills the buffer with the bytes starting at the given
+	/// offset and returns how many it read, see [`VideoPlayer::from_reader`].
+	pub fn from_range_fn(
+		len: u64,
+		read_range: impl FnMut(u64, &mut [u8]) -> std::io::Result<usize> + Send + 'static,
+		options: PlayerOptions,
+		player_sink: SharedSink,
+	) -> Result<Self, VideoError> {
+		Self::from_reader(RangeReader::new(len, read_range), options, player_sink)
+	}
+
+	fn playbin(
+		uri: &url::Url,
+		live: bool,
+		options: PlayerOptions,
+		player_sink: SharedSink,
+		reader: Option<SharedReader>,
+	) -> Result<Self, VideoError> {
+		// Initialize GStreamer
+		gst::init()?;
+
+		// Build the pipeline
+		let pipeline = gst::ElementFactory::make("playbin", None).unwrap();
+		pipeline.set_property("uri", uri.as_str());
+		apply_buffering_options(&pipeline, &options)?;
+		connect_source_setup(&pipeline, &options, reader);
+		let demuxer = connect_adaptive_demuxer(&pipeline);
+		let streams = record::connect(&pipeline);
+		let variants = if adaptive::is_adaptive(uri) {
+			adaptive::variants(uri, &options.source).unwrap_or_else(|err| {
+				log::warn!("Failed to list the variants of {}: {}", uri, err);
+				Vec::new()
+			})
+		} else {
+			Vec::new()
+		};
+		let audio_taps = SharedTaps::default();
+		pipeline.set_property("audio-sink", &audio_sink_bin(audio_taps.clone())?);
+		let frame_taps = SharedTaps::default();
+		let filters = FilterChain::default();
+		let video_sink = video_sink_bin(player_sink.clone(), frame_taps.clone(), filters.clone())?;
+		pipeline.set_property("video-sink", &video_sink);
+		pipeline.set_property("volume", 0.9);
+
+		let mut player =
+			Self::start(pipeline, &video_sink, live, &options, variants, demuxer, player_sink)?;
+		player.frame_taps = frame_taps;
+		player.audio_taps = audio_taps;
+		player.filters = filters;
+		player.streams = streams;
+		// A live source may not have delivered a frame yet, its size comes with the frames.
+		if player.width == 0 && !player.live {
+			return Err(VideoError::Caps);
+		}
+		if let Some(window) = options.recording.timeshift.filter(|_| player.live) {
+			if let Err(err) = player.start_timeshift(window, options.recording.segment) {
+				log::warn!("No timeshift for {}: {}", uri, err);
+			}
+		}
+		Ok(player)
+	}
+
+	/// Create a video player which previews capture devices.
+	///
+	/// Frames are dropped rather than queued when rendering falls behind, so
+	/// the preview stays close to what the camera sees. This is what
+	/// `capture://` items play, with the devices from [`devices::list`].
+	pub fn from_capture(
+		selection: &CaptureSelection,
+		options: PlayerOptions,
+		player_sink: SharedSink,
+	) -> Result<Self, VideoError> {
+		let video = selection.video.as_deref().ok_or(VideoError::Uri)?;
+		let pipeline = gst::Pipeline::new(None);
+
+		let source = devices::source(video, true)?;
+		let capsfilter = gst::ElementFactory::make("capsfilter", None)?;
+		if let Some(mode) = selection.mode {
+			capsfilter.set_property(
+				"caps",
+				gst::Caps::new_simple(
+					"video/x-raw",
+					&[
+						("width", &mode.width),
+						("height", &mode.height),
+						("framerate", &gst::Fraction::new(mode.framerate.0, mode.framerate.1)),
+					],
+				),
+			);
+		}
+		let queue = leaky_queue()?;
+		let frame_taps = SharedTaps::default();
+		let filters = FilterChain::default();
+		let video_sink = video_sink_bin(player_sink.clone(), frame_taps.clone(), filters.clone())?;
+		let elements = [&source, &capsfilter, &queue, video_sink.upcast_ref::<Element>()];
+		pipeline.add_many(&elements)?;
+		Element::link_many(&elements)?;
+
+		let audio_taps = SharedTaps::default();
+		if let Some(ref audio) = selection.audio {
+			let source = devices::source(audio, false)?;
+			let queue = leaky_queue()?;
+			// Named so the player finds it for volume and mute.
+			let volume = gst::ElementFactory::make("volume", Some("volume"))?;
+			let audio_sink = audio_sink_bin(audio_taps.clone())?;
+			let elements = [&source, &queue, &volume, audio_sink.upcast_ref::<Element>()];
+			pipeline.add_many(&elements)?;
+			Element::link_many(&elements)?;
+		}
+
+		let mut player =
+			Self::from_pipeline(pipeline.upcast(), &video_sink, true, &options, player_sink)?;
+		player.frame_taps = frame_taps;
+		player.audio_taps = audio_taps;
+		player.filters = filters;
+		Ok(player)
+	}
+
+	/// Create a video player which plays a gst-launch pipeline description.
+	///
+	/// The description has to end in `appsink name=videosink`, after a
+	/// `videoconvert` so the appsink gets RGBA frames:
+	///
+	/// ```text
+	/// videotestsrc ! x264enc ! avdec_h264 ! videoconvert ! appsink name=videosink
+	/// ```
+	///
+	/// Audio goes to a sink of the description, a `volume name=volume`
+	/// element in front of it makes volume and mute work. Parse errors carry
+	/// the position of the culprit if it is known.
+	pub fn from_launch(
+		description: &str,
+		options: PlayerOptions,
+		player_sink: SharedSink,
+	) -> Result<Self, VideoError> {
+		let (pipeline, appsink) = launch::parse(description)?;
+		let frame_taps = SharedTaps::default();
+		let filters = FilterChain::default();
+		// The converter in front of the appsink sees the caps of the decoder.
+		let converted = appsink
+			.static_pad("sink")
+			.and_then(|pad| pad.peer())
+			.and_then(|pad| pad.parent_element())
+			.and_then(|convert| convert.static_pad("sink"));
+		attach_video_appsink(
+			&appsink,
+			converted,
+			player_sink.clone(),
+			frame_taps.clone(),
+			filters.clone(),
+		);
+
+		let mut player =
+			Self::from_pipeline(pipeline.upcast(), &appsink, false, &options, player_sink)?;
+		player.frame_taps = frame_taps;
+		player.filters = filters;
+		Ok(player)
+	}
+
+	/// Create a video player which plays the closed files of a timeshift
+	/// buffer one after the other.
+	pub(crate) fn from_fragments(
+		fragments: &[Fragment],
+		audio: bool,
+		options: &PlayerOptions,
+		player_sink: SharedSink,
+	) -> Result<Self, VideoError> {
+		gst::init()?;
+		let pipeline = gst::Pipeline::new(None);
+
+		// splitmuxsink reuses file names, so the files are listed in order
+		// instead of being globbed.
+		let source = gst::ElementFactory::make("splitmuxsrc", None)?;
+		let locations: Vec<String> =
+			fragments.iter().map(|fragment| fragment.path.to_string_lossy().into_owned()).collect();
+		source.connect("format-location", false, move |_| Some(locations.to_value()));
+
+		let frame_taps = SharedTaps::default();
+		let filters = FilterChain::default();
+		let video_sink = video_sink_bin(player_sink.clone(), frame_taps.clone(), filters.clone())?;
+		pipeline.add_many(&[&source, video_sink.upcast_ref::<gst::Element>()])?;
+		let video_pad = video_sink.static_pad("sink").ok_or(VideoError::Cast)?;
+
+		let audio_taps = SharedTaps::default();
+		let audio_pad = if audio {
+			// Named so the player finds it for volume and mute.
+			let volume = gst::ElementFactory::make("volume", Some("volume"))?;
+			let audio_sink = audio_sink_bin(audio_taps.clone())?;
+			pipeline.add_many(&[&volume, audio_sink.upcast_ref::<gst::Element>()])?;
+			volume.link(&audio_sink)?;
+			volume.static_pad("sink")
+		} else {
+			None
+		};
+
+		// The files hold the streams as they came in, every one gets a decoder.
+		let bin = pipeline.downgrade();
+		source.connect_pad_added(move |_, pad| {
+			let (bin, decodebin) =
+				match (bin.upgrade(), gst::ElementFactory::make("decodebin", None)) {
+					(Some(bin), Ok(decodebin)) => (bin, decodebin),
+					_ => {
+						log::error!("Failed to decode the timeshift buffer");
+						return;
+					}
+				};
+			let video_pad = video_pad.clone();
+			let audio_pad = audio_pad.clone();
+			decodebin.connect_pad_added(move |_, pad| {
+				let caps = pad.query_caps(None);
+				let media = caps.structure(0).map(|s| s.name());
+				let target = match media {
+					Some(media) if media.starts_with("video/") => Some(&video_pad),
+					Some(media) if media.starts_with("audio/") => audio_pad.as_ref(),
+					_ => None,
+				};
+				if let Some(target) = target.filter(|target| !target.is_linked()) {
+					if let Err(err) = pad.link(target) {
+						log::error!("Failed to link the timeshift buffer: {}", err);
+					}
+				}
+			});
+			let linked = bin.add(&decodebin).is_ok()
+				&& decodebin.sync_state_with_parent().is_ok()
+				&& decodebin.static_pad("sink").map_or(false, |sink| pad.link(&sink).is_ok());
+			if !linked {
+				log::error!("Failed to decode the timeshift buffer");
+			}
+		});
+
+		let mut player =
+			Self::from_pipeline(pipeline.upcast(), &video_sink, false, options, player_sink)?;
+		player.frame_taps = frame_taps;
+		player.audio_taps = audio_taps;
+		player.filters = filters;
+		Ok(player)
+	}
+
+	/// Create a video player around a pipeline built elsewhere.
+	///
+	/// `video_sink` is where the frames arrive at, the bin from
+	/// [`video_sink_bin`] or an appsink set up by [`attach_video_appsink`]. It
+	/// has to be part of `pipeline` already. The size of the video is zero
+	/// until the first frame negotiated its caps.
+	pub(crate) fn from_pipeline(
+		pipeline: Element,
+		video_sink: &impl IsA<Element>,
+		live: bool,
+		options: &PlayerOptions,
+		player_sink: SharedSink,
+	) -> Result<Self, VideoError> {
+		Self::start(pipeline, video_sink, live, options, Vec::new(), Arc::default(), player_sink)
+	}
+
+	/// Preroll `pipeline`, watch its bus and read the stream properties.
+	fn start(
+		pipeline: Element,
+		video_sink: &impl IsA<Element>,
+		live: bool,
+		options: &PlayerOptions,
+		variants: Vec<Variant>,
+		demuxer: Arc<Mutex<Option<Element>>>,
+		player_sink: SharedSink,
+	) -> Result<Self, VideoError> {
+		let bus = pipeline.bus().ok_or(VideoError::Bus)?;
+
+		// Live sources don't preroll, they only produce data while playing.
+		let live = match pipeline.set_state(gst::State::Paused)? {
+			gst::StateChangeSuccess::NoPreroll => true,
+			_ => live,
+		};
+		if live {
+			if let Some(latency) = options.latency {
+				if let Some(pipeline) = pipeline.downcast_ref::<gst::Pipeline>() {
+					pipeline.set_latency(gst::ClockTime::from_nseconds(latency.as_nanos() as _));
+				}
+			}
+			pipeline.set_state(gst::State::Playing)?;
+		}
+
+		// wait for up to 5 seconds until the decoder gets the source capabilities
+		pipeline.state(gst::ClockTime::from_seconds(5)).0?;
+
+		// Messages posted so far are queued on the bus until the watch picks them up.
+		let stop = Arc::new(StopFlag::default());
+		let buffering = Arc::new(Mutex::new(BufferingGate::default()));
+		BusWatch {
+			pipeline: pipeline.clone(),
+			live,
+			retry: options.retry.clone(),
+			variants: variants.clone(),
+			player_sink,
+			stop: stop.clone(),
+			buffering: buffering.clone(),
+		}
+		.spawn(bus.clone());
+
+		// extract resolution and framerate
+		// TODO(jazzfool): maybe we want to extract some other information too?
+		let pad = video_sink.static_pad("sink").ok_or(VideoError::Caps)?;
+		let (width, height, framerate) = match pad.current_caps().as_ref().map(video_caps) {
+			Some(Ok(caps)) => caps,
+			_ => (0, 0, gst::Fraction::new(0, 1)),
+		};
+		// Appsinks set up elsewhere may have nothing in front to scale or flip with.
+		let sink_bin = video_sink.dynamic_cast_ref::<gst::Bin>();
+		let scaling_sink = sink_bin
+			.and_then(|bin| bin.by_name(VIDEO_APPSINK))
+			.and_then(|sink| sink.downcast::<gst_app::AppSink>().ok());
+		let video_flip = sink_bin.and_then(|bin| bin.by_name(VIDEO_FLIP));
+
+		let duration = if !live {
+			Duration::from_nanos(
+				pipeline.query_duration::<gst::ClockTime>().map_or(0, |d| d.nseconds()),
+			)
+		} else {
+			Duration::from_secs(0)
+		};
+
+		Ok(VideoPlayer {
+			bus,
+			pipeline,
+
+			volume: 0.0,
+			width,
+			height,
+			framerate: num_rational::Rational32::new(
+				framerate.numer() ,
+				framerate.denom() ,
+			)
+				.to_f64().unwrap(/* if the video framerate is bad then it would've been implicitly caught far earlier */),
+			duration,
+
+			live,
+			variants,
+			demuxer,
+			growing: false,
+			frame_taps: SharedTaps::default(),
+			audio_taps: SharedTaps::default(),
+			filters: FilterChain::default(),
+			streams: SharedStreams::default(),
+			recording: None,
+			timeshift: None,
+			hidden: Vec::new(),
+			video_sink: scaling_sink,
+			video_flip,
+			stop,
+			buffering,
+			paused: !live,
+			muted: false,
+			looping: false,
+			is_eos: false,
+			restart_stream: false,
+		})
+	}
+
+	/// Get the size/resolution of the video as `(width, height)`.
+	#[inline(always)]
+	pub fn size(&self) -> (i32, i32) {
+		(self.width, self.height)
+	}
+
+	/// Set the volume multiplier of the audio.
+	/// `0.0` = 0% volume, `1.0` = 100% volume.
+	///
+	/// This uses a linear scale, for example `0.5` is perceived as half as
+	/// loud.
+	pub fn set_volume(&mut self, volume: f64) {
+		if let Some(volume_element) = self.volume_element() {
+			volume_element.set_property("volume", &volume);
+		}
+	}
+
+	/// Set if the audio is muted or not, without changing the volume.
+	pub fn set_muted(&self, muted: bool) {
+		// self.muted = muted;
+		if let Some(volume_element) = self.volume_element() {
+			volume_element.set_property("mute", &muted);
+		}
+	}
+
+	/// The element volume and mute are set on: playbin itself, or the
+	/// `volume` element of a pipeline built elsewhere.
+	fn volume_element(&self) -> Option<Element> {
+		if self.pipeline.find_property("mute").is_some() {
+			Some(self.pipeline.clone())
+		} else {
+			self.pipeline.downcast_ref::<gst::Bin>()?.by_name("volume")
+		}
+	}
+
+	/// Get if the stream ended or not.
+	#[inline(always)]
+	pub fn eos(&self) -> bool {
+		self.is_eos
+	}
+
+	/// Set if the media will loop or not.
+	#[inline(always)]
+	pub fn set_looping(&mut self, looping: bool) {
+		self.looping = looping;
+	}
+
+	/// Set if the media is paused or not.
+	pub fn set_paused(&mut self, paused: bool) {
+		let mut buffering = self.buffering.lock().unwrap();
+		if paused {
+			buffering.pause();
+			self.pipeline
+				.set_state(gst::State::Paused)
+				.unwrap(/* state was changed in ctor; state errors caught there */);
+		} else if buffering.play() {
+			self.pipeline
+				.set_state(gst::State::Playing)
+				.unwrap(/* state was changed in ctor; state errors caught there */);
+		}
+		self.paused = paused;
+
+		// Set restart_stream flag to make the stream restart on the next
+		// Message::NextFrame
+		if self.is_eos && !paused {
+			self.restart_stream = true;
+		}
+	}
+
+	/*	/// Get if the media is paused or not.
+	#[inline(always)]
+	pub fn paused(&self) -> bool {
+		self.paused
+	}*/
+
+	/// Drop whatever is queued for a live source and continue from the
+	/// current live point.
+	pub fn jump_to_live_edge(&self) -> Result<(), VideoError> {
+		// Going through READY flushes the pipeline and restarts the source.
+		self.pipeline.set_state(gst::State::Ready)?;
+		self.pipeline.set_state(gst::State::Playing)?;
+		Ok(())
+	}
+
+	/// Select the variant of an adaptive stream.
+	///
+	/// A fixed quality is pinned through the demuxer's `connection-speed`,
+	/// it picks the best variant which fits into that bandwidth.
+	pub fn set_quality(&self, quality: Quality) {
+		let speed = match quality {
+			Quality::Auto => 0,
+			Quality::Fixed(bitrate) => ((bitrate + 999) / 1000) as u32,
+		};
+		if let Some(ref demuxer) = *self.demuxer.lock().unwrap() {
+			demuxer.set_property("connection-speed", speed);
+		}
+	}
+
+	/// Scale the frames down in the pipeline to fit into `size` in pixels,
+	/// or let them have the size of the video.
+	///
+	/// Frames are never scaled up. The caps of the appsink change and the
+	/// pipeline renegotiates, so only the pixels shown get converted.
+	pub fn set_render_size(&self, size: Option<(u32, u32)>) {
+		let sink = match self.video_sink {
+			Some(ref sink) => sink,
+			None => return,
+		};
+		sink.set_caps(Some(&frame_caps(size)));
+		if let Some(pad) = sink.static_pad("sink") {
+			pad.push_event(gst::event::Reconfigure::new());
+		}
+	}
+
+	/// Rotate and mirror the frames by `orientation`, after the orientation
+	/// the stream is tagged with.
+	///
+	/// A paused pipeline prerolls the current frame again, so it is shown in
+	/// the new orientation.
+	pub fn set_orientation(&self, orientation: Orientation) -> Result<(), VideoError> {
+		let flip = match self.video_flip {
+			Some(ref flip) => flip,
+			None => return Ok(()),
+		};
+		flip.set_property_from_str("video-direction", orientation.method());
+		if self.paused && !self.live {
+			let flags = gst::SeekFlags::FLUSH | gst::SeekFlags::ACCURATE;
+			let position = gst::ClockTime::from_nseconds(self.position().as_nanos() as u64);
+			self.pipeline.seek_simple(flags, position)?;
+		}
+		Ok(())
+	}
+
+	/// Call `callback` with every decoded frame.
+	///
+	/// The callback runs on the streaming thread and holds up playback while
+	/// it runs, use [`VideoPlayer::on_frame_threaded`] for anything slow.
+	pub fn on_frame(&self, mut callback: impl FnMut(&FrameRef) + Send + 'static) -> TapId {
+		self.frame_taps.lock().unwrap().add_inline(move |sample| with_frame(sample, &mut callback))
+	}
+
+	/// Call `callback` with every decoded frame on a thread of its own.
+	///
+	/// Frames wait for the callback in a queue, `options` decide what happens
+	/// when it is full.
+	pub fn on_frame_threaded(
+		&self,
+		options: TapOptions,
+		mut callback: impl FnMut(&FrameRef) + Send + 'static,
+	) -> TapId {
+		self.frame_taps
+			.lock()
+			.unwrap()
+			.add_queued(options, move |sample| with_frame(sample, &mut callback))
+	}
+
+	/// Stop calling a frame callback.
+	pub fn remove_frame_tap(&self, id: TapId) {
+		self.frame_taps.lock().unwrap().remove(id);
+	}
+
+	/// Transform the frames with `filter`, after the filters added before.
+	pub fn add_filter(&self, filter: impl VideoFilter + 'static) -> FilterId {
+		self.filters.add(filter)
+	}
+
+	/// Turn a filter on or off while playing.
+	pub fn set_filter_enabled(&self, id: FilterId, enabled: bool) {
+		self.filters.set_enabled(id, enabled);
+	}
+
+	pub fn remove_filter(&self, id: FilterId) {
+		self.filters.remove(id);
+	}
+
+	/// The filters of the player, to apply them to exports as well.
+	pub fn filters(&self) -> FilterChain {
+		self.filters.clone()
+	}
+
+	/// Record the stream as it comes in to a Matroska file at `path`,
+	/// without re-encoding.
+	///
+	/// Recording starts at the next keyframe and replaces the one in
+	/// progress. Only media played through playbin can be recorded, and a
+	/// reconnect ends the recording. As the stream isn't decoded for it, the
+	/// [`filters`](Self::filters) of the player aren't applied.
+	pub fn start_recording(&mut self, path: impl Into<PathBuf>) -> Result<(), VideoError> {
+		self.stop_recording();
+		let path = path.into();
+		let id = self.streams.lock().unwrap().start(record::Target::File(path.clone()))?;
+		self.recording = Some((id, path));
+		Ok(())
+	}
+
+	/// Finish the recording, if there is one.
+	pub fn stop_recording(&mut self) {
+		if let Some((id, _)) = self.recording.take() {
+			self.streams.lock().unwrap().stop(id);
+		}
+	}
+
+	/// The recording in progress, `None` if there is none.
+	pub fn recording(&self) -> Option<RecordStatus> {
+		let (id, ref path) = *self.recording.as_ref()?;
+		let progress = self.streams.lock().unwrap().progress(id)?;
+		Some(RecordStatus {
+			path: path.display().to_string(),
+			bytes: std::fs::metadata(path).map_or(0, |metadata| metadata.len()),
+			duration: progress.end.as_secs(),
+		})
+	}
+
+	/// Keep the last `window` of the stream on disk to rewind in, in files
+	/// of `segment` length.
+	fn start_timeshift(&mut self, window: Duration, segment: Duration) -> Result<(), VideoError> {
+		let started = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+		let dir = std::env::temp_dir().join(format!(
+			"druid-video-timeshift-{}-{}",
+			std::process::id(),
+			started.as_nanos()
+		));
+		let segments = window.as_nanos() / segment.as_nanos().max(1);
+		let target = record::Target::Segments { dir, segment, max_files: segments as u32 + 1 };
+		self.timeshift = Some(self.streams.lock().unwrap().start(target)?);
+		Ok(())
+	}
+
+	/// What the timeshift buffer holds, `None` if there is none.
+	pub(crate) fn timeshift_progress(&self) -> Option<Progress> {
+		let progress = self.streams.lock().unwrap().progress(self.timeshift?)?;
+		// Until the first keyframe there is nothing to rewind in.
+		Some(progress).filter(|progress| !progress.finished && progress.end > Duration::ZERO)
+	}
+
+	/// Close the file of the timeshift buffer being written at the next
+	/// keyframe.
+	pub(crate) fn split_timeshift(&self) {
+		if let Some(id) = self.timeshift {
+			self.streams.lock().unwrap().split(id);
+		}
+	}
+
+	/// Drop the output, or show and play it again.
+	///
+	/// A hidden player keeps running, which keeps a live stream recording
+	/// and buffering while something else is shown.
+	pub fn set_hidden(&mut self, hidden: bool) {
+		if !hidden {
+			for (pad, id) in self.hidden.drain(..) {
+				pad.remove_probe(id);
+			}
+			return;
+		}
+		if !self.hidden.is_empty() {
+			return;
+		}
+		for name in ["video-sink", "audio-sink"] {
+			let pad = match self.pipeline.try_property::<Option<Element>>(name) {
+				Ok(Some(sink)) => sink.static_pad("sink"),
+				_ => None,
+			};
+			if let Some(pad) = pad {
+				let probe =
+					pad.add_probe(gst::PadProbeType::BUFFER, |_, _| gst::PadProbeReturn::Drop);
+				if let Some(id) = probe {
+					self.hidden.push((pad, id));
+				}
+			}
+		}
+	}
+
+	/// Call `callback` with the decoded audio, as interleaved `f32` samples.
+	///
+	/// The callback runs on the streaming thread like the ones of
+	/// [`VideoPlayer::on_frame`].
+	pub fn on_audio(&self, callback: impl FnMut(&AudioSamples) + Send + 'static) -> TapId {
+		self.audio_taps.lock().unwrap().add_inline(callback)
+	}
+
+	/// Call `callback` with the decoded audio on a thread of its own, see
+	/// [`VideoPlayer::on_frame_threaded`].
+	pub fn on_audio_threaded(
+		&self,
+		options: TapOptions,
+		callback: impl FnMut(&AudioSamples) + Send + 'static,
+	) -> TapId {
+		self.audio_taps.lock().unwrap().add_queued(options, callback)
+	}
+
+	/// Stop calling an audio callback.
+	pub fn remove_audio_tap(&self, id: TapId) {
+		self.audio_taps.lock().unwrap().remove(id);
+	}
+
+	/// Jumps to a specific position in the media.
+	/// The seeking is not perfectly accurate.
+	pub fn seek(&mut self, position: impl Into<Position>) -> Result<(), Error> {
+		self.pipeline.seek_simple(gst::SeekFlags::FLUSH, position.into())?;
+		Ok(())
+	}
+	pub fn position(&self) -> Duration {
+		Duration::from_nanos(
+			self.pipeline.query_position::<gst::ClockTime>().map_or(0, |pos| pos.nseconds()),
+		)
+		.into()
+	}
+	/*
+	/// Get the current playback position in time.
+	pub fn position(&self) -> Duration {
+		Duration::from_nanos(
+			self.pipeline.query_position::<gst::ClockTime>().map_or(0, |pos| pos.nseconds()),
+		)
+		.into()
+	}
+
+	/// Get the media duration.
+	#[inline(always)]
+	pub fn duration(&self) -> Duration {
+		self.duration
+	}*/
+
+	/*	/// Generates a list of thumbnails based on a set of positions in the media.
+	///
+	/// Slow; only needs to be called once for each instance.
+	/// It's best to call this at the very start of playback, otherwise the
+	/// position may shift.
+	pub fn thumbnails(&mut self, positions: &[Position]) -> Result<Vec<img::Handle>, VideoError> {
+		let paused = self.paused();
+		let pos = self.position();
+		self.set_paused(false);
+		let out = positions
+			.iter()
+			.map(|&pos| {
+				self.seek(pos)?;
+				self.wait.recv().map_err(|_| VideoError::Sync)?;
+				Ok(self.frame_image())
+			})
+			.collect();
+		self.set_paused(paused);
+		self.seek(pos)?;
+		out
+	}*/
+
+	/*	/// Restarts a stream; seeks to the first frame and unpauses, sets the `eos`
+	/// flag to false.
+	pub fn restart_stream(&mut self) -> Result<(), VideoError> {
+		self.is_eos = false;
+		self.set_paused(false);
+		self.seek(0)?;
+		Ok(())
+	}*/
+}
+
+/// Change the playback rate of `pipeline`, keeping its position.
+pub(crate) fn send_seek_event(pipeline: &Element, rate: f64) -> bool {
+	// Obtain the current position, needed for the seek event
+	let position = match pipeline.query_position() {
+		Some(pos) => pos,
+		None => {
+			log::warn!("Unable to retrieve the position to change the rate at");
+			return false;
+		}
+	};
+
+	// Create the seek event
+	let seek_event = if rate > 0. {
+		Seek::new(
+			rate,
+			SeekFlags::FLUSH | SeekFlags::ACCURATE,
+			SeekType::Set,
+			position,
+			SeekType::End,
+			gst::ClockTime::ZERO,
+		)
+	} else {
+		Seek::new(
+			rate,
+			SeekFlags::FLUSH | SeekFlags::ACCURATE,
+			SeekType::Set,
+			position,
+			SeekType::Set,
+			position,
+		)
+	};
+
+	// If we have not done so, obtain the sink through which we will send the seek
+	// events
+	if let Ok(Some(video_sink)) = pipeline.try_property::<Option<Element>>("video-sink") {
+		log::debug!("Changing the rate to {}", rate);
+		// Send the event
+		video_sink.send_event(seek_event)
+	} else {
+		// Pipelines built elsewhere have no video-sink property, they take the
+		// event themselves.
+		pipeline.send_event(seek_event)
+	}
+}
+
+/// Build the bin audio is played through: an equalizer in front of the
+/// default audio output, and a branch handing the samples to `audio_taps`.
+pub(crate) fn audio_sink_bin(audio_taps: SharedTaps<AudioSamples>) -> Result<gst::Bin, VideoError> {
+	// Create elements that go inside the sink bin
+	let equalizer = gst::ElementFactory::make("equalizer-3bands", Some("equalizer"))?;
+	let tee = gst::ElementFactory::make("tee", None)?;
+	let queue = gst::ElementFactory::make("queue", None)?;
+	let convert = gst::ElementFactory::make("audioconvert", Some("convert"))?;
+	let sink = gst::ElementFactory::make("autoaudiosink", Some("audio_sink"))?;
+	let tap_queue = gst::ElementFactory::make("queue", None)?;
+	let tap_convert = gst::ElementFactory::make("audioconvert", None)?;
+	let tap_sink = gst::ElementFactory::make("appsink", None)?;
+
+	// Create the sink bin, add the elements and link them
+	let bin = gst::Bin::new(Some("audio_sink_bin"));
+	bin.add_many(&[
+		&equalizer,
+		&tee,
+		&queue,
+		&convert,
+		&sink,
+		&tap_queue,
+		&tap_convert,
+		&tap_sink,
+	])?;
+	gst::Element::link_many(&[&equalizer, &tee, &queue, &convert, &sink])?;
+	gst::Element::link_many(&[&tee, &tap_queue, &tap_convert, &tap_sink])?;
+
+	let tap_sink = tap_sink.dynamic_cast::<gst_app::AppSink>().map_err(|_| VideoError::Cast)?;
+	tap_sink.set_caps(Some(&gst::Caps::new_simple(
+		"audio/x-raw",
+		&[("format", &"F32LE"), ("layout", &"interleaved")],
+	)));
+	tap_sink.set_callbacks(
+		gst_app::AppSinkCallbacks::builder()
+			.new_sample(move |sink| {
+				let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+				if audio_taps.lock().unwrap().is_empty() {
+					return Ok(gst::FlowSuccess::Ok);
+				}
+				let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
+				let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+				let caps = sample.caps().ok_or(gst::FlowError::Error)?;
+				let s = caps.structure(0).ok_or(gst::FlowError::Error)?;
+				let samples = AudioSamples {
+					samples: map
+						.chunks_exact(4)
+						.map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+						.collect(),
+					rate: s.get::<i32>("rate").map_err(|_| gst::FlowError::Error)?,
+					channels: s.get::<i32>("channels").map_err(|_| gst::FlowError::Error)?,
+					channel_mask: s.get::<gst::Bitmask>("channel-mask").ok().map(|mask| mask.0),
+					pts: buffer.pts().map(|pts| Duration::from_nanos(pts.nseconds())),
+				};
+				tap::dispatch(&audio_taps, samples);
+				Ok(gst::FlowSuccess::Ok)
+			})
+			.build(),
+	);
+
+	let pad = equalizer.static_pad("sink").ok_or(VideoError::Cast)?;
+	let ghost_pad = gst::GhostPad::with_target(Some("sink"), &pad)?;
+	ghost_pad.set_active(true)?;
+	bin.add_pad(&ghost_pad)?;
+
+	// Configure the equalizer
+	equalizer.set_property("band1", -24.0);
+	equalizer.set_property("band2", -24.0);
+	Ok(bin)
+}
+
+/// Build the bin video is rendered through: frames get converted to RGBA
+/// and taken by [`attach_video_appsink`].
+pub(crate) fn video_sink_bin(
+	player_sink: SharedSink,
+	frame_taps: SharedTaps<FrameSample>,
+	filters: FilterChain,
+) -> Result<gst::Bin, VideoError> {
+	// Create elements that go inside the sink bin
+	let queue = gst::ElementFactory::make("queue", None)?;
+	let convert = gst::ElementFactory::make("videoconvert", None)?;
+	// The first flip follows the image-orientation tag, the second the user.
+	let orient = gst::ElementFactory::make("videoflip", None)?;
+	orient.set_property_from_str("video-direction", "auto");
+	let flip = gst::ElementFactory::make("videoflip", Some(VIDEO_FLIP))?;
+	let scale = gst::ElementFactory::make("videoscale", None)?;
+	let sink = gst::ElementFactory::make("appsink", Some(VIDEO_APPSINK))?;
+
+	// Create the sink bin, add the elements and link them
+	let bin = gst::Bin::new(Some("video_sink_bin"));
+	bin.add_many(&[&queue, &convert, &orient, &flip, &scale, &sink])?;
+	gst::Element::link_many(&[&queue, &convert, &orient, &flip, &scale, &sink])?;
+
+	let pad = queue.static_pad("sink").ok_or(VideoError::Cast)?;
+	let ghost_pad = gst::GhostPad::with_target(Some("sink"), &pad)?;
+	ghost_pad.set_active(true)?;
+	bin.add_pad(&ghost_pad)?;
+
+	let video_sink = sink.dynamic_cast::<gst_app::AppSink>().map_err(|_| VideoError::Cast)?;
+	let converted = convert.static_pad("sink");
+	attach_video_appsink(&video_sink, converted, player_sink, frame_taps, filters);
+	Ok(bin)
+}
+
+/// Caps of the frames the appsink takes, at most `size` if there is one.
+fn frame_caps(size: Option<(u32, u32)>) -> gst::Caps {
+	// caps=video/x-raw,format=RGBA
+	// The pixel aspect ratio is left to the stream, the view applies it.
+	match size {
+		// The scaler keeps the aspect ratio within these bounds.
+		Some((width, height)) => {
+			let width = gst::IntRange::new(1, width.max(1) as i32);
+			let height = gst::IntRange::new(1, height.max(1) as i32);
+			gst::Caps::new_simple(
+				"video/x-raw",
+				&[("format", &"RGBA"), ("width", &width), ("height", &height)],
+			)
+		}
+		None => gst::Caps::new_simple("video/x-raw", &[("format", &"RGBA")]),
+	}
+}
+
+/// Take the frames from `video_sink`: they get run through `filters`, handed
+/// to `frame_taps` and to `player_sink`. Their colorimetry is read from the
+/// caps of `converted`, the pad the frames got converted to RGBA behind.
+pub(crate) fn attach_video_appsink(
+	video_sink: &gst_app::AppSink,
+	converted: Option<gst::Pad>,
+	player_sink: SharedSink,
+	frame_taps: SharedTaps<FrameSample>,
+	filters: FilterChain,
+) {
+	video_sink.set_caps(Some(&frame_caps(None)));
+	let mut number = 0;
+	let clock: SharedClock = Arc::new(ElementClock::new(video_sink));
+	video_sink.set_callbacks(
+		gst_app::AppSinkCallbacks::builder()
+			.new_sample(move |sink| {
+				let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+				let buffer = sample.buffer_owned().ok_or(gst::FlowError::Error)?;
+				let pts = buffer.pts();
+
+				let pad = sink.static_pad("sink").ok_or(gst::FlowError::Error)?;
+
+				let caps = pad.current_caps().ok_or(gst::FlowError::Error)?;
+				let s = caps.structure(0).ok_or(gst::FlowError::Error)?;
+				let width = s.get::<i32>("width").map_err(|_| gst::FlowError::Error)?;
+				let height = s.get::<i32>("height").map_err(|_| gst::FlowError::Error)?;
+				let par = s
+					.get::<gst::Fraction>("pixel-aspect-ratio")
+					.map_or((1, 1), |par| (par.numer(), par.denom()));
+				let colorimetry = converted
+					.as_ref()
+					.and_then(|pad| pad.current_caps())
+					.map_or_else(Colorimetry::default, |caps| Colorimetry::from_caps(&caps));
+
+				// Filters work on a copy, the decoder may still reference its buffer.
+				let buffer = if filters.is_active() {
+					let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+					let mut data = map.as_slice().to_owned();
+					filters.apply(&mut FrameMut {
+						stride: data.len() / height.max(1) as usize,
+						data: &mut data,
+						width: width as u32,
+						height: height as u32,
+						pts: pts.map(|pts| Duration::from_nanos(pts.nseconds())),
+						number,
+					});
+					let mut filtered = gst::Buffer::from_mut_slice(data);
+					filtered.get_mut().unwrap().set_pts(pts);
+					filtered
+				} else {
+					buffer
+				};
+
+				if !frame_taps.lock().unwrap().is_empty() {
+					// Taps get the buffer itself, queuing it copies nothing.
+					let sample = FrameSample {
+						buffer: buffer.clone(),
+						width: width as u32,
+						height: height as u32,
+						number,
+					};
+					tap::dispatch(&frame_taps, sample);
+				}
+				// Where the segment puts the frame on the pipeline clock.
+				let running_time = sample
+					.segment()
+					.and_then(|segment| segment.downcast_ref::<gst::ClockTime>())
+					.zip(pts)
+					.and_then(|(segment, pts)| segment.to_running_time(pts));
+				// The view shares the mapped buffer, the pixels aren't copied.
+				let data =
+					buffer.into_mapped_buffer_readable().map_err(|_| gst::FlowError::Error)?;
+				let frame = VideoFrame {
+					data: FrameData::from(data),
+					width: width as u32,
+					height: height as u32,
+					pixel_aspect_ratio: par,
+					pts: pts.map(|pts| Duration::from_nanos(pts.nseconds())),
+					running_time: running_time.map(|time| Duration::from_nanos(time.nseconds())),
+					clock: Some(clock.clone()),
+					number,
+					colorimetry,
+				};
+				number += 1;
+				player_sink.frame(frame).map_err(|_| gst::FlowError::Error)?;
+
+				Ok(gst::FlowSuccess::Ok)
+			})
+			.build(),
+	);
+}
+
+/// Map the buffer of `sample` and hand it to `callback`.
+fn with_frame(sample: &FrameSample, callback: &mut impl FnMut(&FrameRef)) {
+	let map = match sample.buffer.map_readable() {
+		Ok(map) => map,
+		Err(_) => return,
+	};
+	let stride = map.len() / sample.height.max(1) as usize;
+	callback(&FrameRef {
+		data: map.as_slice(),
+		width: sample.width,
+		height: sample.height,
+		stride,
+		pts: sample.buffer.pts().map(|pts| Duration::from_nanos(pts.nseconds())),
+		number: sample.number,
+	});
+}
+
+/// A queue holding a single buffer, dropping the older one when full.
+fn leaky_queue() -> Result<Element, VideoError> {
+	let queue = gst::ElementFactory::make("queue", None)?;
+	queue.set_property("max-size-buffers", 1u32);
+	queue.set_property("max-size-bytes", 0u32);
+	queue.set_property("max-size-time", 0u64);
+	queue.set_property_from_str("leaky", "downstream");
+	Ok(queue)
+}
+
+/// Extract `(width, height, framerate)` from negotiated video caps.
+fn video_caps(caps: &gst::Caps) -> Result<(i32, i32, gst::Fraction), VideoError> {
+	let s = caps.structure(0).ok_or(VideoError::Caps)?;
+	let width = s.get::<i32>("width").map_err(|_| VideoError::Caps)?;
+	let height = s.get::<i32>("height").map_err(|_| VideoError::Caps)?;
+	let framerate = s.get::<gst::Fraction>("framerate").map_err(|_| VideoError::Caps)?;
+	Ok((width, height, framerate))
+}
+
+/// Apply the buffering tunables from `options` to `playbin`.
+fn apply_buffering_options(pipeline: &Element, options: &PlayerOptions) -> Result<(), VideoError> {
+	let buffering = &options.buffering;
+	if let Some(size) = buffering.buffer_size {
+		pipeline.set_property("buffer-size", size);
+	}
+	if let Some(duration) = buffering.buffer_duration {
+		pipeline.set_property("buffer-duration", duration.as_nanos() as i64);
+	}
+	if buffering.download {
+		let flags = pipeline.property_value("flags");
+		let flags_class = glib::FlagsClass::new(flags.type_()).ok_or(VideoError::Cast)?;
+		let flags = flags_class
+			.builder_with_value(flags)
+			.ok_or(VideoError::Cast)?
+			.set_by_nick("download")
+			.build()
+			.ok_or(VideoError::Cast)?;
+		pipeline.set_property_from_value("flags", &flags);
+	}
+	Ok(())
+}
+
+/// Configure the sources playbin creates for HTTP, RTSP, UDP and SRT URIs,
+/// and the appsrc which plays `reader`.
+fn connect_source_setup(pipeline: &Element, options: &PlayerOptions, reader: Option<SharedReader>) {
+	let network = options.network.clone();
+	let source_options = options.source.clone();
+	pipeline.connect("source-setup", false, move |args| {
+		let source = args[1].get::<Element>().expect("source-setup without a source");
+		setup_network_source(&source, &network);
+		setup_http_source(&source, &source_options);
+		if let Some(ref reader) = reader {
+			if let Some(appsrc) = source.downcast_ref::<gst_app::AppSrc>() {
+				if let Err(err) = reader::setup_appsrc(appsrc, reader.clone()) {
+					log::error!("Failed to set up the reader: {}", err);
+				}
+			}
+		}
+		None
+	});
+
+	// RTP described by a SDP file is depayloaded by sdpdemux further downstream.
+	if let Some(bin) = pipeline.downcast_ref::<gst::Bin>() {
+		let latency = options.network.latency;
+		bin.connect_deep_element_added(move |_, _, element| {
+			let factory = element.factory().map(|factory| factory.name());
+			if factory.as_deref() == Some("sdpdemux") {
+				element.set_property("latency", latency.as_millis() as u32);
+			}
+		});
+	}
+}
+
+/// Keep track of the adaptive demuxer playbin plugs for HLS and DASH.
+fn connect_adaptive_demuxer(pipeline: &Element) -> Arc<Mutex<Option<Element>>> {
+	let demuxer = Arc::new(Mutex::new(None));
+	if let Some(bin) = pipeline.downcast_ref::<gst::Bin>() {
+		let demuxer = demuxer.clone();
+		bin.connect_deep_element_added(move |_, _, element| {
+			let factory = element.factory().map(|factory| factory.name());
+			if matches!(
+				factory.as_deref(),
+				Some("hlsdemux") | Some("hlsdemux2") | Some("dashdemux") | Some("dashdemux2")
+			) {
+				*demuxer.lock().unwrap() = Some(element.clone());
+			}
+		});
+	}
+	demuxer
+}
+
+fn setup_network_source(source: &Element, network: &NetworkOptions) {
+	let factory = source.factory().map(|factory| factory.name());
+	match factory.as_deref() {
+		Some("rtspsrc") => {
+			source.set_property("latency", network.latency.as_millis() as u32);
+			source.set_property("do-rtsp-keep-alive", network.reconnect);
+			let protocols = match network.transport {
+				Transport::Auto => None,
+				Transport::Tcp => Some("tcp"),
+				Transport::Udp => Some("udp"),
+			};
+			if let Some(protocols) = protocols {
+				let value = source.property_value("protocols");
+				let flags = glib::FlagsClass::new(value.type_())
+					.and_then(|class| class.to_value_by_nick(protocols));
+				match flags {
+					Some(flags) => source.set_property_from_value("protocols", &flags),
+					None => log::warn!("rtspsrc doesn't support the {} transport", protocols),
+				}
+			}
+		}
+		Some("srtsrc") | Some("srtclientsrc") => {
+			source.set_property("latency", network.latency.as_millis() as i32);
+			source.set_property("wait-for-connection", network.reconnect);
+		}
+		_ => {}
+	}
+}
+
+/// Query the downloaded ranges as `(start, stop)` fractions of the media.
+fn buffered_ranges(pipeline: &Element) -> Vec<(f64, f64)> {
+	let mut query = gst::query::Buffering::new(gst::Format::Percent);
+	if !pipeline.query(&mut query) {
+		return Vec::new();
+	}
+	query
+		.ranges()
+		.into_iter()
+		.map(|(start, stop)| {
+			(start.value() as f64 / PERCENT_MAX, stop.value() as f64 / PERCENT_MAX)
+		})
+		.collect()
+}
+
+/// Watches the pipeline bus on its own thread and forwards the interesting
+/// messages to the player sink, so they are handled even while no frames arrive.
+struct BusWatch {
+	pipeline: Element,
+	live: bool,
+	retry: RetryPolicy,
+	variants: Vec<Variant>,
+	player_sink: SharedSink,
+	stop: Arc<StopFlag>,
+	buffering: Arc<Mutex<BufferingGate>>,
+}
+
+impl BusWatch {
+	fn spawn(self, bus: gst::Bus) {
+		std::thread::spawn(move || self.run(bus));
+	}
+
+	fn run(self, bus: gst::Bus) {
+		let pipeline = &self.pipeline;
+		let player_sink = &self.player_sink;
+		let mut current_variant = None;
+		let mut attempt = 0;
+		for msg in bus.iter_timed(gst::ClockTime::NONE) {
+			match msg.view() {
+				gst::MessageView::Element(element) => {
+					let s = match element.structure() {
+						Some(s) if s.name() == "adaptive-streaming-statistics" => s,
+						_ => continue,
+					};
+					let variant = s
+						.get::<String>("uri")
+						.ok()
+						.and_then(|uri| adaptive::variant_of_fragment(&self.variants, &uri));
+					let size = s.get::<u64>("fragment-size").unwrap_or(0);
+					let time = s.get::<u64>("fragment-download-time").unwrap_or(0);
+					let bandwidth = if time > 0 { size * 8 * 1_000_000_000 / time } else { 0 };
+					let stats = AdaptiveStats { variant, bandwidth };
+					let _ = player_sink.event(PlayerEvent::AdaptiveStats(stats));
+					if variant.is_some() && variant != current_variant {
+						current_variant = variant;
+						let _ = player_sink.event(PlayerEvent::VariantChanged(variant.unwrap()));
+					}
+				}
+				gst::MessageView::Buffering(buffering) => {
+					let info = BufferingInfo {
+						percent: buffering.percent(),
+						ranges: buffered_ranges(pipeline),
+					};
+					// Live sources can't be paused to fill the buffer, they would just drop data.
+					if !self.live {
+						self.hold_while_buffering(info.percent);
+					}
+					if player_sink.event(PlayerEvent::Buffering(info)).is_err() {
+						break;
+					}
+				}
+				gst::MessageView::Eos(_) => {
+					let _ = player_sink.event(PlayerEvent::Ended);
+				}
+				gst::MessageView::Latency(_) => {
+					if let Some(bin) = pipeline.downcast_ref::<gst::Bin>() {
+						if bin.recalculate_latency().is_err() {
+							log::warn!("Failed to recalculate latency");
+						}
+					}
+				}
+				gst::MessageView::StateChanged(state)
+					if attempt > 0
+						&& state.current() == gst::State::Playing
+						&& msg.src().as_ref() == Some(pipeline.upcast_ref::<gst::Object>()) =>
+				{
+					attempt = 0;
+					let _ = player_sink.event(PlayerEvent::Reconnected);
+				}
+				gst::MessageView::Error(err) => {
+					log::error!("{}: {:?}", err.error(), err.debug());
+					if error_kind(&err.error()) == ErrorKind::Resource
+						&& attempt < self.retry.max_attempts
+					{
+						attempt += 1;
+						let _ = player_sink.event(PlayerEvent::Reconnecting(attempt));
+						self.reconnect(attempt);
+					} else {
+						let _ = player_sink.event(PlayerEvent::Error(err.error().to_string()));
+					}
+				}
+				gst::MessageView::Application(app)
+					if app.structure().map_or(false, |s| s.name() == BUS_WATCH_QUIT) =>
+				{
+					break;
+				}
+				_ => {}
+			}
+		}
+	}
+
+	/// Pause the pipeline while its buffer fills, and play it again once
+	/// full if it was playing.
+	fn hold_while_buffering(&self, percent: i32) {
+		let mut buffering = self.buffering.lock().unwrap();
+		if let Some(state) = buffering.update(percent) {
+			if let Some(Err(_)) = self.stop.unless_stopped(|| self.pipeline.set_state(state)) {
+				log::error!("Failed to set the pipeline to {:?} while buffering", state);
+			}
+		}
+	}
+
+	/// Restart the pipeline after the backoff of `attempt`, unless the player
+	/// gets dropped meanwhile.
+	fn reconnect(&self, attempt: u32) {
+		let pipeline = &self.pipeline;
+		let stop = &self.stop;
+		let position = pipeline.query_position::<gst::ClockTime>();
+		log::info!("Reconnecting to the source (attempt {})", attempt);
+		if stop.wait(self.retry.backoff(attempt)) {
+			return;
+		}
+
+		if stop.unless_stopped(|| pipeline.set_state(gst::State::Null)).is_none() {
+			return;
+		}
+		if !self.live && self.retry.resume_position {
+			if let Some(position) = position {
+				// Seeking needs a prerolled pipeline.
+				let paused = stop.unless_stopped(|| pipeline.set_state(gst::State::Paused));
+				let prerolled = matches!(paused, Some(Ok(_)))
+					&& pipeline.state(gst::ClockTime::from_seconds(5)).0.is_ok();
+				let seek = || pipeline.seek_simple(gst::SeekFlags::FLUSH, position);
+				if prerolled && matches!(stop.unless_stopped(seek), Some(Err(_))) {
+					log::warn!("Failed to resume at {}", position);
+				}
+			}
+		}
+		match stop.unless_stopped(|| pipeline.set_state(gst::State::Playing)) {
+			Some(Err(_)) => log::error!("Failed to restart the pipeline"),
+			Some(Ok(_)) => {}
+			None => log::debug!("The player was dropped while reconnecting"),
+		}
+	}
+}
+
+/// Whether the pipeline should be playing, given what was asked for and
+/// how full its buffer is.
+#[derive(Debug, Default)]
+pub(crate) struct BufferingGate {
+	/// The buffer is filling, the pipeline is held paused.
+	filling: bool,
+	/// Playing was asked for.
+	playing: bool,
+}
+
+impl BufferingGate {
+	/// Ask for playing. Returns if the pipeline can play right away.
+	pub(crate) fn play(&mut self) -> bool {
+		self.playing = true;
+		!self.filling
+	}
+
+	pub(crate) fn pause(&mut self) {
+		self.playing = false;
+	}
+
+	/// Take the buffer at `percent` full, returning the state the pipeline
+	/// has to change to.
+	fn update(&mut self, percent: i32) -> Option<gst::State> {
+		if percent < 100 && !self.filling {
+			self.filling = true;
+			Some(gst::State::Paused)
+		} else if percent >= 100 && self.filling {
+			self.filling = false;
+			Some(gst::State::Playing).filter(|_| self.playing)
+		} else {
+			None
+		}
+	}
+}
+
+/// Tells the bus watch that its player is gone.
+///
+/// State changes of the watch run while holding the flag, so none of them
+/// can come after the player set its pipeline to `Null`.
+#[derive(Debug, Default)]
+struct StopFlag {
+	stopped: Mutex<bool>,
+	changed: Condvar,
+}
+
+impl StopFlag {
+	fn stop(&self) {
+		*self.stopped.lock().unwrap() = true;
+		self.changed.notify_all();
+	}
+
+	/// Wait for `timeout`. Returns early, with `true`, once stopped.
+	fn wait(&self, timeout: Duration) -> bool {
+		let stopped = self.stopped.lock().unwrap();
+		let (stopped, _) =
+			self.changed.wait_timeout_while(stopped, timeout, |stopped| !*stopped).unwrap();
+		*stopped
+	}
+
+	/// Run `change` unless stopped.
+	fn unless_stopped<T>(&self, change: impl FnOnce() -> T) -> Option<T> {
+		let stopped = self.stopped.lock().unwrap();
+		if *stopped {
+			None
+		} else {
+			Some(change())
+		}
+	}
+}
+
+/// Rough classification of pipeline errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorKind {
+	/// The source failed, reconnecting may help.
+	Resource,
+	/// The media itself is broken or unsupported.
+	Decode,
+	Other,
+}
+
+fn error_kind(err: &glib::Error) -> ErrorKind {
+	if let Some(err) = err.kind::<gst::ResourceError>() {
+		// Those won't go away by trying again.
+		match err {
+			gst::ResourceError::NotFound
+			| gst::ResourceError::NotAuthorized
+			| gst::ResourceError::Settings
+			| gst::ResourceError::NoSpaceLeft => ErrorKind::Other,
+			_ => ErrorKind::Resource,
+		}
+	} else if err.is::<gst::StreamError>() || err.is::<gst::CoreError>() {
+		ErrorKind::Decode
+	} else {
+		ErrorKind::Other
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn buffering_holds_playback_until_full() {
+		let mut gate = BufferingGate::default();
+		assert!(gate.play());
+		assert_eq!(gate.update(40), Some(gst::State::Paused));
+		// Only the start of filling pauses.
+		assert_eq!(gate.update(70), None);
+		// Asked to play meanwhile, it waits for the buffer.
+		assert!(!gate.play());
+		assert_eq!(gate.update(100), Some(gst::State::Playing));
+		assert_eq!(gate.update(100), None);
+		assert!(gate.play());
+	}
+
+	#[test]
+	fn buffering_while_paused_stays_paused() {
+		let mut gate = BufferingGate::default();
+		assert_eq!(gate.update(40), Some(gst::State::Paused));
+		assert_eq!(gate.update(100), None);
+
+		assert!(gate.play());
+		assert_eq!(gate.update(40), Some(gst::State::Paused));
+		gate.pause();
+		assert_eq!(gate.update(100), None);
+	}
+}
@@ -0,0 +1,331 @@
+//! Presenting frames at their running time instead of on arrival.
+//!
+//! Frames carry the running time they are due at and the
+//! [`PresentationClock`] of the pipeline they come from. The streaming thread
+//! puts them in a [`FrameSlot`], which only keeps the newest one and is
+//! shared without a lock. On every animation frame the view asks its
+//! [`FrameScheduler`] for the frame due on that clock. Frames overtaken
+//! before they were shown are dropped, frames shown well after their time
+//! are counted as late:
+//!
+//! ```text
+//! {appsink} - {FrameSlot} - {FrameScheduler} - {take_due on AnimFrame} - {paint}
+//!                           {PresentationClock} /
+//! ```
+use std::{
+	fmt,
+	sync::{
+		atomic::{AtomicU64, Ordering},
+		Arc,
+	},
+	time::Duration,
+};
+
+use arc_swap::ArcSwapOption;
+use gst::prelude::*;
+use gstreamer as gst;
+
+use crate::media::player::VideoFrame;
+
+/// A frame shown this long after its running time is late.
+const LATE: Duration = Duration::from_millis(20);
+
+/// A frame due this far ahead of the clock comes after a discontinuity of
+/// the clock (a flushing seek, a new segment) and is shown right away.
+const MAX_AHEAD: Duration = Duration::from_secs(1);
+
+/// What came of presenting frames at their running time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PresentStats {
+	/// Frames overtaken by newer ones before they were shown.
+	pub dropped: u64,
+	/// Frames shown well after their running time.
+	pub late: u64,
+}
+
+/// Tells the running time of a pipeline, which frames are due against.
+pub trait PresentationClock: Send + Sync + fmt::Debug {
+	/// Running time now, `None` if the pipeline has no clock (anymore).
+	fn running_time(&self) -> Option<Duration>;
+}
+
+/// [`PresentationClock`] shared by the frames of a pipeline.
+pub type SharedClock = Arc<dyn PresentationClock>;
+
+/// Running time of the pipeline an element is in.
+///
+/// It holds the element weakly, so frames don't keep a closed pipeline
+/// around.
+pub(crate) struct ElementClock(gst::glib::WeakRef<gst::Element>);
+
+impl ElementClock {
+	pub(crate) fn new(element: &impl IsA<gst::Element>) -> Self {
+		Self(element.upcast_ref::<gst::Element>().downgrade())
+	}
+}
+
+impl fmt::Debug for ElementClock {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_tuple("ElementClock").field(&self.0.upgrade()).finish()
+	}
+}
+
+impl PresentationClock for ElementClock {
+	fn running_time(&self) -> Option<Duration> {
+		let element = self.0.upgrade()?;
+		let base = element.base_time()?;
+		let now = element.clock()?.time()?;
+		Some(Duration::from_nanos(now.nseconds().saturating_sub(base.nseconds())))
+	}
+}
+
+/// The newest frame of a pipeline, handed from its streaming thread to the
+/// view without a lock.
+///
+/// Putting a frame replaces the one not taken yet, which counts as dropped.
+#[derive(Debug, Default)]
+pub struct FrameSlot {
+	frame: ArcSwapOption<VideoFrame>,
+	replaced: AtomicU64,
+}
+
+impl FrameSlot {
+	/// Put `frame` in the slot. Returns whether the slot was empty, so the
+	/// receiver only has to be woken up for the first frame it hasn't taken.
+	pub fn put(&self, frame: VideoFrame) -> bool {
+		let replaced = self.frame.swap(Some(Arc::new(frame))).is_some();
+		if replaced {
+			self.replaced.fetch_add(1, Ordering::Relaxed);
+		}
+		!replaced
+	}
+
+	/// Take the frame out of the slot.
+	pub fn take(&self) -> Option<Arc<VideoFrame>> {
+		self.frame.swap(None)
+	}
+
+	/// Frames replaced before they were taken.
+	pub fn replaced(&self) -> u64 {
+		self.replaced.load(Ordering::Relaxed)
+	}
+}
+
+/// Hands out the frames of a [`FrameSlot`] once they are due on the clock of
+/// their pipeline.
+#[derive(Debug, Default)]
+pub struct FrameScheduler {
+	slot: Arc<FrameSlot>,
+	/// Frame taken from the slot, waiting for its time.
+	waiting: Option<Arc<VideoFrame>>,
+	stats: PresentStats,
+}
+
+impl FrameScheduler {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// The slot frames are put in, for the streaming threads.
+	pub fn slot(&self) -> Arc<FrameSlot> {
+		self.slot.clone()
+	}
+
+	/// The frame to show now, if a new one is due.
+	///
+	/// A newer frame replaces the one waiting, unless only the one waiting
+	/// is due yet.
+	pub fn take_due(&mut self) -> Option<Arc<VideoFrame>> {
+		if let Some(newer) = self.slot.take() {
+			match self.waiting.take() {
+				Some(older) if is_due(&older) && !is_due(&newer) => {
+					self.waiting = Some(newer);
+					return Some(self.present(older));
+				}
+				Some(_) => self.stats.dropped += 1,
+				None => {}
+			}
+			self.waiting = Some(newer);
+		}
+		match self.waiting.take() {
+			Some(frame) if is_due(&frame) => Some(self.present(frame)),
+			waiting => {
+				self.waiting = waiting;
+				None
+			}
+		}
+	}
+
+	/// A frame is waiting for its time.
+	pub fn pending(&self) -> bool {
+		self.waiting.is_some()
+	}
+
+	/// Frames dropped and shown late so far.
+	pub fn stats(&self) -> PresentStats {
+		PresentStats { dropped: self.stats.dropped + self.slot.replaced(), ..self.stats }
+	}
+
+	fn present(&mut self, frame: Arc<VideoFrame>) -> Arc<VideoFrame> {
+		if let (Some(running_time), Some(now)) = (frame.running_time, now(&frame)) {
+			if now.saturating_sub(running_time) > LATE {
+				self.stats.late += 1;
+			}
+		}
+		frame
+	}
+}
+
+/// Running time now on the clock of `frame`.
+fn now(frame: &VideoFrame) -> Option<Duration> {
+	frame.clock.as_ref()?.running_time()
+}
+
+/// Whether `frame` is to be shown now. Frames without a running time always
+/// are.
+fn is_due(frame: &VideoFrame) -> bool {
+	match (frame.running_time, now(frame)) {
+		(Some(running_time), Some(now)) => running_time <= now || running_time > now + MAX_AHEAD,
+		_ => true,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Mutex;
+
+	use super::*;
+	use crate::media::{player::FrameData, types::Colorimetry};
+
+	/// Running time set by the test.
+	#[derive(Debug, Default)]
+	struct TestClock(Mutex<Duration>);
+
+	impl TestClock {
+		fn set(&self, millis: u64) {
+			*self.0.lock().unwrap() = Duration::from_millis(millis);
+		}
+	}
+
+	impl PresentationClock for TestClock {
+		fn running_time(&self) -> Option<Duration> {
+			Some(*self.0.lock().unwrap())
+		}
+	}
+
+	/// Frame `number`, due at `due` milliseconds on `clock`.
+	fn frame(number: u64, due: Option<u64>, clock: &Arc<TestClock>) -> VideoFrame {
+		VideoFrame {
+			data: FrameData::from(vec![0; 4]),
+			width: 1,
+			height: 1,
+			pixel_aspect_ratio: (1, 1),
+			pts: None,
+			running_time: due.map(Duration::from_millis),
+			clock: Some(clock.clone() as SharedClock),
+			number,
+			colorimetry: Colorimetry::default(),
+		}
+	}
+
+	fn scheduler() -> (FrameScheduler, Arc<FrameSlot>, Arc<TestClock>) {
+		let scheduler = FrameScheduler::new();
+		let slot = scheduler.slot();
+		(scheduler, slot, Arc::default())
+	}
+
+	#[test]
+	fn early_frame_waits_for_its_time() {
+		let (mut scheduler, slot, clock) = scheduler();
+		slot.put(frame(0, Some(100), &clock));
+		assert!(scheduler.take_due().is_none());
+		assert!(scheduler.pending());
+
+		clock.set(100);
+		assert_eq!(scheduler.take_due().map(|frame| frame.number), Some(0));
+		assert!(!scheduler.pending());
+		assert_eq!(scheduler.stats(), PresentStats::default());
+	}
+
+	#[test]
+	fn frame_on_time_is_shown() {
+		let (mut scheduler, slot, clock) = scheduler();
+		clock.set(40);
+		slot.put(frame(0, Some(40), &clock));
+		assert_eq!(scheduler.take_due().map(|frame| frame.number), Some(0));
+		assert_eq!(scheduler.stats().late, 0);
+	}
+
+	#[test]
+	fn late_frame_is_counted() {
+		let (mut scheduler, slot, clock) = scheduler();
+		clock.set(100);
+		slot.put(frame(0, Some(100 - LATE.as_millis() as u64 - 1), &clock));
+		assert_eq!(scheduler.take_due().map(|frame| frame.number), Some(0));
+		assert_eq!(scheduler.stats(), PresentStats { dropped: 0, late: 1 });
+	}
+
+	#[test]
+	fn frame_far_ahead_is_shown_right_away() {
+		let (mut scheduler, slot, clock) = scheduler();
+		slot.put(frame(0, Some(MAX_AHEAD.as_millis() as u64 + 1), &clock));
+		assert_eq!(scheduler.take_due().map(|frame| frame.number), Some(0));
+		assert_eq!(scheduler.stats(), PresentStats::default());
+	}
+
+	#[test]
+	fn frame_without_running_time_is_shown_right_away() {
+		let (mut scheduler, slot, clock) = scheduler();
+		slot.put(frame(0, None, &clock));
+		assert_eq!(scheduler.take_due().map(|frame| frame.number), Some(0));
+	}
+
+	#[test]
+	fn newer_frame_replaces_the_waiting_one() {
+		let (mut scheduler, slot, clock) = scheduler();
+		slot.put(frame(0, Some(100), &clock));
+		assert!(scheduler.take_due().is_none());
+		slot.put(frame(1, Some(140), &clock));
+		assert!(scheduler.take_due().is_none());
+
+		clock.set(140);
+		assert_eq!(scheduler.take_due().map(|frame| frame.number), Some(1));
+		assert_eq!(scheduler.stats(), PresentStats { dropped: 1, late: 0 });
+	}
+
+	#[test]
+	fn due_frame_is_shown_before_a_newer_one() {
+		let (mut scheduler, slot, clock) = scheduler();
+		slot.put(frame(0, Some(100), &clock));
+		assert!(scheduler.take_due().is_none());
+		clock.set(100);
+		slot.put(frame(1, Some(140), &clock));
+		assert_eq!(scheduler.take_due().map(|frame| frame.number), Some(0));
+		assert!(scheduler.pending());
+
+		clock.set(140);
+		assert_eq!(scheduler.take_due().map(|frame| frame.number), Some(1));
+		assert_eq!(scheduler.stats(), PresentStats::default());
+	}
+
+	#[test]
+	fn slot_keeps_the_newest_frame() {
+		let clock = Arc::default();
+		let slot = FrameSlot::default();
+		assert!(slot.put(frame(0, None, &clock)));
+		assert!(!slot.put(frame(1, None, &clock)));
+		assert_eq!(slot.replaced(), 1);
+		assert_eq!(slot.take().map(|frame| frame.number), Some(1));
+		assert!(slot.take().is_none());
+		assert!(slot.put(frame(2, None, &clock)));
+	}
+
+	#[test]
+	fn replaced_frames_count_as_dropped() {
+		let (mut scheduler, slot, clock) = scheduler();
+		slot.put(frame(0, None, &clock));
+		slot.put(frame(1, None, &clock));
+		assert_eq!(scheduler.take_due().map(|frame| frame.number), Some(1));
+		assert_eq!(scheduler.stats(), PresentStats { dropped: 1, late: 0 });
+	}
+}
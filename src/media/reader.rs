@@ -0,0 +1,283 @@
+// Playback from Rust readers.
+
+// playbin plays `appsrc://` through an appsrc which gets configured in the
+// `source-setup` signal. In random-access mode the appsrc asks for byte
+// ranges, which are read from the reader:
+
+// {appsrc (random-access)} - {decodebin} - ...
+use std::{
+	io::{self, Read, Seek, SeekFrom},
+	sync::{Arc, Mutex},
+};
+
+use gst::prelude::*;
+use gstreamer as gst;
+use gstreamer_app as gst_app;
+
+/// Size of the chunks pushed when appsrc doesn't ask for a size.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Anything media can be read from.
+pub trait MediaReader: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> MediaReader for T {}
+
+/// Reader shared between the appsrc callbacks; playbin creates a new appsrc
+/// whenever the pipeline is restarted.
+pub type SharedReader = Arc<Mutex<Box<dyn MediaReader>>>;
+
+/// Turns a byte range callback into a [`Read`] + [`Seek`] reader.
+///
+/// The callback fills the buffer with the bytes starting at the given
+/// offset and returns how many it read.
+pub struct RangeReader<F> {
+	read_range: F,
+	len: u64,
+	position: u64,
+}
+
+impl<F> std::fmt::Debug for RangeReader<F> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("RangeReader")
+			.field("len", &self.len)
+			.field("position", &self.position)
+			.finish()
+	}
+}
+
+impl<F: FnMut(u64, &mut [u8]) -> io::Result<usize>> RangeReader<F> {
+	/// Create a reader over `len` bytes served by `read_range`.
+	pub fn new(len: u64, read_range: F) -> Self {
+		Self { read_range, len, position: 0 }
+	}
+}
+
+impl<F: FnMut(u64, &mut [u8]) -> io::Result<usize>> Read for RangeReader<F> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let remaining = self.len.saturating_sub(self.position);
+		let len = buf.len().min(remaining.min(usize::MAX as u64) as usize);
+		if len == 0 {
+			return Ok(0);
+		}
+		let read = (self.read_range)(self.position, &mut buf[..len])?;
+		self.position += read as u64;
+		Ok(read)
+	}
+}
+
+impl<F> Seek for RangeReader<F> {
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		let position = match pos {
+			SeekFrom::Start(offset) => Some(offset),
+			SeekFrom::End(offset) => offset_by(self.len, offset),
+			SeekFrom::Current(offset) => offset_by(self.position, offset),
+		};
+		self.position = position
+			.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before the start"))?;
+		Ok(self.position)
+	}
+}
+
+fn offset_by(base: u64, offset: i64) -> Option<u64> {
+	if offset >= 0 {
+		base.checked_add(offset as u64)
+	} else {
+		base.checked_sub(offset.unsigned_abs())
+	}
+}
+
+/// Feed `reader` to `appsrc` in random-access mode, so seeking works.
+pub fn setup_appsrc(appsrc: &gst_app::AppSrc, reader: SharedReader) -> io::Result<()> {
+	let len = {
+		let mut reader = reader.lock().unwrap();
+		let len = reader.seek(SeekFrom::End(0))?;
+		reader.seek(SeekFrom::Start(0))?;
+		len
+	};
+	appsrc.set_stream_type(gst_app::AppStreamType::RandomAccess);
+	appsrc.set_size(len as i64);
+
+	let seek_reader = reader.clone();
+	appsrc.set_callbacks(
+		gst_app::AppSrcCallbacks::builder()
+			.need_data(move |appsrc, length| match next_chunk(&reader, length) {
+				Ok(Some(data)) => {
+					let _ = appsrc.push_buffer(gst::Buffer::from_mut_slice(data));
+				}
+				Ok(None) => {
+					let _ = appsrc.end_of_stream();
+				}
+				Err(err) => {
+					gst::element_error!(
+						appsrc,
+						gst::ResourceError::Read,
+						("Failed to read media: {}", err)
+					);
+				}
+			})
+			.seek_data(move |_, offset| seek_to(&seek_reader, offset))
+			.build(),
+	);
+	Ok(())
+}
+
+/// The next chunk of `reader` for an appsrc asking for `length` bytes,
+/// `None` once the reader is exhausted.
+fn next_chunk(reader: &SharedReader, length: u32) -> io::Result<Option<Vec<u8>>> {
+	let length = if length == u32::MAX { CHUNK_SIZE } else { length as usize };
+	let mut data = vec![0; length];
+	let read = read_full(&mut **reader.lock().unwrap(), &mut data)?;
+	data.truncate(read);
+	Ok(Some(data).filter(|data| !data.is_empty()))
+}
+
+/// Continue reading at `offset`, for the seek-data callback of the appsrc.
+fn seek_to(reader: &SharedReader, offset: u64) -> bool {
+	reader.lock().unwrap().seek(SeekFrom::Start(offset)).is_ok()
+}
+
+/// Read until `buf` is full or the reader is exhausted.
+fn read_full(reader: &mut dyn MediaReader, buf: &mut [u8]) -> io::Result<usize> {
+	let mut filled = 0;
+	while filled < buf.len() {
+		match reader.read(&mut buf[filled..]) {
+			Ok(0) => break,
+			Ok(read) => filled += read,
+			Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+			Err(err) => return Err(err),
+		}
+	}
+	Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+
+	use super::*;
+
+	/// Bytes counting up from zero.
+	fn bytes(len: usize) -> Vec<u8> {
+		(0..len).map(|byte| byte as u8).collect()
+	}
+
+	/// A reader over `data` whose callback returns at most `chunk` bytes at
+	/// a time, like a network source would.
+	fn chunked(
+		data: Vec<u8>,
+		chunk: usize,
+	) -> RangeReader<impl FnMut(u64, &mut [u8]) -> io::Result<usize>> {
+		let len = data.len() as u64;
+		RangeReader::new(len, move |offset, buf| {
+			assert!(offset < len, "read at {} past the end", offset);
+			let start = offset as usize;
+			let read = buf.len().min(chunk).min(data.len() - start);
+			buf[..read].copy_from_slice(&data[start..start + read]);
+			Ok(read)
+		})
+	}
+
+	fn shared(reader: impl MediaReader + 'static) -> SharedReader {
+		Arc::new(Mutex::new(Box::new(reader)))
+	}
+
+	#[test]
+	fn range_reader_passes_short_reads_on() {
+		let mut reader = chunked(bytes(100), 7);
+		let mut buf = [0; 50];
+		assert_eq!(reader.read(&mut buf).unwrap(), 7);
+		assert_eq!(&buf[..7], &bytes(7)[..]);
+		assert_eq!(reader.stream_position().unwrap(), 7);
+	}
+
+	#[test]
+	fn read_full_collects_short_reads() {
+		let mut reader = chunked(bytes(100), 7);
+		let mut buf = [0; 50];
+		assert_eq!(read_full(&mut reader, &mut buf).unwrap(), 50);
+		assert_eq!(&buf[..], &bytes(50)[..]);
+		// The end of the reader stops it early.
+		assert_eq!(read_full(&mut reader, &mut buf).unwrap(), 50);
+		assert_eq!(read_full(&mut reader, &mut buf).unwrap(), 0);
+	}
+
+	#[test]
+	fn range_reader_stops_at_its_length() {
+		let mut reader = chunked(bytes(100), 64);
+		assert_eq!(reader.seek(SeekFrom::Start(95)).unwrap(), 95);
+		let mut buf = [0; 10];
+		assert_eq!(read_full(&mut reader, &mut buf).unwrap(), 5);
+		assert_eq!(&buf[..5], &bytes(100)[95..]);
+		assert_eq!(reader.read(&mut buf).unwrap(), 0);
+	}
+
+	#[test]
+	fn range_reader_seeks() {
+		let mut reader = chunked(bytes(100), 64);
+		assert_eq!(reader.seek(SeekFrom::End(-10)).unwrap(), 90);
+		assert_eq!(reader.seek(SeekFrom::Current(5)).unwrap(), 95);
+		assert_eq!(reader.seek(SeekFrom::Current(-95)).unwrap(), 0);
+		// Past the end reads nothing, before the start fails.
+		assert_eq!(reader.seek(SeekFrom::Start(200)).unwrap(), 200);
+		assert_eq!(reader.read(&mut [0; 10]).unwrap(), 0);
+		let err = reader.seek(SeekFrom::End(-101)).unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+		assert_eq!(reader.stream_position().unwrap(), 200);
+	}
+
+	#[test]
+	fn read_full_retries_interrupted_reads() {
+		let mut interrupted = false;
+		let mut reader = RangeReader::new(10, |_, buf: &mut [u8]| {
+			if !interrupted {
+				interrupted = true;
+				return Err(io::ErrorKind::Interrupted.into());
+			}
+			buf.fill(1);
+			Ok(buf.len())
+		});
+		let mut buf = [0; 10];
+		assert_eq!(read_full(&mut reader, &mut buf).unwrap(), 10);
+		assert_eq!(buf, [1; 10]);
+	}
+
+	#[test]
+	fn read_errors_are_passed_on() {
+		let mut reader =
+			RangeReader::new(10, |_, _: &mut [u8]| Err(io::ErrorKind::ConnectionReset.into()));
+		let err = read_full(&mut reader, &mut [0; 10]).unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+		let err = next_chunk(&shared(reader), 10).unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+	}
+
+	#[test]
+	fn chunks_of_a_cursor() {
+		let reader = shared(Cursor::new(bytes(100)));
+		assert_eq!(next_chunk(&reader, 40).unwrap(), Some(bytes(40)));
+		// Without a requested size the rest fits in one chunk.
+		assert_eq!(next_chunk(&reader, u32::MAX).unwrap(), Some(bytes(100)[40..].to_vec()));
+		assert_eq!(next_chunk(&reader, 40).unwrap(), None);
+	}
+
+	#[test]
+	fn chunks_of_partial_ranges() {
+		let reader = shared(chunked(bytes(100), 7));
+		assert_eq!(next_chunk(&reader, 60).unwrap(), Some(bytes(60)));
+		assert_eq!(next_chunk(&reader, 60).unwrap(), Some(bytes(100)[60..].to_vec()));
+		assert_eq!(next_chunk(&reader, 60).unwrap(), None);
+	}
+
+	#[test]
+	fn seek_data_moves_the_chunks() {
+		for reader in [shared(Cursor::new(bytes(100))), shared(chunked(bytes(100), 7))] {
+			assert!(seek_to(&reader, 90));
+			assert_eq!(next_chunk(&reader, 40).unwrap(), Some(bytes(100)[90..].to_vec()));
+			assert!(seek_to(&reader, 10));
+			assert_eq!(next_chunk(&reader, 5).unwrap(), Some(bytes(100)[10..15].to_vec()));
+			// Seeking past the end ends the stream.
+			assert!(seek_to(&reader, 150));
+			assert_eq!(next_chunk(&reader, 40).unwrap(), None);
+		}
+	}
+}
@@ -0,0 +1,414 @@
+// Recording of the streams being played.
+
+// Playbin decodes what the parsers it plugs put out. Their output is handed
+// to the recordings as well, still encoded as it came in, so recordings and
+// the timeshift buffer are written without re-encoding:
+
+// {demuxer} - {parser} - {decoder} - ...
+//                      - {appsrc} - {queue} - {matroskamux} - {filesink}
+//                      - {appsrc} - {queue} - {splitmuxsink}
+use std::{
+	collections::VecDeque,
+	path::PathBuf,
+	sync::{Arc, Mutex},
+	thread,
+	time::Duration,
+};
+
+use gst::prelude::*;
+use gstreamer as gst;
+use gstreamer_app as gst_app;
+
+use crate::media::error::VideoError;
+
+/// Streams of a pipeline shared with the parser probes.
+pub type SharedStreams = Arc<Mutex<Streams>>;
+
+/// Handle of a recording in [`Streams`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordingId(u64);
+
+/// Where a recording is written to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+	/// A single Matroska file.
+	File(PathBuf),
+	/// Matroska files of `segment` length in `dir`, keeping the newest
+	/// `max_files`. The directory is removed when the recording ends.
+	Segments { dir: PathBuf, segment: Duration, max_files: u32 },
+}
+
+/// A closed file of a segmented recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fragment {
+	pub path: PathBuf,
+	/// Time in the recording the file starts at.
+	pub start: Duration,
+	pub end: Duration,
+}
+
+/// What a recording wrote so far.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Progress {
+	/// Time recorded, from the first keyframe on.
+	pub end: Duration,
+	/// Closed files of a segmented recording, oldest first.
+	pub fragments: Vec<Fragment>,
+	/// An audio stream is recorded.
+	pub audio: bool,
+	/// The recording stopped, by itself if it failed.
+	pub finished: bool,
+}
+
+/// Parsers of a pipeline and the recordings their output goes to.
+#[derive(Debug, Default)]
+pub struct Streams {
+	parsers: Vec<gst::Pad>,
+	next_id: u64,
+	recordings: Vec<(RecordingId, Recording)>,
+}
+
+/// Watch `pipeline` for parsers and hand their output to the recordings.
+pub fn connect(pipeline: &gst::Element) -> SharedStreams {
+	let streams = SharedStreams::default();
+	let bin = match pipeline.downcast_ref::<gst::Bin>() {
+		Some(bin) => bin,
+		None => return streams,
+	};
+	let added = streams.clone();
+	bin.connect_deep_element_added(move |_, _, element| {
+		if !is_parser(element) {
+			return;
+		}
+		let pad = match element.static_pad("src") {
+			Some(pad) => pad,
+			None => return,
+		};
+		let probe_streams = added.clone();
+		pad.add_probe(gst::PadProbeType::BUFFER, move |pad, info| {
+			if let Some(gst::PadProbeData::Buffer(ref buffer)) = info.data {
+				probe_streams.lock().unwrap().push(pad, buffer);
+			}
+			gst::PadProbeReturn::Ok
+		});
+		added.lock().unwrap().parsers.push(pad);
+	});
+	let removed = streams.clone();
+	bin.connect_deep_element_removed(move |_, _, element| {
+		let mut streams = removed.lock().unwrap();
+		let count = streams.parsers.len();
+		streams.parsers.retain(|pad| pad.parent_element().as_ref() != Some(element));
+		// The streams start over, e.g. after reconnecting, and won't line up
+		// with what was recorded.
+		if streams.parsers.len() != count {
+			streams.stop_all();
+		}
+	});
+	streams
+}
+
+/// Parsers of elementary audio and video streams, which is what decodebin
+/// plugs in front of decoders.
+fn is_parser(element: &gst::Element) -> bool {
+	let factory = match element.factory() {
+		Some(factory) => factory,
+		None => return false,
+	};
+	let klass = factory.klass();
+	klass.contains("Parser") && (klass.contains("Video") || klass.contains("Audio"))
+}
+
+/// Media type of the stream leaving `pad`, once it is known.
+fn media_type(pad: &gst::Pad) -> Option<String> {
+	let caps = pad.current_caps()?;
+	Some(caps.structure(0)?.name().to_string())
+}
+
+fn is_video(pad: &gst::Pad) -> bool {
+	media_type(pad).map_or(false, |media| media.starts_with("video/"))
+}
+
+impl Streams {
+	/// Start recording to `target` from the next keyframe on.
+	pub fn start(&mut self, target: Target) -> Result<RecordingId, VideoError> {
+		if self.parsers.is_empty() {
+			return Err(VideoError::Record);
+		}
+		let id = RecordingId(self.next_id);
+		self.next_id += 1;
+		self.recordings.push((id, Recording::new(target)));
+		Ok(id)
+	}
+
+	/// Finish a recording, the file is complete once the muxer drained.
+	pub fn stop(&mut self, id: RecordingId) {
+		if let Some(index) = self.recordings.iter().position(|(other, _)| *other == id) {
+			self.recordings.remove(index).1.finish();
+		}
+	}
+
+	fn stop_all(&mut self) {
+		for (_, recording) in self.recordings.drain(..) {
+			recording.finish();
+		}
+	}
+
+	/// What the recording wrote so far, `None` once it is stopped.
+	pub fn progress(&self, id: RecordingId) -> Option<Progress> {
+		let (_, recording) = self.recordings.iter().find(|(other, _)| *other == id)?;
+		Some(recording.progress.lock().unwrap().clone())
+	}
+
+	/// Split a segmented recording at the next keyframe, so what was
+	/// recorded up to now is in closed files.
+	pub fn split(&self, id: RecordingId) {
+		let recording = self.recordings.iter().find(|(other, _)| *other == id);
+		if let Some((_, Recording { sink: Some(sink), target: Target::Segments { .. }, .. })) =
+			recording
+		{
+			sink.emit_by_name::<()>("split-now", &[]);
+		}
+	}
+
+	fn push(&mut self, pad: &gst::Pad, buffer: &gst::Buffer) {
+		let Self { parsers, recordings, .. } = self;
+		recordings.retain_mut(|(_, recording)| match recording.push(parsers, pad, buffer) {
+			Ok(()) => true,
+			Err(err) => {
+				log::error!("Recording failed: {}", err);
+				recording.finish();
+				false
+			}
+		});
+	}
+}
+
+/// An appsrc of a recording and the parser it is fed from.
+#[derive(Debug)]
+struct Input {
+	parser: gst::Pad,
+	appsrc: gst_app::AppSrc,
+}
+
+#[derive(Debug)]
+struct Recording {
+	target: Target,
+	pipeline: Option<gst::Pipeline>,
+	/// The muxer or splitmuxsink.
+	sink: Option<gst::Element>,
+	inputs: Vec<Input>,
+	/// Timestamp of the first recorded keyframe, recorded at zero.
+	base: gst::ClockTime,
+	/// Shared with the bus watch of the recording.
+	progress: Arc<Mutex<Progress>>,
+}
+
+impl Recording {
+	fn new(target: Target) -> Self {
+		Self {
+			target,
+			pipeline: None,
+			sink: None,
+			inputs: Vec::new(),
+			base: gst::ClockTime::ZERO,
+			progress: Arc::default(),
+		}
+	}
+
+	fn push(
+		&mut self,
+		parsers: &[gst::Pad],
+		pad: &gst::Pad,
+		buffer: &gst::Buffer,
+	) -> Result<(), VideoError> {
+		if self.pipeline.is_none() {
+			// Decoding has to start at a keyframe, of the video if there is one.
+			let keyframe = !buffer.flags().contains(gst::BufferFlags::DELTA_UNIT);
+			let video = is_video(pad);
+			if !keyframe || !video && parsers.iter().any(is_video) {
+				return Ok(());
+			}
+			self.base = match buffer.dts_or_pts() {
+				Some(base) => base,
+				None => return Ok(()),
+			};
+			self.build(parsers)?;
+		}
+		let input = match self.inputs.iter().find(|input| input.parser == *pad) {
+			Some(input) => input,
+			None => return Ok(()),
+		};
+		let timestamp = match buffer.dts_or_pts() {
+			Some(timestamp) if timestamp >= self.base => timestamp,
+			// Before the recording started.
+			_ => return Ok(()),
+		};
+		// The copy shares the memory, only the metadata is copied.
+		let mut buffer = buffer.copy();
+		{
+			let buffer = buffer.get_mut().unwrap();
+			buffer.set_pts(buffer.pts().and_then(|pts| pts.checked_sub(self.base)));
+			buffer.set_dts(buffer.dts().and_then(|dts| dts.checked_sub(self.base)));
+		}
+		let end = Duration::from_nanos((timestamp - self.base).nseconds());
+		let mut progress = self.progress.lock().unwrap();
+		progress.end = progress.end.max(end);
+		drop(progress);
+		input.appsrc.push_buffer(buffer).map_err(VideoError::FlowError)?;
+		Ok(())
+	}
+
+	/// Build the pipeline writing the streams of `parsers`.
+	fn build(&mut self, parsers: &[gst::Pad]) -> Result<(), VideoError> {
+		let pipeline = gst::Pipeline::new(None);
+		let sink = match self.target {
+			Target::File(ref path) => {
+				let mux = gst::ElementFactory::make("matroskamux", None)?;
+				let filesink = gst::ElementFactory::make("filesink", None)?;
+				filesink.set_property("location", path.to_string_lossy().as_ref());
+				pipeline.add_many(&[&mux, &filesink])?;
+				mux.link(&filesink)?;
+				mux
+			}
+			Target::Segments { ref dir, segment, max_files } => {
+				std::fs::create_dir_all(dir)?;
+				let sink = gst::ElementFactory::make("splitmuxsink", None)?;
+				sink.set_property("muxer", gst::ElementFactory::make("matroskamux", None)?);
+				sink.set_property(
+					"location",
+					dir.join("segment%05d.mkv").to_string_lossy().as_ref(),
+				);
+				sink.set_property("max-size-time", segment.as_nanos() as u64);
+				sink.set_property("max-files", max_files);
+				pipeline.add(&sink)?;
+				sink
+			}
+		};
+
+		let segments = matches!(self.target, Target::Segments { .. });
+		let mut video = false;
+		let mut audio = false;
+		for parser in parsers {
+			let caps = match parser.current_caps() {
+				Some(caps) => caps,
+				None => continue,
+			};
+			let pad = match media_type(parser) {
+				// splitmuxsink takes a single video stream.
+				Some(media) if media.starts_with("video/") && !(segments && video) => {
+					video = true;
+					sink.request_pad_simple(if segments { "video" } else { "video_%u" })
+				}
+				Some(media) if media.starts_with("audio/") => {
+					audio = true;
+					sink.request_pad_simple("audio_%u")
+				}
+				_ => None,
+			};
+			let pad = match pad {
+				Some(pad) => pad,
+				None => continue,
+			};
+			let appsrc = gst::ElementFactory::make("appsrc", None)?
+				.downcast::<gst_app::AppSrc>()
+				.map_err(|_| VideoError::Cast)?;
+			appsrc.set_caps(Some(&caps));
+			appsrc.set_format(gst::Format::Time);
+			let queue = gst::ElementFactory::make("queue", None)?;
+			pipeline.add_many(&[appsrc.upcast_ref::<gst::Element>(), &queue])?;
+			appsrc.link(&queue)?;
+			queue.static_pad("src").ok_or(VideoError::Cast)?.link(&pad)?;
+			self.inputs.push(Input { parser: parser.clone(), appsrc });
+		}
+		if self.inputs.is_empty() {
+			return Err(VideoError::Record);
+		}
+		self.progress.lock().unwrap().audio = audio;
+
+		pipeline.set_state(gst::State::Playing)?;
+		let bus = pipeline.bus().ok_or(VideoError::Bus)?;
+		let cleanup = match self.target {
+			Target::Segments { ref dir, .. } => Some(dir.clone()),
+			Target::File(_) => None,
+		};
+		let max_files = match self.target {
+			Target::Segments { max_files, .. } => max_files as usize,
+			Target::File(_) => 0,
+		};
+		let watch = Watch { pipeline: pipeline.clone(), progress: self.progress.clone() };
+		thread::spawn(move || watch.run(bus, max_files, cleanup));
+		self.pipeline = Some(pipeline);
+		self.sink = Some(sink);
+		Ok(())
+	}
+
+	/// Let the muxer write out what it has, the bus watch shuts the pipeline
+	/// down once it did.
+	fn finish(&self) {
+		if self.pipeline.is_none() {
+			self.progress.lock().unwrap().finished = true;
+		}
+		for input in &self.inputs {
+			let _ = input.appsrc.end_of_stream();
+		}
+	}
+}
+
+/// Follows the bus of a recording until it is done.
+struct Watch {
+	pipeline: gst::Pipeline,
+	progress: Arc<Mutex<Progress>>,
+}
+
+impl Watch {
+	fn run(self, bus: gst::Bus, max_files: usize, cleanup: Option<PathBuf>) {
+		let mut open: Option<(PathBuf, Duration)> = None;
+		let mut fragments = VecDeque::new();
+		for msg in bus.iter_timed(gst::ClockTime::NONE) {
+			match msg.view() {
+				gst::MessageView::Eos(_) => break,
+				gst::MessageView::Error(err) => {
+					log::error!("Recording failed: {}: {:?}", err.error(), err.debug());
+					break;
+				}
+				gst::MessageView::Element(element) => {
+					let s = match element.structure() {
+						Some(s) => s,
+						None => continue,
+					};
+					let (path, time) =
+						match (s.get::<String>("location"), s.get::<u64>("running-time")) {
+							(Ok(path), Ok(time)) => {
+								(PathBuf::from(path), Duration::from_nanos(time))
+							}
+							_ => continue,
+						};
+					match s.name() {
+						"splitmuxsink-fragment-opened" => {
+							// File names are reused once there are `max_files`.
+							fragments.retain(|fragment: &Fragment| fragment.path != path);
+							open = Some((path, time));
+						}
+						"splitmuxsink-fragment-closed" => {
+							let start = open.take().map_or(time, |(_, start)| start);
+							fragments.push_back(Fragment { path, start, end: time });
+							while fragments.len() > max_files {
+								fragments.pop_front();
+							}
+						}
+						_ => continue,
+					}
+					self.progress.lock().unwrap().fragments = fragments.iter().cloned().collect();
+				}
+				_ => {}
+			}
+		}
+		let _ = self.pipeline.set_state(gst::State::Null);
+		self.progress.lock().unwrap().finished = true;
+		if let Some(dir) = cleanup {
+			if let Err(err) = std::fs::remove_dir_all(&dir) {
+				log::warn!("Failed to remove {}: {}", dir.display(), err);
+			}
+		}
+	}
+}
@@ -0,0 +1,43 @@
+// Configuration of HTTP source elements.
+
+// Applied from playbin's `source-setup` signal and to the source used to
+// fetch adaptive manifests, so both reach endpoints behind auth and proxies.
+use gst::prelude::*;
+use gstreamer as gst;
+
+use crate::media::options::SourceOptions;
+
+/// Apply `options` to `source` if it is a `souphttpsrc`.
+pub fn setup_http_source(source: &gst::Element, options: &SourceOptions) {
+	let factory = source.factory().map(|factory| factory.name());
+	if factory.as_deref() != Some("souphttpsrc") {
+		return;
+	}
+
+	if let Some(ref user_agent) = options.user_agent {
+		source.set_property("user-agent", user_agent);
+	}
+	if !options.headers.is_empty() {
+		let mut headers = gst::Structure::new_empty("extra-headers");
+		for (name, value) in &options.headers {
+			headers.set(name, value.as_str());
+		}
+		source.set_property("extra-headers", &headers);
+	}
+	if let Some(ref user_id) = options.user_id {
+		source.set_property("user-id", user_id);
+	}
+	if let Some(ref user_pw) = options.user_pw {
+		source.set_property("user-pw", user_pw);
+	}
+	if !options.cookies.is_empty() {
+		source.set_property("cookies", options.cookies.clone());
+	}
+	if let Some(ref proxy) = options.proxy {
+		source.set_property("proxy", proxy);
+	}
+	source.set_property("ssl-strict", options.ssl_strict);
+	if let Some(ref ca_file) = options.ssl_ca_file {
+		source.set_property("ssl-ca-file", ca_file.to_string_lossy().as_ref());
+	}
+}
@@ -0,0 +1,397 @@
+// Subscriptions to decoded media.
+
+// The sink bins hand every decoded buffer to the registered taps. Taps
+// either run on the streaming thread, or get a bounded queue drained by a
+// thread of their own:
+
+// {appsink} - {inline callback}
+//           - {queue} - {tap thread} - {callback}
+use std::{
+	collections::VecDeque,
+	sync::{Arc, Condvar, Mutex},
+	thread,
+};
+
+use gstreamer as gst;
+
+/// Handle of a registered tap, to remove it again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TapId(pub(crate) u64);
+
+/// What a tap does when its queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropPolicy {
+	/// Drop the sample that doesn't fit anymore.
+	DropNewest,
+	/// Drop the oldest queued sample to make room.
+	DropOldest,
+	/// Hold up playback until the tap caught up.
+	Block,
+}
+
+/// Queue of a tap running on its own thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TapOptions {
+	/// Number of samples queued for the tap.
+	pub queue: usize,
+	pub drop: DropPolicy,
+}
+
+impl Default for TapOptions {
+	fn default() -> Self {
+		Self { queue: 4, drop: DropPolicy::DropOldest }
+	}
+}
+
+/// A decoded frame handed to frame taps, only valid during the callback.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameRef<'a> {
+	pub(crate) data: &'a [u8],
+	pub(crate) width: u32,
+	pub(crate) height: u32,
+	pub(crate) stride: usize,
+	pub(crate) pts: Option<std::time::Duration>,
+	pub(crate) number: u64,
+}
+
+impl<'a> FrameRef<'a> {
+	/// Pixel data, `height` rows of `stride` bytes.
+	pub fn data(&self) -> &'a [u8] {
+		self.data
+	}
+
+	/// The pixels of row `y`, without padding.
+	pub fn row(&self, y: u32) -> &'a [u8] {
+		let start = y as usize * self.stride;
+		&self.data[start..start + self.width as usize * 4]
+	}
+
+	pub fn width(&self) -> u32 {
+		self.width
+	}
+
+	pub fn height(&self) -> u32 {
+		self.height
+	}
+
+	/// Bytes from the start of one row to the next.
+	pub fn stride(&self) -> usize {
+		self.stride
+	}
+
+	/// Presentation timestamp in the media.
+	pub fn pts(&self) -> Option<std::time::Duration> {
+		self.pts
+	}
+
+	/// Number of the frame since the player started, counting from zero.
+	pub fn number(&self) -> u64 {
+		self.number
+	}
+}
+
+/// A decoded frame as queued for frame taps.
+#[derive(Debug, Clone)]
+pub(crate) struct FrameSample {
+	pub buffer: gst::Buffer,
+	pub width: u32,
+	pub height: u32,
+	pub number: u64,
+}
+
+/// Decoded audio handed to audio taps.
+#[derive(Debug, Clone)]
+pub struct AudioSamples {
+	pub(crate) samples: Arc<[f32]>,
+	pub(crate) rate: i32,
+	pub(crate) channels: i32,
+	pub(crate) channel_mask: Option<u64>,
+	pub(crate) pts: Option<std::time::Duration>,
+}
+
+impl AudioSamples {
+	/// Interleaved samples, one per channel for every point in time.
+	pub fn samples(&self) -> &[f32] {
+		&self.samples
+	}
+
+	/// Sample rate in Hz.
+	pub fn rate(&self) -> i32 {
+		self.rate
+	}
+
+	pub fn channels(&self) -> i32 {
+		self.channels
+	}
+
+	/// Channel positions as a GStreamer channel mask, one bit per position
+	/// in the order the channels are interleaved. `None` for the default
+	/// layout of the channel count.
+	pub fn channel_mask(&self) -> Option<u64> {
+		self.channel_mask
+	}
+
+	/// Presentation timestamp of the first sample in the media.
+	pub fn pts(&self) -> Option<std::time::Duration> {
+		self.pts
+	}
+
+	/// Duration of the samples.
+	pub fn duration(&self) -> std::time::Duration {
+		let frames = self.samples.len() as u64 / self.channels.max(1) as u64;
+		std::time::Duration::from_nanos(frames * 1_000_000_000 / self.rate.max(1) as u64)
+	}
+}
+
+/// Taps shared between the player and the streaming thread.
+pub type SharedTaps<T> = Arc<Mutex<Taps<T>>>;
+
+enum Tap<T> {
+	Inline(Box<dyn FnMut(&T) + Send>),
+	Queued(Arc<Queue<T>>, DropPolicy),
+}
+
+/// Registered taps for samples of type `T`.
+pub struct Taps<T> {
+	next_id: u64,
+	taps: Vec<(TapId, Tap<T>)>,
+}
+
+impl<T> std::fmt::Debug for Taps<T> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("Taps").field("len", &self.taps.len()).finish()
+	}
+}
+
+impl<T> Default for Taps<T> {
+	fn default() -> Self {
+		Self { next_id: 0, taps: Vec::new() }
+	}
+}
+
+impl<T: Send + 'static> Taps<T> {
+	/// Call `callback` on the streaming thread.
+	pub fn add_inline(&mut self, callback: impl FnMut(&T) + Send + 'static) -> TapId {
+		self.add(Tap::Inline(Box::new(callback)))
+	}
+
+	/// Call `callback` on a new thread, queuing samples as `options` say.
+	pub fn add_queued(
+		&mut self,
+		options: TapOptions,
+		mut callback: impl FnMut(&T) + Send + 'static,
+	) -> TapId {
+		let queue = Arc::new(Queue::new(options.queue.max(1)));
+		let worker = queue.clone();
+		thread::spawn(move || {
+			while let Some(sample) = worker.pop() {
+				callback(&sample);
+			}
+		});
+		self.add(Tap::Queued(queue, options.drop))
+	}
+
+	fn add(&mut self, tap: Tap<T>) -> TapId {
+		let id = TapId(self.next_id);
+		self.next_id += 1;
+		self.taps.push((id, tap));
+		id
+	}
+
+	/// Unregister a tap, its thread ends after the queued samples.
+	pub fn remove(&mut self, id: TapId) {
+		self.taps.retain(|(tap_id, _)| *tap_id != id);
+	}
+
+	/// Whether there is any tap to hand samples to.
+	pub fn is_empty(&self) -> bool {
+		self.taps.is_empty()
+	}
+}
+
+/// Hand `sample` to every tap of `taps`.
+///
+/// Queued taps are pushed to after unlocking `taps`: a blocking push waits
+/// for the tap thread, meanwhile taps can still be added and removed.
+pub fn dispatch<T: Clone>(taps: &Mutex<Taps<T>>, sample: T) {
+	let mut queues = Vec::new();
+	for (_, tap) in &mut taps.lock().unwrap().taps {
+		match tap {
+			Tap::Inline(callback) => callback(&sample),
+			Tap::Queued(queue, policy) => queues.push((queue.clone(), *policy)),
+		}
+	}
+	for (queue, policy) in queues {
+		queue.push(sample.clone(), policy);
+	}
+}
+
+impl<T> Drop for Tap<T> {
+	fn drop(&mut self) {
+		if let Tap::Queued(queue, _) = self {
+			queue.close();
+		}
+	}
+}
+
+struct Queue<T> {
+	state: Mutex<QueueState<T>>,
+	changed: Condvar,
+	capacity: usize,
+}
+
+struct QueueState<T> {
+	samples: VecDeque<T>,
+	closed: bool,
+}
+
+impl<T> Queue<T> {
+	fn new(capacity: usize) -> Self {
+		Self {
+			state: Mutex::new(QueueState { samples: VecDeque::new(), closed: false }),
+			changed: Condvar::new(),
+			capacity,
+		}
+	}
+
+	fn push(&self, sample: T, policy: DropPolicy) {
+		let mut state = self.state.lock().unwrap();
+		while state.samples.len() >= self.capacity && !state.closed {
+			match policy {
+				DropPolicy::DropNewest => return,
+				DropPolicy::DropOldest => {
+					state.samples.pop_front();
+				}
+				DropPolicy::Block => state = self.changed.wait(state).unwrap(),
+			}
+		}
+		state.samples.push_back(sample);
+		self.changed.notify_all();
+	}
+
+	/// Wait for the next sample, `None` once the queue is closed and empty.
+	fn pop(&self) -> Option<T> {
+		let mut state = self.state.lock().unwrap();
+		loop {
+			if let Some(sample) = state.samples.pop_front() {
+				self.changed.notify_all();
+				return Some(sample);
+			}
+			if state.closed {
+				return None;
+			}
+			state = self.changed.wait(state).unwrap();
+		}
+	}
+
+	fn close(&self) {
+		self.state.lock().unwrap().closed = true;
+		self.changed.notify_all();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{sync::mpsc, time::Duration};
+
+	use super::*;
+
+	/// Long enough for a thread to get blocked.
+	const SETTLE: Duration = Duration::from_millis(50);
+
+	fn drain(queue: &Queue<u32>) -> Vec<u32> {
+		queue.close();
+		std::iter::from_fn(|| queue.pop()).collect()
+	}
+
+	#[test]
+	fn drop_newest() {
+		let queue = Queue::new(2);
+		(1..=4).for_each(|sample| queue.push(sample, DropPolicy::DropNewest));
+		assert_eq!(drain(&queue), [1, 2]);
+	}
+
+	#[test]
+	fn drop_oldest() {
+		let queue = Queue::new(2);
+		(1..=4).for_each(|sample| queue.push(sample, DropPolicy::DropOldest));
+		assert_eq!(drain(&queue), [3, 4]);
+	}
+
+	#[test]
+	fn block() {
+		let queue = Arc::new(Queue::new(1));
+		queue.push(1, DropPolicy::Block);
+		let (pushed, pushed_rx) = mpsc::channel();
+		let pusher = queue.clone();
+		thread::spawn(move || {
+			pusher.push(2, DropPolicy::Block);
+			pushed.send(()).unwrap();
+		});
+		assert!(pushed_rx.recv_timeout(SETTLE).is_err(), "pushed to a full queue");
+		assert_eq!(queue.pop(), Some(1));
+		pushed_rx.recv().unwrap();
+		assert_eq!(drain(&queue), [2]);
+	}
+
+	#[test]
+	fn close_wakes_pop() {
+		let queue = Arc::new(Queue::<u32>::new(1));
+		let popper = queue.clone();
+		let popped = thread::spawn(move || popper.pop());
+		thread::sleep(SETTLE);
+		queue.close();
+		assert_eq!(popped.join().unwrap(), None);
+	}
+
+	#[test]
+	fn close_wakes_blocked_push() {
+		let queue = Arc::new(Queue::new(1));
+		queue.push(1, DropPolicy::Block);
+		let pusher = queue.clone();
+		let pushed = thread::spawn(move || pusher.push(2, DropPolicy::Block));
+		thread::sleep(SETTLE);
+		queue.close();
+		pushed.join().unwrap();
+		// Queued samples are still handed out after closing.
+		assert_eq!(queue.pop(), Some(1));
+	}
+
+	#[test]
+	fn taps_get_every_sample() {
+		let taps = Mutex::new(Taps::default());
+		let (inline, inline_rx) = mpsc::channel();
+		taps.lock().unwrap().add_inline(move |sample: &u32| inline.send(*sample).unwrap());
+		let (queued, queued_rx) = mpsc::channel();
+		let options = TapOptions { queue: 4, drop: DropPolicy::Block };
+		let id =
+			taps.lock().unwrap().add_queued(options, move |sample| queued.send(*sample).unwrap());
+		(1..=3).for_each(|sample| dispatch(&taps, sample));
+		taps.lock().unwrap().remove(id);
+		assert_eq!(inline_rx.iter().take(3).collect::<Vec<_>>(), [1, 2, 3]);
+		// The tap thread ends after the queued samples.
+		assert_eq!(queued_rx.iter().collect::<Vec<_>>(), [1, 2, 3]);
+	}
+
+	#[test]
+	fn blocked_dispatch_leaves_the_taps_unlocked() {
+		let taps = Arc::new(Mutex::new(Taps::default()));
+		let (release, released) = mpsc::channel::<()>();
+		let options = TapOptions { queue: 1, drop: DropPolicy::Block };
+		// The tap thread takes the first sample and hangs on to it.
+		let id = taps.lock().unwrap().add_queued(options, move |_: &u32| {
+			let _ = released.recv();
+		});
+		let (dispatched, dispatched_rx) = mpsc::channel();
+		let dispatcher = taps.clone();
+		thread::spawn(move || {
+			(1..=3).for_each(|sample| dispatch(&dispatcher, sample));
+			dispatched.send(()).unwrap();
+		});
+		assert!(dispatched_rx.recv_timeout(SETTLE).is_err(), "the full queue didn't block");
+		// Removing the tap closes the queue, which wakes the dispatch.
+		taps.lock().unwrap().remove(id);
+		dispatched_rx.recv_timeout(Duration::from_secs(5)).expect("dispatch still blocked");
+		drop(release);
+	}
+}
@@ -0,0 +1,140 @@
+// Test patterns and tones.
+
+// `testsrc://` URIs play videotestsrc and audiotestsrc through the same sink
+// bins as any other media:
+
+// {videotestsrc} - {capsfilter} - {video sink bin}
+// {audiotestsrc} - {volume} - {audio sink bin}
+use crate::media::error::VideoError;
+
+/// URI scheme of the test source.
+pub const SCHEME: &str = "testsrc";
+
+/// Picture of the built-in test source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestPattern {
+	SmpteBars,
+	Checkers,
+	Ball,
+	ZonePlate,
+}
+
+/// Test pattern and tone played for `testsrc://` URIs.
+///
+/// `testsrc://<pattern>?width=<w>&height=<h>&framerate=<n[/d]>&freq=<Hz>`,
+/// with `smpte`, `checkers`, `ball` or `zone-plate` as pattern. `freq=0`
+/// turns the tone off.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestSourceConfig {
+	pub pattern: TestPattern,
+	pub width: i32,
+	pub height: i32,
+	/// Framerate as `(numerator, denominator)`.
+	pub framerate: (i32, i32),
+	/// Frequency of the sine tone in Hz, `None` for no audio.
+	pub frequency: Option<f64>,
+}
+
+impl Default for TestSourceConfig {
+	fn default() -> Self {
+		Self {
+			pattern: TestPattern::SmpteBars,
+			width: 1280,
+			height: 720,
+			framerate: (30, 1),
+			frequency: Some(440.0),
+		}
+	}
+}
+
+/// Read the test source configuration from a `testsrc://` URI.
+pub fn parse_uri(uri: &url::Url) -> Result<TestSourceConfig, VideoError> {
+	let pattern = match uri.host_str().unwrap_or_default() {
+		"" | "smpte" | "bars" => TestPattern::SmpteBars,
+		"checkers" => TestPattern::Checkers,
+		"ball" => TestPattern::Ball,
+		"zone-plate" => TestPattern::ZonePlate,
+		_ => return Err(VideoError::Uri),
+	};
+	let mut config = TestSourceConfig { pattern, ..Default::default() };
+	for (key, value) in uri.query_pairs() {
+		match &*key {
+			"width" => config.width = value.parse().map_err(|_| VideoError::Uri)?,
+			"height" => config.height = value.parse().map_err(|_| VideoError::Uri)?,
+			"framerate" => {
+				let (numer, denom) = value.split_once('/').unwrap_or((&value, "1"));
+				config.framerate = (
+					numer.parse().map_err(|_| VideoError::Uri)?,
+					denom.parse().map_err(|_| VideoError::Uri)?,
+				);
+			}
+			"freq" => {
+				let frequency: f64 = value.parse().map_err(|_| VideoError::Uri)?;
+				config.frequency = Some(frequency).filter(|&frequency| frequency > 0.0);
+			}
+			_ => return Err(VideoError::Uri),
+		}
+	}
+	if config.width <= 0 || config.height <= 0 || config.framerate.0 <= 0 || config.framerate.1 <= 0
+	{
+		return Err(VideoError::Uri);
+	}
+	Ok(config)
+}
+
+/// Name of the videotestsrc `pattern` value.
+pub fn pattern_nick(pattern: TestPattern) -> &'static str {
+	match pattern {
+		TestPattern::SmpteBars => "smpte",
+		TestPattern::Checkers => "checkers-8",
+		TestPattern::Ball => "ball",
+		TestPattern::ZonePlate => "zone-plate",
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse(uri: &str) -> Result<TestSourceConfig, VideoError> {
+		parse_uri(&url::Url::parse(uri).unwrap())
+	}
+
+	#[test]
+	fn defaults() {
+		assert_eq!(parse("testsrc://").unwrap(), TestSourceConfig::default());
+		assert_eq!(parse("testsrc://bars").unwrap().pattern, TestPattern::SmpteBars);
+	}
+
+	#[test]
+	fn query() {
+		let config =
+			parse("testsrc://checkers?width=320&height=240&framerate=30000/1001&freq=0").unwrap();
+		assert_eq!(
+			config,
+			TestSourceConfig {
+				pattern: TestPattern::Checkers,
+				width: 320,
+				height: 240,
+				framerate: (30000, 1001),
+				frequency: None,
+			}
+		);
+		assert_eq!(parse("testsrc://ball?framerate=25").unwrap().framerate, (25, 1));
+		assert_eq!(parse("testsrc://zone-plate?freq=1000").unwrap().frequency, Some(1000.0));
+	}
+
+	#[test]
+	fn invalid() {
+		for uri in [
+			"testsrc://snow",
+			"testsrc://smpte?width=-1",
+			"testsrc://smpte?height=tall",
+			"testsrc://smpte?framerate=30/0",
+			"testsrc://smpte?freq=high",
+			"testsrc://smpte?brightness=2",
+		] {
+			assert!(parse(uri).is_err(), "{}", uri);
+		}
+	}
+}
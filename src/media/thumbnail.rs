@@ -11,13 +11,16 @@ use std::sync::mpsc::{sync_channel, Receiver, Sender, TryRecvError, TrySendError
 
 use anyhow::{Error, Result};
 use derive_more::{Display, Error};
-use druid::{piet::ImageFormat, ImageBuf};
 use gst::{element_error, prelude::*};
 use gstreamer as gst;
 use gstreamer::Pipeline;
 use gstreamer_app as gst_app;
 
-use crate::gui::data::video::VideoError;
+use crate::media::{
+	error::VideoError,
+	player::{FrameData, VideoFrame},
+	types::Colorimetry,
+};
 
 #[derive(Debug, Display, Error)]
 #[display(fmt = "Missing element {}", _0)]
@@ -32,7 +35,7 @@ struct ErrorMessage {
 	source: glib::Error,
 }
 pub struct Thumbnail {
-	pub receiver: Receiver<ImageBuf>,
+	pub receiver: Receiver<VideoFrame>,
 	pipeline: Pipeline,
 	pub duration: u64,
 }
@@ -102,13 +105,18 @@ impl Thumbnail {
 					let height = s.get::<i32>("height").map_err(|_| gst::FlowError::Error)?;
 					// println!("W: {:?}, H: {:?}", width, height);
 					// Send original and processed image.
-					let image = ImageBuf::from_raw(
-						map.as_slice().to_owned(),
-						ImageFormat::RgbaSeparate,
-						width as _,
-						height as _,
-					);
-					match sender.try_send(image) {
+					let frame = VideoFrame {
+						data: FrameData::from(map.as_slice().to_owned()),
+						width: width as u32,
+						height: height as u32,
+						pixel_aspect_ratio: (1, 1),
+						pts: None,
+						running_time: None,
+						clock: None,
+						number: 0,
+						colorimetry: Colorimetry::default(),
+					};
+					match sender.try_send(frame) {
 						Ok(_) => {
 							// Ok(gst::FlowSuccess::Ok)
 						}
@@ -133,7 +141,7 @@ impl Thumbnail {
 			pipeline.query_duration::<gst::ClockTime>().ok_or(VideoError::Duration)?.nseconds(),
 		)
 		.as_secs();
-		println!("duration: {}", duration);
+		log::debug!("Thumbnail of {}s of media", duration);
 
 		let bus = pipeline.bus().expect("Pipeline without bus. Shouldn't happen!");
 
@@ -146,26 +154,23 @@ impl Thumbnail {
 				MessageView::AsyncDone(..) => {
 					if !seeked {
 						// AsyncDone means that the pipeline has started now and that we can seek
-						println!("Got AsyncDone message, seeking to {}s", position);
+						log::debug!("Seeking the thumbnail to {}s", position);
 
 						if pipeline
 							.seek_simple(gst::SeekFlags::FLUSH, position * gst::ClockTime::SECOND)
 							.is_err()
 						{
-							println!("Failed to seek, taking first frame");
+							log::warn!("Failed to seek the thumbnail, taking the first frame");
 						}
 
 						pipeline.set_state(gst::State::Playing)?;
 						seeked = true;
-					} else {
-						println!("Got second AsyncDone message, seek finished");
 					}
 				}
 				MessageView::Eos(..) => {
 					// The End-of-stream message is posted when the stream is done, which in our
 					// case happens immediately after creating the thumbnail because we return
 					// gst::FlowError::Eos then.
-					println!("Got Eos message, done");
 					break;
 				}
 				MessageView::Error(err) => {
@@ -0,0 +1,299 @@
+//! What the players report and are told: positions, variants, orientation,
+//! colorimetry and buffering.
+use std::fmt;
+
+use gstreamer as gst;
+
+/// Position in the media.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Position {
+	/// Position based on time.
+	///
+	/// Not the most accurate format for videos.
+	Time(std::time::Duration),
+	/// Position based on nth frame.
+	Frame(u64),
+}
+
+impl From<Position> for gst::GenericFormattedValue {
+	fn from(pos: Position) -> Self {
+		match pos {
+			Position::Time(t) => gst::ClockTime::from_nseconds(t.as_nanos() as _).into(),
+			Position::Frame(f) => gst::format::Default(f).into(),
+		}
+	}
+}
+
+impl From<std::time::Duration> for Position {
+	fn from(t: std::time::Duration) -> Self {
+		Position::Time(t)
+	}
+}
+
+impl From<u64> for Position {
+	fn from(f: u64) -> Self {
+		Position::Frame(f)
+	}
+}
+
+/// A recording as shown while it is written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordStatus {
+	pub path: String,
+	/// Size of the file in bytes.
+	pub bytes: u64,
+	/// Recorded time in seconds.
+	pub duration: u64,
+}
+
+/// A variant of an adaptive stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variant {
+	/// Advertised bitrate in bits per second.
+	pub bitrate: u64,
+	/// Resolution as `(width, height)`, if advertised.
+	pub size: Option<(u32, u32)>,
+	/// Part of the variant's URI used to recognise its fragments.
+	pub hint: String,
+}
+
+impl fmt::Display for Variant {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if let Some((width, height)) = self.size {
+			write!(f, "{}×{} · ", width, height)?;
+		}
+		write!(f, "{:.1} Mb/s", self.bitrate as f64 / 1_000_000.0)
+	}
+}
+
+/// How frames were coded as Y'CbCr before they got converted to RGBA, as
+/// the caps upstream of the converter tell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Colorimetry {
+	pub matrix: YuvMatrix,
+	/// The values span `0..=255` instead of `16..=235` for luma and
+	/// `16..=240` for chroma.
+	pub full_range: bool,
+}
+
+/// Coefficients Y'CbCr is computed from R'G'B' with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum YuvMatrix {
+	Bt601,
+	Bt709,
+	Bt2020,
+	Smpte240m,
+}
+
+impl Default for Colorimetry {
+	fn default() -> Self {
+		Self { matrix: YuvMatrix::Bt709, full_range: false }
+	}
+}
+
+impl Colorimetry {
+	/// The colorimetry of video `caps`. Without one they get what GStreamer
+	/// assumes: BT.601 up to 576 lines, BT.709 above.
+	pub fn from_caps(caps: &gst::CapsRef) -> Self {
+		let s = match caps.structure(0) {
+			Some(s) => s,
+			None => return Self::default(),
+		};
+		let tagged = s.get::<&str>("colorimetry").ok().and_then(Self::parse);
+		tagged.unwrap_or_else(|| {
+			let matrix = match s.get::<i32>("height") {
+				Ok(height) if height <= 576 => YuvMatrix::Bt601,
+				_ => YuvMatrix::Bt709,
+			};
+			Self { matrix, full_range: false }
+		})
+	}
+
+	/// Parse the `colorimetry` field of caps, a name or
+	/// `range:matrix:transfer:primaries`. Colorimetries without a Y'CbCr
+	/// matrix, like sRGB, give `None`.
+	pub fn parse(colorimetry: &str) -> Option<Self> {
+		let limited = |matrix| Some(Self { matrix, full_range: false });
+		match colorimetry {
+			"bt601" => limited(YuvMatrix::Bt601),
+			"bt709" => limited(YuvMatrix::Bt709),
+			"bt2020" | "bt2020-10" | "bt2100-pq" | "bt2100-hlg" => limited(YuvMatrix::Bt2020),
+			"smpte240m" => limited(YuvMatrix::Smpte240m),
+			_ => {
+				// Numbered like GstVideoColorRange and GstVideoColorMatrix.
+				let mut fields = colorimetry.split(':').map(str::parse::<u32>);
+				let (range, matrix) = (fields.next()?.ok()?, fields.next()?.ok()?);
+				let matrix = match matrix {
+					3 => YuvMatrix::Bt709,
+					4 => YuvMatrix::Bt601,
+					5 => YuvMatrix::Smpte240m,
+					6 => YuvMatrix::Bt2020,
+					_ => return None,
+				};
+				// An unknown range is taken as limited, like most video is.
+				Some(Self { matrix, full_range: range == 1 })
+			}
+		}
+	}
+}
+
+impl fmt::Display for Colorimetry {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let matrix = match self.matrix {
+			YuvMatrix::Bt601 => "BT.601",
+			YuvMatrix::Bt709 => "BT.709",
+			YuvMatrix::Bt2020 => "BT.2020",
+			YuvMatrix::Smpte240m => "SMPTE 240M",
+		};
+		let range = if self.full_range { "full" } else { "limited" };
+		write!(f, "{} {}", matrix, range)
+	}
+}
+
+impl YuvMatrix {
+	/// The weights of red and blue in luma, `(Kr, Kb)`.
+	pub fn coefficients(self) -> (f64, f64) {
+		match self {
+			YuvMatrix::Bt601 => (0.299, 0.114),
+			YuvMatrix::Bt709 => (0.2126, 0.0722),
+			YuvMatrix::Bt2020 => (0.2627, 0.0593),
+			YuvMatrix::Smpte240m => (0.212, 0.087),
+		}
+	}
+}
+
+/// Rotation and mirroring of the frames, on top of the orientation the
+/// stream is tagged with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Orientation {
+	/// Clockwise rotation in degrees, 0, 90, 180 or 270.
+	pub rotation: u16,
+	/// Mirrored horizontally before the rotation.
+	pub mirrored: bool,
+}
+
+impl Orientation {
+	/// Rotated 90° further clockwise.
+	pub fn rotate_clockwise(self) -> Self {
+		Self { rotation: (self.rotation + 90) % 360, ..self }
+	}
+
+	/// Flipped left to right, as shown.
+	pub fn flip_horizontal(self) -> Self {
+		Self { rotation: (360 - self.rotation) % 360, mirrored: !self.mirrored }
+	}
+
+	/// Flipped upside down, as shown.
+	pub fn flip_vertical(self) -> Self {
+		Self { rotation: (540 - self.rotation) % 360, mirrored: !self.mirrored }
+	}
+
+	/// Nick of the `GstVideoOrientationMethod` doing this.
+	pub fn method(&self) -> &'static str {
+		match (self.rotation, self.mirrored) {
+			(90, false) => "90r",
+			(180, false) => "180",
+			(270, false) => "90l",
+			(0, true) => "horiz",
+			(90, true) => "ur-ll",
+			(180, true) => "vert",
+			(270, true) => "ul-lr",
+			_ => "identity",
+		}
+	}
+}
+
+/// Quality selection for adaptive streams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quality {
+	/// Let the demuxer pick the variant from the measured bandwidth.
+	Auto,
+	/// Stick to the variant with the given bitrate.
+	Fixed(u64),
+}
+
+impl Default for Quality {
+	fn default() -> Self {
+		Self::Auto
+	}
+}
+
+/// Download statistics of an adaptive stream, reported per fragment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdaptiveStats {
+	/// Index of the variant the fragment belongs to.
+	pub variant: Option<usize>,
+	/// Estimated download bandwidth in bits per second.
+	pub bandwidth: u64,
+}
+
+/// Buffering progress reported by the pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BufferingInfo {
+	/// Fill level of the buffer, `0..=100`.
+	pub percent: i32,
+	/// Downloaded ranges as `(start, stop)` fractions of the media.
+	pub ranges: Vec<(f64, f64)>,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn orientation_methods() {
+		let methods: Vec<_> = [false, true]
+			.into_iter()
+			.flat_map(|mirrored| {
+				[0, 90, 180, 270].map(|rotation| Orientation { rotation, mirrored }.method())
+			})
+			.collect();
+		assert_eq!(methods, ["identity", "90r", "180", "90l", "horiz", "ur-ll", "vert", "ul-lr"]);
+		assert_eq!(Orientation::default().method(), "identity");
+	}
+
+	#[test]
+	fn orientation_flips_compose() {
+		let identity = Orientation::default();
+		let rotated = identity.rotate_clockwise();
+		assert_eq!(rotated.rotate_clockwise().rotate_clockwise().rotate_clockwise(), identity);
+		assert_eq!(identity.flip_horizontal().flip_horizontal(), identity);
+		assert_eq!(rotated.flip_vertical().flip_vertical(), rotated);
+		assert_eq!(identity.flip_horizontal().method(), "horiz");
+		assert_eq!(identity.flip_vertical().method(), "vert");
+		// Both flips turn the frame upside down.
+		assert_eq!(identity.flip_horizontal().flip_vertical().method(), "180");
+		// Flips apply to the frame as shown, after the rotation.
+		assert_eq!(rotated.flip_horizontal().method(), "ul-lr");
+		assert_eq!(rotated.flip_vertical().method(), "ur-ll");
+		assert_eq!(identity.flip_horizontal().rotate_clockwise().method(), "ur-ll");
+		assert_eq!(identity.flip_vertical().rotate_clockwise().method(), "ul-lr");
+	}
+
+	#[test]
+	fn colorimetry_names_and_numbers() {
+		let colorimetry = |matrix, full_range| Some(Colorimetry { matrix, full_range });
+		assert_eq!(Colorimetry::parse("bt601"), colorimetry(YuvMatrix::Bt601, false));
+		assert_eq!(Colorimetry::parse("bt709"), colorimetry(YuvMatrix::Bt709, false));
+		assert_eq!(Colorimetry::parse("bt2100-pq"), colorimetry(YuvMatrix::Bt2020, false));
+		// JPEG: full range BT.601.
+		assert_eq!(Colorimetry::parse("1:4:0:0"), colorimetry(YuvMatrix::Bt601, true));
+		assert_eq!(Colorimetry::parse("2:3:5:1"), colorimetry(YuvMatrix::Bt709, false));
+		assert_eq!(Colorimetry::parse("0:6:0:0"), colorimetry(YuvMatrix::Bt2020, false));
+		// RGB has no Y'CbCr matrix.
+		assert_eq!(Colorimetry::parse("sRGB"), None);
+		assert_eq!(Colorimetry::parse("1:1:5:1"), None);
+		assert_eq!(Colorimetry::parse("bt"), None);
+	}
+
+	#[test]
+	fn colorimetry_from_caps() {
+		gst::init().unwrap();
+		let caps = |height: i32| gst::Caps::builder("video/x-raw").field("height", height);
+		let tagged = caps(480).field("colorimetry", "bt709").build();
+		assert_eq!(Colorimetry::from_caps(&tagged).matrix, YuvMatrix::Bt709);
+		// Untagged SD is BT.601, HD BT.709.
+		assert_eq!(Colorimetry::from_caps(&caps(576).build()).matrix, YuvMatrix::Bt601);
+		assert_eq!(Colorimetry::from_caps(&caps(720).build()).matrix, YuvMatrix::Bt709);
+	}
+}
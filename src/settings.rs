@@ -0,0 +1,222 @@
+//! Player configuration from the command line and settings files.
+//!
+//! Both use the same keys: `--key value` (or `--key=value`) on the command
+//! line and `key = value` lines in a settings file, which is loaded with
+//! `--settings <path>`. Lines starting with `#` are comments. Settings files
+//! may load other settings files, but not themselves, not even indirectly.
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Result};
+
+use crate::media::options::PlayerOptions;
+
+/// Configuration of a player run.
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+	/// Media item to play, an URI or a local path.
+	pub item: Option<String>,
+	pub options: PlayerOptions,
+}
+
+impl Settings {
+	/// Parse command line arguments, without the program name.
+	pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self> {
+		let mut settings = Self::default();
+		let mut args = args.into_iter();
+		while let Some(arg) = args.next() {
+			let key = match arg.strip_prefix("--") {
+				Some(key) => key,
+				None => {
+					settings.item = Some(arg);
+					continue;
+				}
+			};
+			match key.split_once('=') {
+				Some((key, value)) => settings.set(key, value, &mut Vec::new())?,
+				// Switches don't take a value.
+				None if key == "insecure" || key == "native-resolution" => {
+					settings.set(key, "", &mut Vec::new())?
+				}
+				None => {
+					let value =
+						args.next().ok_or_else(|| anyhow!("missing value for --{}", key))?;
+					settings.set(key, &value, &mut Vec::new())?;
+				}
+			}
+		}
+		Ok(settings)
+	}
+
+	/// Apply the settings file at `path`.
+	pub fn load(&mut self, path: impl AsRef<Path>) -> Result<()> {
+		self.load_nested(path.as_ref(), &mut Vec::new())
+	}
+
+	/// Apply the settings file at `path`, loaded by the files in `loading`.
+	fn load_nested(&mut self, path: &Path, loading: &mut Vec<PathBuf>) -> Result<()> {
+		let content = std::fs::read_to_string(path)
+			.with_context(|| format!("failed to read {}", path.display()))?;
+		// `a.conf` and `./a.conf` are the same file.
+		let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+		if loading.contains(&canonical) {
+			return Err(anyhow!("{} loads itself", path.display()));
+		}
+		loading.push(canonical);
+		for (number, line) in content.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			let (key, value) = line.split_once('=').ok_or_else(|| {
+				anyhow!("{}:{}: expected `key = value`", path.display(), number + 1)
+			})?;
+			self.set(key.trim(), value.trim(), loading)
+				.with_context(|| format!("{}:{}", path.display(), number + 1))?;
+		}
+		loading.pop();
+		Ok(())
+	}
+
+	/// Apply the setting `key`, `loading` are the settings files it comes from.
+	fn set(&mut self, key: &str, value: &str, loading: &mut Vec<PathBuf>) -> Result<()> {
+		if key == "settings" {
+			return self.load_nested(Path::new(value), loading);
+		}
+		let source = &mut self.options.source;
+		match key {
+			"user-agent" => source.user_agent = Some(value.to_string()),
+			"header" => {
+				let (name, value) = value
+					.split_once(':')
+					.ok_or_else(|| anyhow!("expected `Name: value` header, got `{}`", value))?;
+				source.headers.push((name.trim().to_string(), value.trim().to_string()));
+			}
+			"user" => source.user_id = Some(value.to_string()),
+			"password" => source.user_pw = Some(value.to_string()),
+			"cookie" => source.cookies.push(value.to_string()),
+			"proxy" => source.proxy = Some(value.to_string()),
+			"insecure" => source.ssl_strict = false,
+			"ssl-strict" => source.ssl_strict = value.parse()?,
+			"ca-file" => source.ssl_ca_file = Some(value.into()),
+			"native-resolution" => {
+				self.options.native_resolution = value.is_empty() || value.parse::<bool>()?
+			}
+			"record-dir" => self.options.recording.dir = value.into(),
+			"timeshift" => {
+				let minutes: u64 = value.parse()?;
+				self.options.recording.timeshift =
+					Some(std::time::Duration::from_secs(minutes * 60)).filter(|_| minutes > 0);
+			}
+			_ => return Err(anyhow!("unknown setting `{}`", key)),
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::*;
+
+	/// A settings file of `content` in a directory of its own.
+	fn settings_file(name: &str, content: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("druid_video_settings_{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		let path = dir.join(name);
+		std::fs::write(&path, content).unwrap();
+		path
+	}
+
+	fn args(args: &[&str]) -> Result<Settings> {
+		Settings::from_args(args.iter().map(|arg| arg.to_string()))
+	}
+
+	#[test]
+	fn from_args() {
+		let settings = args(&[
+			"--user-agent",
+			"druid_video/test",
+			"--header=X-Token: secret",
+			"--cookie",
+			"session=1",
+			"--insecure",
+			"--native-resolution",
+			"--timeshift",
+			"2",
+			"https://example.com/live.m3u8",
+		])
+		.unwrap();
+		assert_eq!(settings.item.as_deref(), Some("https://example.com/live.m3u8"));
+		let source = &settings.options.source;
+		assert_eq!(source.user_agent.as_deref(), Some("druid_video/test"));
+		assert_eq!(source.headers, [("X-Token".to_string(), "secret".to_string())]);
+		assert_eq!(source.cookies, ["session=1"]);
+		assert!(!source.ssl_strict);
+		assert!(settings.options.native_resolution);
+		assert_eq!(settings.options.recording.timeshift, Some(Duration::from_secs(120)));
+	}
+
+	#[test]
+	fn from_args_errors() {
+		assert!(args(&["--user-agent"]).is_err());
+		assert!(args(&["--header", "no colon"]).is_err());
+		assert!(args(&["--timeshift=soon"]).is_err());
+		assert!(args(&["--volume", "11"]).is_err());
+	}
+
+	#[test]
+	fn load() {
+		let path = settings_file(
+			"load.conf",
+			"# Comment\n\nproxy = http://127.0.0.1:3128\n  user = alice  \ntimeshift = 0\n",
+		);
+		let mut settings = Settings::default();
+		settings.load(&path).unwrap();
+		assert_eq!(settings.options.source.proxy.as_deref(), Some("http://127.0.0.1:3128"));
+		assert_eq!(settings.options.source.user_id.as_deref(), Some("alice"));
+		assert_eq!(settings.options.recording.timeshift, None);
+	}
+
+	#[test]
+	fn load_errors_name_the_line() {
+		let path = settings_file("errors.conf", "user = alice\nvolume = 11\n");
+		let err = Settings::default().load(&path).unwrap_err();
+		assert!(format!("{:#}", err).contains("errors.conf:2"), "{:#}", err);
+		let path = settings_file("no_value.conf", "insecure\n");
+		assert!(Settings::default().load(&path).is_err());
+	}
+
+	#[test]
+	fn load_nested() {
+		let inner = settings_file("inner.conf", "cookie = inner=1\n");
+		let outer = settings_file(
+			"outer.conf",
+			&format!("settings = {}\ncookie = outer=1\n", inner.display()),
+		);
+		let settings = args(&["--settings", outer.to_str().unwrap()]).unwrap();
+		assert_eq!(settings.options.source.cookies, ["inner=1", "outer=1"]);
+		// Loading a file twice is fine, as long as it doesn't load itself.
+		let twice = settings_file(
+			"twice.conf",
+			&format!("settings = {0}\nsettings = {0}\n", inner.display()),
+		);
+		let mut settings = Settings::default();
+		settings.load(&twice).unwrap();
+		assert_eq!(settings.options.source.cookies, ["inner=1", "inner=1"]);
+	}
+
+	#[test]
+	fn load_cycle() {
+		let dir = settings_file("a.conf", "").parent().unwrap().to_path_buf();
+		settings_file("a.conf", &format!("settings = {}\n", dir.join("b.conf").display()));
+		// The same file under another name.
+		let a = dir.join(".").join("a.conf");
+		settings_file("b.conf", &format!("settings = {}\n", a.display()));
+		let err = Settings::default().load(dir.join("a.conf")).unwrap_err();
+		assert!(format!("{:#}", err).contains("loads itself"), "{:#}", err);
+		let selfish = settings_file("self.conf", "");
+		settings_file("self.conf", &format!("settings = {}\n", selfish.display()));
+		assert!(Settings::default().load(&selfish).is_err());
+	}
+}
@@ -0,0 +1,32 @@
+//! `capture://` items preview cameras, or the stand-in test pattern without
+//! any.
+mod common;
+
+use common::channel_sink;
+use druid_video::{
+	devices::{self, CaptureMode, CaptureSelection},
+	player::{PlayerOptions, VideoError, VideoPlayer},
+};
+
+#[test]
+fn the_virtual_device_plays_the_test_pattern() {
+	let selection = CaptureSelection {
+		video: Some(devices::VIRTUAL_DEVICE.to_owned()),
+		mode: Some(CaptureMode { width: 320, height: 240, framerate: (30, 1) }),
+		audio: None,
+	};
+	let (sink, output) = channel_sink();
+	let mut player = VideoPlayer::from_capture(&selection, PlayerOptions::default(), sink)
+		.expect("failed to open the virtual device");
+	player.set_paused(false);
+	let frame = output.frame();
+	assert_eq!((frame.width, frame.height), (320, 240));
+}
+
+#[test]
+fn capture_needs_a_camera() {
+	let (sink, _output) = channel_sink();
+	let result =
+		VideoPlayer::from_capture(&CaptureSelection::default(), PlayerOptions::default(), sink);
+	assert!(matches!(result, Err(VideoError::Uri)));
+}
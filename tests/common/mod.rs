@@ -0,0 +1,99 @@
+//! Fixtures shared by the integration tests.
+#![allow(dead_code)]
+
+use std::{
+	sync::{
+		mpsc::{self, Receiver, RecvTimeoutError, SyncSender},
+		Arc, Mutex,
+	},
+	time::{Duration, Instant},
+};
+
+use druid_video::player::{
+	Disconnected, PlayerEvent, PlayerOptions, PlayerSink, SharedSink, VideoFrame, VideoPlayer,
+};
+
+/// How long the tests wait for the pipeline.
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Frames kept until the test takes them, newer ones are dropped.
+const QUEUED_FRAMES: usize = 8;
+
+/// [`PlayerSink`] handing frames and events to the test thread.
+#[derive(Debug)]
+pub struct ChannelSink {
+	frames: Mutex<SyncSender<VideoFrame>>,
+	events: Mutex<mpsc::Sender<PlayerEvent>>,
+}
+
+impl PlayerSink for ChannelSink {
+	fn frame(&self, frame: VideoFrame) -> Result<(), Disconnected> {
+		match self.frames.lock().unwrap().try_send(frame) {
+			Err(mpsc::TrySendError::Disconnected(_)) => Err(Disconnected),
+			_ => Ok(()),
+		}
+	}
+
+	fn event(&self, event: PlayerEvent) -> Result<(), Disconnected> {
+		self.events.lock().unwrap().send(event).map_err(|_| Disconnected)
+	}
+}
+
+/// What a player put out into its [`ChannelSink`].
+#[derive(Debug)]
+pub struct Output {
+	pub frames: Receiver<VideoFrame>,
+	pub events: Receiver<PlayerEvent>,
+}
+
+impl Output {
+	/// The next frame.
+	pub fn frame(&self) -> VideoFrame {
+		self.frames.recv_timeout(TIMEOUT).expect("no frame")
+	}
+
+	/// Wait for an event `matches` accepts, skipping the others.
+	pub fn event(&self, matches: impl Fn(&PlayerEvent) -> bool) -> PlayerEvent {
+		let deadline = Instant::now() + TIMEOUT;
+		loop {
+			let left = deadline.saturating_duration_since(Instant::now());
+			match self.events.recv_timeout(left) {
+				Ok(event) if matches(&event) => return event,
+				Ok(_) => continue,
+				Err(RecvTimeoutError::Timeout) => panic!("no matching event"),
+				Err(RecvTimeoutError::Disconnected) => panic!("the player is gone"),
+			}
+		}
+	}
+}
+
+/// A sink for a player and what it receives.
+pub fn channel_sink() -> (SharedSink, Output) {
+	let (frames, frames_rx) = mpsc::sync_channel(QUEUED_FRAMES);
+	let (events, events_rx) = mpsc::channel();
+	let sink = ChannelSink { frames: Mutex::new(frames), events: Mutex::new(events) };
+	(Arc::new(sink), Output { frames: frames_rx, events: events_rx })
+}
+
+/// A playing player of the test source at `uri` and what it puts out.
+pub fn play_test_source(uri: &str) -> (VideoPlayer, Output) {
+	let uri = url::Url::parse(uri).unwrap();
+	let (sink, output) = channel_sink();
+	let mut player = VideoPlayer::with_options(&uri, false, PlayerOptions::default(), sink)
+		.expect("failed to open the test source");
+	player.set_paused(false);
+	(player, output)
+}
+
+/// The RGBA values of the pixel at `x`, `y` of `frame`.
+pub fn pixel(frame: &VideoFrame, x: u32, y: u32) -> [u8; 4] {
+	let offset = ((y * frame.width + x) * 4) as usize;
+	let rgba = &frame.data.as_slice()[offset..offset + 4];
+	[rgba[0], rgba[1], rgba[2], rgba[3]]
+}
+
+/// Whether the colours `a` and `b` are the same, give or take the rounding
+/// of the colour conversions.
+pub fn same_colour(a: [u8; 4], b: [u8; 4]) -> bool {
+	a.iter().zip(&b).all(|(a, b)| a.abs_diff(*b) <= 8)
+}
@@ -0,0 +1,100 @@
+//! The HTTP settings end up in the requests of the source.
+mod common;
+
+use std::{
+	io::{BufRead, BufReader, Write},
+	net::TcpListener,
+	sync::mpsc::{self, Receiver},
+	thread,
+};
+
+use druid_video::{player::VideoPlayer, settings::Settings};
+
+/// Serve 404 to every request on a local port, handing the request heads to
+/// the test. Returns the address of the server.
+fn serve() -> (String, Receiver<Vec<String>>) {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let address = listener.local_addr().unwrap().to_string();
+	let (requests, requests_rx) = mpsc::channel();
+	thread::spawn(move || {
+		for stream in listener.incoming() {
+			let mut stream = match stream {
+				Ok(stream) => stream,
+				Err(_) => return,
+			};
+			let head: Vec<String> = BufReader::new(&stream)
+				.lines()
+				.map_while(Result::ok)
+				.take_while(|line| !line.is_empty())
+				.collect();
+			let _ = stream.write_all(
+				b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+			);
+			if requests.send(head).is_err() {
+				return;
+			}
+		}
+	});
+	(address, requests_rx)
+}
+
+/// Open `args` as on the command line and return the first request the
+/// server got.
+fn first_request(args: &[&str], requests: &Receiver<Vec<String>>) -> Vec<String> {
+	let settings = Settings::from_args(args.iter().map(|arg| arg.to_string())).unwrap();
+	let uri = url::Url::parse(settings.item.as_deref().unwrap()).unwrap();
+	let (sink, _output) = common::channel_sink();
+	// There is nothing to play, only the request matters.
+	let _ = VideoPlayer::with_options(&uri, false, settings.options, sink);
+	requests.recv_timeout(common::TIMEOUT).expect("no request")
+}
+
+/// The value of the header `name` in the request `head`.
+fn header<'a>(head: &'a [String], name: &str) -> Option<&'a str> {
+	head.iter().find_map(|line| {
+		let (key, value) = line.split_once(':')?;
+		key.eq_ignore_ascii_case(name).then(|| value.trim())
+	})
+}
+
+#[test]
+fn user_agent_headers_and_cookies() {
+	let (address, requests) = serve();
+	let uri = format!("http://{}/clip.mp4", address);
+	let head = first_request(
+		&[
+			"--user-agent",
+			"druid_video/test",
+			"--header",
+			"X-Token: secret",
+			"--cookie",
+			"session=42",
+			&uri,
+		],
+		&requests,
+	);
+	assert_eq!(head[0], "GET /clip.mp4 HTTP/1.1");
+	assert_eq!(header(&head, "User-Agent"), Some("druid_video/test"));
+	assert_eq!(header(&head, "X-Token"), Some("secret"));
+	assert!(header(&head, "Cookie").unwrap_or_default().contains("session=42"), "{:?}", head);
+}
+
+#[test]
+fn proxy() {
+	let (address, requests) = serve();
+	let proxy = format!("http://{}", address);
+	// Only the proxy knows the way to this host.
+	let head = first_request(&["--proxy", &proxy, "http://media.invalid/live.ts"], &requests);
+	assert_eq!(head[0], "GET http://media.invalid/live.ts HTTP/1.1");
+	assert_eq!(header(&head, "Host"), Some("media.invalid"));
+}
+
+#[test]
+fn settings_file() {
+	let (address, requests) = serve();
+	let path = std::env::temp_dir().join(format!("druid_video_http_{}.conf", std::process::id()));
+	std::fs::write(&path, "# Sent with every request\nuser-agent = from-a-file\n").unwrap();
+	let uri = format!("http://{}/clip.mp4", address);
+	let head = first_request(&["--settings", path.to_str().unwrap(), &uri], &requests);
+	assert_eq!(header(&head, "User-Agent"), Some("from-a-file"));
+}
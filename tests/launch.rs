@@ -0,0 +1,58 @@
+//! `launch:` items play gst-launch descriptions ending in the video appsink.
+mod common;
+
+use common::channel_sink;
+use druid_video::player::{PlayerOptions, VideoError, VideoPlayer};
+
+fn launch(description: &str) -> Result<VideoPlayer, VideoError> {
+	let (sink, _output) = channel_sink();
+	VideoPlayer::from_launch(description, PlayerOptions::default(), sink)
+}
+
+/// The position of the launch error of `description`.
+fn error_position(description: &str) -> Option<usize> {
+	match launch(description) {
+		Err(VideoError::Launch { position, .. }) => position,
+		Err(err) => panic!("{:?} failed with {}", description, err),
+		Ok(_) => panic!("{:?} was played", description),
+	}
+}
+
+#[test]
+fn plays_the_description() {
+	let (sink, output) = channel_sink();
+	let description = "videotestsrc ! video/x-raw,width=64,height=48 ! videoconvert ! appsink \
+		name=videosink";
+	let mut player = VideoPlayer::from_launch(description, PlayerOptions::default(), sink)
+		.expect("failed to launch");
+	player.set_paused(false);
+	let frame = output.frame();
+	assert_eq!((frame.width, frame.height), (64, 48));
+}
+
+#[test]
+fn unknown_elements_are_pointed_at() {
+	assert_eq!(error_position("videotestsrc ! nosuchelement ! appsink name=videosink"), Some(15));
+}
+
+#[test]
+fn broken_links_are_pointed_at() {
+	assert_eq!(error_position("videotestsrc ! ! appsink name=videosink"), Some(15));
+	assert_eq!(error_position("videotestsrc ! appsink name=videosink !"), Some(38));
+}
+
+#[test]
+fn the_video_appsink_is_required() {
+	let description = "videotestsrc ! fakesink";
+	assert_eq!(error_position(description), Some(description.len()));
+	let description = "videotestsrc ! fakesink name=videosink";
+	assert_eq!(error_position(description), Some(description.len()));
+	// A single element isn't even a pipeline.
+	assert_eq!(error_position("videotestsrc  "), Some(12));
+}
+
+#[test]
+fn empty_descriptions_fail() {
+	assert!(matches!(launch(""), Err(VideoError::Launch { .. })));
+	assert!(matches!(launch("   "), Err(VideoError::Launch { .. })));
+}
@@ -0,0 +1,137 @@
+//! Network sources against local stand-ins: a RTP stream of the test
+//! pattern sent with `udpsink`, and a RTSP server which drops its clients.
+mod common;
+
+use std::{
+	io::{BufRead, BufReader},
+	net::{TcpListener, UdpSocket},
+	sync::mpsc::{self, Receiver},
+	thread,
+	time::Duration,
+};
+
+use druid_video::player::{NetworkOptions, PlayerEvent, PlayerOptions, Transport, VideoPlayer};
+use gst::prelude::*;
+use gstreamer as gst;
+
+/// Accept RTSP clients on a local port, handing their first request line to
+/// the test. Clients are dropped right away if `drop_clients` is set, kept
+/// waiting for an answer otherwise.
+fn rtsp_server(drop_clients: bool) -> (u16, Receiver<String>) {
+	let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+	let port = listener.local_addr().unwrap().port();
+	let (requests, requests_rx) = mpsc::channel();
+	thread::spawn(move || {
+		let mut waiting = Vec::new();
+		for stream in listener.incoming().flatten() {
+			let mut line = String::new();
+			let _ = BufReader::new(&stream).read_line(&mut line);
+			if requests.send(line.trim_end().to_string()).is_err() {
+				return;
+			}
+			if !drop_clients {
+				waiting.push(stream);
+			}
+		}
+	});
+	(port, requests_rx)
+}
+
+fn open(uri: &str, options: PlayerOptions) -> (VideoPlayer, common::Output) {
+	let uri = url::Url::parse(uri).unwrap();
+	let (sink, output) = common::channel_sink();
+	let player = VideoPlayer::with_options(&uri, true, options, sink).unwrap();
+	(player, output)
+}
+
+#[test]
+fn rtsp_options_reach_the_source() {
+	let (port, requests) = rtsp_server(false);
+	let options = PlayerOptions {
+		network: NetworkOptions {
+			transport: Transport::Tcp,
+			latency: Duration::from_millis(50),
+			reconnect: false,
+		},
+		..PlayerOptions::default()
+	};
+	let (player, _output) = open(&format!("rtsp://127.0.0.1:{}/test", port), options);
+	let request = requests.recv_timeout(common::TIMEOUT).expect("no RTSP request");
+	assert!(request.starts_with("OPTIONS rtsp://127.0.0.1"), "{}", request);
+
+	let source = player.pipeline.property::<gst::Element>("source");
+	assert_eq!(source.factory().unwrap().name(), "rtspsrc");
+	assert_eq!(source.property::<u32>("latency"), 50);
+	assert!(!source.property::<bool>("do-rtsp-keep-alive"));
+	let protocols = source.property_value("protocols");
+	let class = glib::FlagsClass::new(protocols.type_()).unwrap();
+	assert!(class.is_set_by_nick(&protocols, "tcp"));
+	assert!(!class.is_set_by_nick(&protocols, "udp"));
+}
+
+#[test]
+fn dropped_rtsp_connection_reconnects() {
+	let (port, requests) = rtsp_server(true);
+	let (_player, output) =
+		open(&format!("rtsp://127.0.0.1:{}/test", port), PlayerOptions::default());
+	requests.recv_timeout(common::TIMEOUT).expect("no RTSP request");
+	let event = output.event(|event| matches!(event, PlayerEvent::Reconnecting(_)));
+	assert!(matches!(event, PlayerEvent::Reconnecting(1)), "{:?}", event);
+	// After the backoff the player connects again.
+	requests.recv_timeout(common::TIMEOUT).expect("no second RTSP request");
+}
+
+/// A local UDP port nobody listens on.
+fn free_udp_port() -> u16 {
+	UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+#[test]
+fn rtp_over_udp() {
+	gst::init().unwrap();
+	let port = free_udp_port();
+	// The test pattern, as JPEG over RTP.
+	let sender = gst::parse_launch(&format!(
+		"videotestsrc is-live=true pattern=smpte \
+		 ! video/x-raw,width=160,height=120,framerate=15/1 \
+		 ! jpegenc ! rtpjpegpay ! udpsink host=127.0.0.1 port={}",
+		port
+	))
+	.unwrap();
+	sender.set_state(gst::State::Playing).unwrap();
+
+	let sdp = std::env::temp_dir().join(format!("druid_video_rtp_{}.sdp", std::process::id()));
+	std::fs::write(
+		&sdp,
+		format!(
+			"v=0\r\no=- 0 0 IN IP4 127.0.0.1\r\ns=druid_video\r\nc=IN IP4 127.0.0.1\r\nt=0 0\r\n\
+			 m=video {} RTP/AVP 26\r\na=rtpmap:26 JPEG/90000\r\n",
+			port
+		),
+	)
+	.unwrap();
+	let options = PlayerOptions {
+		network: NetworkOptions {
+			latency: Duration::from_millis(100),
+			..NetworkOptions::default()
+		},
+		..PlayerOptions::default()
+	};
+	let uri = url::Url::from_file_path(&sdp).unwrap();
+	let (mut player, output) = open(uri.as_str(), options);
+	player.set_paused(false);
+
+	let frame = output.frame();
+	assert_eq!((frame.width, frame.height), (160, 120));
+	let bin = player.pipeline.downcast_ref::<gst::Bin>().unwrap();
+	let sdpdemux = bin
+		.iterate_recurse()
+		.into_iter()
+		.flatten()
+		.find(|element| element.factory().map_or(false, |factory| factory.name() == "sdpdemux"))
+		.expect("no sdpdemux");
+	assert_eq!(sdpdemux.property::<u32>("latency"), 100);
+
+	drop(player);
+	sender.set_state(gst::State::Null).unwrap();
+}
@@ -0,0 +1,148 @@
+//! The player core runs without druid, handing its output to any
+//! [`PlayerSink`].
+mod common;
+
+use std::{
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc, Mutex,
+	},
+	thread,
+	time::{Duration, Instant},
+};
+
+use druid_video::player::{
+	Disconnected, PlayerEvent, PlayerOptions, PlayerSink, VideoFrame, VideoPlayer,
+};
+
+/// What a [`Collector`] keeps of a frame.
+#[derive(Debug, Clone, Copy)]
+struct Seen {
+	number: u64,
+	pts: Option<Duration>,
+	size: (u32, u32),
+}
+
+/// Keeps what the player puts out, hanging up after `limit` frames.
+#[derive(Debug, Default)]
+struct Collector {
+	frames: Mutex<Vec<Seen>>,
+	events: Mutex<Vec<PlayerEvent>>,
+	limit: Option<usize>,
+}
+
+impl PlayerSink for Collector {
+	fn frame(&self, frame: VideoFrame) -> Result<(), Disconnected> {
+		let mut frames = self.frames.lock().unwrap();
+		if self.limit.map_or(false, |limit| frames.len() >= limit) {
+			return Err(Disconnected);
+		}
+		let size = (frame.width, frame.height);
+		frames.push(Seen { number: frame.number, pts: frame.pts, size });
+		Ok(())
+	}
+
+	fn event(&self, event: PlayerEvent) -> Result<(), Disconnected> {
+		self.events.lock().unwrap().push(event);
+		Ok(())
+	}
+}
+
+impl Collector {
+	fn frames(&self) -> Vec<Seen> {
+		self.frames.lock().unwrap().clone()
+	}
+
+	/// Wait until the frames `done` says so.
+	fn wait_for(&self, done: impl Fn(&[Seen]) -> bool) -> Vec<Seen> {
+		let deadline = Instant::now() + common::TIMEOUT;
+		loop {
+			let frames = self.frames();
+			if done(&frames) {
+				return frames;
+			}
+			assert!(Instant::now() < deadline, "gave up after {} frames", frames.len());
+			thread::sleep(Duration::from_millis(10));
+		}
+	}
+}
+
+fn play(collector: &Arc<Collector>) -> VideoPlayer {
+	let uri = url::Url::parse("testsrc://ball?width=160&height=120&framerate=30&freq=0").unwrap();
+	let mut player =
+		VideoPlayer::with_options(&uri, false, PlayerOptions::default(), collector.clone())
+			.unwrap();
+	player.set_paused(false);
+	player
+}
+
+#[test]
+fn frames_reach_the_sink() {
+	let collector = Arc::new(Collector::default());
+	let _player = play(&collector);
+	let frames = collector.wait_for(|frames| frames.len() >= 5);
+	assert!(frames.iter().all(|frame| frame.size == (160, 120)));
+	for pair in frames.windows(2) {
+		assert!(pair[0].number < pair[1].number, "{:?}", pair);
+		assert!(pair[0].pts < pair[1].pts, "{:?}", pair);
+	}
+}
+
+#[test]
+fn pausing_stops_the_frames() {
+	let collector = Arc::new(Collector::default());
+	let mut player = play(&collector);
+	collector.wait_for(|frames| frames.len() >= 2);
+	player.set_paused(true);
+	// Frames on their way out when pausing still arrive.
+	thread::sleep(Duration::from_millis(200));
+	let paused = collector.frames().len();
+	thread::sleep(Duration::from_millis(300));
+	assert_eq!(collector.frames().len(), paused);
+	player.set_paused(false);
+	collector.wait_for(|frames| frames.len() > paused);
+}
+
+#[test]
+fn seeking() {
+	let collector = Arc::new(Collector::default());
+	let mut player = play(&collector);
+	collector.wait_for(|frames| !frames.is_empty());
+	player.seek(Duration::from_secs(5)).unwrap();
+	let target = Some(Duration::from_secs(5));
+	collector.wait_for(|frames| frames.last().map_or(false, |frame| frame.pts >= target));
+}
+
+#[test]
+fn hung_up_sink_stops_the_player() {
+	let collector = Arc::new(Collector { limit: Some(3), ..Collector::default() });
+	let _player = play(&collector);
+	collector.wait_for(|frames| frames.len() == 3);
+	thread::sleep(Duration::from_millis(300));
+	assert_eq!(collector.frames().len(), 3);
+	// The player reports why it stopped.
+	assert!(collector
+		.events
+		.lock()
+		.unwrap()
+		.iter()
+		.any(|event| matches!(event, PlayerEvent::Error(_))));
+}
+
+#[test]
+fn frame_taps() {
+	let collector = Arc::new(Collector::default());
+	let player = play(&collector);
+	let tapped = Arc::new(AtomicUsize::new(0));
+	let counter = tapped.clone();
+	player.on_frame(move |frame| {
+		assert_eq!((frame.width(), frame.height()), (160, 120));
+		assert!(frame.stride() >= 160 * 4);
+		counter.fetch_add(1, Ordering::Relaxed);
+	});
+	let start = Instant::now();
+	while tapped.load(Ordering::Relaxed) < 3 {
+		assert!(start.elapsed() < common::TIMEOUT, "the tap got no frames");
+		thread::sleep(Duration::from_millis(10));
+	}
+}
@@ -0,0 +1,164 @@
+//! Recording the encoded streams of a pipeline, to a file and to the
+//! rotating segments of a timeshift buffer.
+//!
+//! `testsrc://` plays raw video, which has no parser to record from, so the
+//! test pattern is encoded and parsed here the way playbin would.
+mod common;
+
+use std::{
+	fs,
+	path::{Path, PathBuf},
+	thread,
+	time::{Duration, Instant},
+};
+
+use druid_video::record::{self, Progress, RecordingId, SharedStreams, Target};
+use gst::prelude::*;
+use gstreamer as gst;
+
+/// A playing pipeline of the encoded test pattern, with its streams watched
+/// for recording.
+struct Source {
+	pipeline: gst::Pipeline,
+	streams: SharedStreams,
+}
+
+impl Source {
+	fn new() -> Self {
+		gst::init().unwrap();
+		let pipeline = gst::Pipeline::new(None);
+		let streams = record::connect(pipeline.upcast_ref());
+		let make = |name: &str| gst::ElementFactory::make(name, None).unwrap();
+		let source = make("videotestsrc");
+		source.set_property("is-live", true);
+		let raw = make("capsfilter");
+		raw.set_property(
+			"caps",
+			gst::Caps::builder("video/x-raw")
+				.field("width", 160i32)
+				.field("height", 120i32)
+				.field("framerate", gst::Fraction::new(30, 1))
+				.build(),
+		);
+		let encoder = make("x264enc");
+		encoder.set_property_from_str("tune", "zerolatency");
+		encoder.set_property_from_str("speed-preset", "ultrafast");
+		// A keyframe every half second, so the segments can be split often.
+		encoder.set_property("key-int-max", 15u32);
+		let parser = make("h264parse");
+		// Matroska takes H.264 in AVC form.
+		let avc = make("capsfilter");
+		avc.set_property(
+			"caps",
+			gst::Caps::builder("video/x-h264")
+				.field("stream-format", "avc")
+				.field("alignment", "au")
+				.build(),
+		);
+		let sink = make("fakesink");
+		// Added one by one, so the streams see the parser being added.
+		let elements = [&source, &raw, &encoder, &parser, &avc, &sink];
+		pipeline.add_many(&elements).unwrap();
+		gst::Element::link_many(&elements).unwrap();
+		pipeline.set_state(gst::State::Playing).unwrap();
+		Self { pipeline, streams }
+	}
+
+	/// Start recording once the parser knows its caps.
+	fn record(&self, target: Target) -> RecordingId {
+		wait_for("the parser", || self.streams.lock().unwrap().start(target.clone()).ok())
+	}
+
+	fn progress(&self, id: RecordingId) -> Option<Progress> {
+		self.streams.lock().unwrap().progress(id)
+	}
+}
+
+impl Drop for Source {
+	fn drop(&mut self) {
+		let _ = self.pipeline.set_state(gst::State::Null);
+	}
+}
+
+/// Poll `check` until it returns something, for up to the test timeout.
+fn wait_for<T>(what: &str, mut check: impl FnMut() -> Option<T>) -> T {
+	let deadline = Instant::now() + common::TIMEOUT;
+	loop {
+		if let Some(value) = check() {
+			return value;
+		}
+		assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+		thread::sleep(Duration::from_millis(20));
+	}
+}
+
+/// A fresh directory for the files of test `name`.
+fn temp_dir(name: &str) -> PathBuf {
+	let dir = std::env::temp_dir().join(format!("druid_video_{}_{}", name, std::process::id()));
+	let _ = fs::remove_dir_all(&dir);
+	dir
+}
+
+fn files_in(dir: &Path) -> Vec<PathBuf> {
+	fs::read_dir(dir).map_or_else(
+		|_| Vec::new(),
+		|entries| entries.flatten().map(|entry| entry.path()).collect(),
+	)
+}
+
+#[test]
+fn records_to_a_file() {
+	let dir = temp_dir("record_file");
+	fs::create_dir_all(&dir).unwrap();
+	let path = dir.join("recording.mkv");
+	let source = Source::new();
+	let id = source.record(Target::File(path.clone()));
+
+	let progress = wait_for("a second of recording", || {
+		source.progress(id).filter(|progress| progress.end >= Duration::from_secs(1))
+	});
+	assert!(!progress.finished);
+	assert!(progress.fragments.is_empty());
+	assert!(!progress.audio);
+
+	source.streams.lock().unwrap().stop(id);
+	assert_eq!(source.progress(id), None);
+	// The muxer drains in the background, then the file starts with the
+	// EBML header of Matroska.
+	let data = wait_for("the file", || fs::read(&path).ok().filter(|data| data.len() > 1024));
+	assert_eq!(&data[..4], &[0x1a, 0x45, 0xdf, 0xa3]);
+	let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn segments_rotate() {
+	let dir = temp_dir("record_segments");
+	let source = Source::new();
+	let max_files = 2;
+	let target =
+		Target::Segments { dir: dir.clone(), segment: Duration::from_millis(500), max_files };
+	let id = source.record(target);
+
+	// Four segments closed, so the oldest files were reused twice.
+	let progress = wait_for("the segments", || {
+		source.progress(id).filter(|progress| progress.end >= Duration::from_millis(2500))
+	});
+	assert!(!progress.finished);
+	assert!(!progress.fragments.is_empty());
+	assert!(progress.fragments.len() <= max_files as usize, "{:?}", progress.fragments);
+	for pair in progress.fragments.windows(2) {
+		assert!(pair[0].end <= pair[1].start, "{:?}", pair);
+	}
+	for fragment in &progress.fragments {
+		assert!(fragment.start < fragment.end, "{:?}", fragment);
+		assert!(fragment.path.starts_with(&dir) && fragment.path.exists(), "{:?}", fragment);
+	}
+	// Only the newest files are kept, the one being written included.
+	let files = files_in(&dir);
+	assert!(!files.is_empty() && files.len() <= max_files as usize, "{:?}", files);
+
+	// Stopping finishes the recording, which removes its directory.
+	source.streams.lock().unwrap().stop(id);
+	assert_eq!(source.progress(id), None);
+	wait_for("the directory to be removed", || Some(()).filter(|_| !dir.exists()));
+}
@@ -0,0 +1,73 @@
+//! `testsrc://` URIs play the configured pattern at the configured size.
+mod common;
+
+use common::{pixel, play_test_source, same_colour};
+use druid_video::player::{PlayerOptions, VideoPlayer};
+
+#[test]
+fn frame_size() {
+	let (_player, output) = play_test_source("testsrc://ball?width=320&height=180&freq=0");
+	let frame = output.frame();
+	assert_eq!((frame.width, frame.height), (320, 180));
+	assert_eq!(frame.data.as_slice().len(), 320 * 180 * 4);
+	assert_eq!(frame.pixel_aspect_ratio, (1, 1));
+}
+
+#[test]
+fn smpte_bars() {
+	let (_player, output) = play_test_source("testsrc://smpte?width=350&height=200&freq=0");
+	let frame = output.frame();
+	// Seven bars of 75% intensity across the top, 50 pixels each.
+	let bar = |index: u32| pixel(&frame, index * 50 + 25, 10);
+	let expected = [
+		[191, 191, 191, 255],
+		[191, 191, 0, 255],
+		[0, 191, 191, 255],
+		[0, 191, 0, 255],
+		[191, 0, 191, 255],
+		[191, 0, 0, 255],
+		[0, 0, 191, 255],
+	];
+	for (index, colour) in expected.into_iter().enumerate() {
+		let shown = bar(index as u32);
+		assert!(same_colour(shown, colour), "bar {} is {:?}, not {:?}", index, shown, colour);
+	}
+}
+
+#[test]
+fn checkers() {
+	let (_player, output) = play_test_source("testsrc://checkers?width=64&height=64&freq=0");
+	let frame = output.frame();
+	// Squares of 8 by 8 pixels.
+	let square = |x: u32, y: u32| pixel(&frame, x * 8 + 4, y * 8 + 4);
+	let first = square(0, 0);
+	for (x, y) in [(1, 0), (0, 1), (7, 6)] {
+		assert!(!same_colour(square(x, y), first), "square {}, {}", x, y);
+	}
+	for (x, y) in [(1, 1), (2, 0), (7, 7)] {
+		assert!(same_colour(square(x, y), first), "square {}, {}", x, y);
+	}
+}
+
+#[test]
+fn frames_keep_coming() {
+	let (_player, output) =
+		play_test_source("testsrc://zone-plate?width=64&height=64&framerate=60&freq=0");
+	let first = output.frame().number;
+	let later = (0..3).map(|_| output.frame().number).last().unwrap();
+	assert!(later > first);
+}
+
+#[test]
+fn invalid_uris() {
+	for uri in [
+		"testsrc://snow",
+		"testsrc://smpte?width=0",
+		"testsrc://smpte?framerate=30/0",
+		"testsrc://smpte?brightness=2",
+	] {
+		let (sink, _output) = common::channel_sink();
+		let uri = url::Url::parse(uri).unwrap();
+		assert!(VideoPlayer::with_options(&uri, false, PlayerOptions::default(), sink).is_err());
+	}
+}